@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{DataValue, Span};
+
+/// How a substack was called: the label it was invoked through, if any, and
+/// where the invocation happened, if known.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Call {
+    pub callee: Option<String>,
+    pub call_site: Option<Span>,
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.callee.as_deref().unwrap_or("<substack>"))?;
+        if let Some(span) = self.call_site {
+            write!(f, ", called at {}", span)?;
+        }
+        Ok(())
+    }
+}
+
+/// An active substack call, pushed by `PushScope` and popped by `PopScope`.
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    pub call: Call,
+    /// The caller's local scope, put back when the call returns.
+    pub saved_scope: HashMap<String, DataValue>,
+}
+
+/// The substack calls that were active when an error was raised, innermost
+/// first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Backtrace {
+    pub calls: Vec<Call>,
+}
+
+impl Backtrace {
+    /// The calls in `frames` (a scope stack, outermost first), innermost first.
+    /// Calls with neither a callee nor a call site (e.g. a program invoked
+    /// directly through the library API) say nothing useful and are left out.
+    pub fn of(frames: &[Frame]) -> Self {
+        Self {
+            calls: frames
+                .iter()
+                .rev()
+                .map(|frame| &frame.call)
+                .filter(|call| call.callee.is_some() || call.call_site.is_some())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+}
+
+/// One numbered line per call, innermost first.
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, call) in self.calls.iter().enumerate() {
+            writeln!(f, "  {}: {}", i, call)?;
+        }
+        Ok(())
+    }
+}
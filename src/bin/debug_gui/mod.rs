@@ -1,4 +1,4 @@
-use crate::HashMap;
+use std::collections::HashMap;
 
 use eframe::egui;
 
@@ -8,26 +8,19 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::compile;
-use sid::get_built_in_functions;
-use sid::interpret_one;
-use sid::DataValue;
-use sid::GlobalState;
-use sid::ProgramValue;
-use sid::TemplateValue;
-use sid::ToSyntax;
-
-/// Interpreter state stored flat to avoid lifetime issues with ExeState<'a>.
-struct DebugState {
-    program_stack: Vec<ProgramValue>,
-    data_stack: Vec<TemplateValue>,
-    local_scope: HashMap<String, DataValue>,
-    scope_stack: Vec<HashMap<String, DataValue>>,
-    global_scope: HashMap<String, DataValue>,
-}
+use sid::{Breakpoint, DataValue, Debugger, Resume, ToSyntax};
+
+/// How many program values a resume command runs per frame before letting
+/// the UI redraw, so a long or endless run can still be paused.
+const STEPS_PER_FRAME: usize = 10_000;
 
 pub struct SidDebuggerApp {
-    debug_state: Option<DebugState>,
+    debugger: Debugger,
+    loaded: bool,
+    load_error: Option<String>,
+    new_breakpoint: String,
+    breakpoint_error: Option<String>,
+    new_watch: String,
 
     opened_file: Option<PathBuf>,
     open_file_dialog: Option<FileDialog>,
@@ -36,11 +29,69 @@ pub struct SidDebuggerApp {
 impl SidDebuggerApp {
     pub fn new() -> Self {
         Self {
-            debug_state: None,
+            debugger: Debugger::new(),
+            loaded: false,
+            load_error: None,
+            new_breakpoint: String::new(),
+            breakpoint_error: None,
+            new_watch: String::new(),
             opened_file: None,
             open_file_dialog: None,
         }
     }
+
+    /// Load `file` into the debugger, keeping breakpoints and watches.
+    fn open(&mut self, file: PathBuf) {
+        let loaded = std::fs::read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| self.debugger.load(&source));
+        match loaded {
+            Ok(()) => {
+                self.loaded = true;
+                self.load_error = None;
+            }
+            Err(e) => {
+                self.loaded = false;
+                self.load_error = Some(format!("{:#}", e));
+            }
+        }
+        self.opened_file = Some(file);
+    }
+
+    /// A button that starts `resume`, also triggered by `key` (with `shift`).
+    fn resume_button(
+        &mut self,
+        ui: &mut egui::Ui,
+        label: &str,
+        key: egui::Key,
+        shift: bool,
+        resume: Resume,
+    ) {
+        let enabled = self.loaded && !self.debugger.is_running() && !self.debugger.is_finished();
+        let pressed = ui.input(|i| i.key_pressed(key) && i.modifiers.shift == shift);
+        if ui.add_enabled(enabled, egui::Button::new(label)).clicked() || (enabled && pressed) {
+            self.debugger.start(resume);
+        }
+    }
+}
+
+/// A titled, independently scrolling column.
+fn stack_column(ui: &mut egui::Ui, title: &str, add_contents: impl FnOnce(&mut egui::Ui)) {
+    ui.heading(title);
+    ui.separator();
+    egui::ScrollArea::vertical()
+        .id_source(title)
+        .auto_shrink([false, false])
+        .show(ui, add_contents);
+}
+
+/// One line per binding, sorted by name.
+fn scope_labels(ui: &mut egui::Ui, scope: &HashMap<String, DataValue>) {
+    let mut entries: Vec<_> = scope.iter().collect();
+    entries.sort_by_key(|(name, _)| *name);
+    for (name, value) in entries {
+        ui.label(format!("{} = {}", name, value.to_syntax()));
+    }
 }
 
 impl eframe::App for SidDebuggerApp {
@@ -67,98 +118,170 @@ impl eframe::App for SidDebuggerApp {
                 }
 
                 if ui.button("Reset").clicked() {
-                    *self = Self::new();
-                }
-                if ui.button("Step").clicked() || ui.input(|i| i.key_pressed(egui::Key::F10)) {
-                    if let Some(s) = &mut self.debug_state {
-                        let mut global_state = GlobalState::new(&mut s.global_scope);
-                        interpret_one(
-                            &mut s.data_stack,
-                            &mut s.program_stack,
-                            &mut s.local_scope,
-                            &mut s.scope_stack,
-                            &mut global_state,
-                            &get_built_in_functions(),
-                        );
+                    match self.opened_file.clone() {
+                        Some(file) => self.open(file),
+                        None => *self = Self::new(),
                     }
                 }
+
+                self.resume_button(ui, "Step into", egui::Key::F11, false, Resume::StepInto);
+                self.resume_button(ui, "Step over", egui::Key::F10, false, Resume::StepOver);
+                self.resume_button(ui, "Step out", egui::Key::F11, true, Resume::StepOut);
+                self.resume_button(ui, "Continue", egui::Key::F5, false, Resume::Continue);
+                if ui
+                    .add_enabled(self.debugger.is_running(), egui::Button::new("Pause"))
+                    .clicked()
+                {
+                    self.debugger.pause();
+                }
             });
 
             if let Some(dialog) = &mut self.open_file_dialog {
                 if dialog.show(ctx).selected() {
                     if let Some(file) = dialog.path() {
-                        self.opened_file = Some(file.to_path_buf());
-                        let file_content =
-                            std::fs::read_to_string(self.opened_file.as_ref().unwrap())
-                                .expect("Failed to read file");
-
-                        let program = compile(&file_content);
-                        self.debug_state = Some(DebugState {
-                            program_stack: vec![ProgramValue::Invoke],
-                            data_stack: program.instructions,
-                            local_scope: HashMap::new(),
-                            scope_stack: Vec::new(),
-                            global_scope: program.global_scope,
-                        });
+                        let file = file.to_path_buf();
+                        self.open(file);
                     }
                 }
             }
         });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let mut painter_size = ui.available_size_before_wrap();
-            if !painter_size.is_finite() {
-                painter_size = egui::vec2(500.0, 500.0);
-            }
+        if self.debugger.is_running() {
+            // The error, if any, is shown from `Debugger::error` below.
+            let _ = self.debugger.advance(STEPS_PER_FRAME);
+            ctx.request_repaint();
+        }
 
-            let Some(s) = self.debug_state.as_ref() else {
+        egui::TopBottomPanel::bottom("status_panel").show(ctx, |ui| {
+            if let Some(file) = &self.opened_file {
+                ui.label(format!("File: {}", file.display()));
+            }
+            if let Some(e) = &self.load_error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            if !self.loaded {
                 return;
+            }
+            match self.debugger.span() {
+                Some(span) => ui.label(format!("At: {}", span)),
+                None => ui.label("At: <unknown>"),
             };
+            if let Some(e) = self.debugger.error() {
+                let mut text = format!("error: {}", e);
+                if let Some(backtrace) = e.backtrace() {
+                    text = format!(
+                        "{}\nbacktrace (most recent call first):\n{}",
+                        text, backtrace
+                    );
+                }
+                ui.colored_label(ui.visuals().error_fg_color, text);
+            } else if self.debugger.is_finished() {
+                ui.label("Finished");
+            }
+        });
 
-            // Display stuff
-            ui.label(format!("File: {:?}", self.opened_file.as_ref().unwrap()));
-            ui.horizontal(|ui| {
-                ui.vertical(|ui| {
-                    ui.label("program stack");
-                    ui.label("-------------");
-                    for (i, program_value) in s.program_stack.iter().enumerate() {
-                        ui.label(format!("{}: {}", i, program_value.to_syntax()));
+        egui::SidePanel::right("breakpoints_panel").show(ctx, |ui| {
+            ui.heading("breakpoints");
+            ui.separator();
+            let mut remove = None;
+            for (i, breakpoint) in self.debugger.breakpoints.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
                     }
+                    ui.label(breakpoint.to_string());
                 });
-                ui.vertical(|ui| {
-                    for _data in 0..10 {
-                        ui.label("|");
+            }
+            if let Some(i) = remove {
+                self.debugger.breakpoints.remove(i);
+            }
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.new_breakpoint)
+                    .hint_text("LINE, LINE:COL, label or builtin NAME"),
+            );
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match self.new_breakpoint.parse::<Breakpoint>() {
+                    Ok(breakpoint) => {
+                        self.debugger.breakpoints.push(breakpoint);
+                        self.new_breakpoint.clear();
+                        self.breakpoint_error = None;
                     }
-                });
-                ui.vertical(|ui| {
-                    ui.label("data stack");
-                    ui.label("-------------");
-                    for (i, data) in s.data_stack.iter().enumerate() {
-                        ui.label(format!("{}: {}", i, data.to_syntax()));
+                    Err(e) => self.breakpoint_error = Some(e),
+                }
+            }
+            if let Some(e) = &self.breakpoint_error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+
+            ui.add_space(12.0);
+            ui.heading("watches");
+            ui.separator();
+            let mut remove = None;
+            for (i, (watch, values)) in self.debugger.watch_values().into_iter().enumerate() {
+                let value = match values {
+                    Ok(values) => values
+                        .iter()
+                        .map(|v| v.to_syntax().trim_end().to_owned())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    Err(e) => format!("error: {}", e),
+                };
+                ui.horizontal(|ui| {
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
                     }
+                    ui.label(format!("{} = {}", watch, value));
                 });
-                ui.vertical(|ui| {
-                    for _data in 0..10 {
-                        ui.label("|");
+            }
+            if let Some(i) = remove {
+                self.debugger.watches.remove(i);
+            }
+            let response =
+                ui.add(egui::TextEdit::singleline(&mut self.new_watch).hint_text("sid expression"));
+            if response.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                && !self.new_watch.trim().is_empty()
+            {
+                self.debugger
+                    .watches
+                    .push(std::mem::take(&mut self.new_watch));
+            }
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if !self.loaded {
+                return;
+            }
+            let d = &self.debugger;
+
+            ui.columns(5, |columns| {
+                stack_column(&mut columns[0], "program stack", |ui| {
+                    for (i, program_value) in d.program_stack().iter().enumerate() {
+                        ui.label(format!("{}: {}", i, program_value.to_syntax()));
                     }
                 });
-                ui.vertical(|ui| {
-                    ui.label("local scope");
-                    ui.label("-------------");
-                    for (key, _data) in s.local_scope.iter() {
-                        ui.label(format!("{:?}", key));
+                stack_column(&mut columns[1], "data stack", |ui| {
+                    for (i, data) in d.data_stack().iter().enumerate() {
+                        ui.label(format!("{}: {}", i, data.to_syntax()));
                     }
                 });
-                ui.vertical(|ui| {
-                    for _data in 0..10 {
-                        ui.label("|");
+                stack_column(&mut columns[2], "local scope", |ui| {
+                    scope_labels(ui, d.local_scope());
+                });
+                stack_column(&mut columns[3], "scope stack", |ui| {
+                    for (i, frame) in d.scope_stack().iter().enumerate() {
+                        egui::CollapsingHeader::new(format!("{}: {}", i, frame.call))
+                            .id_source(("frame", i))
+                            .show(ui, |ui| scope_labels(ui, &frame.saved_scope));
                     }
                 });
-                ui.vertical(|ui| {
-                    ui.label("global scope");
-                    ui.label("-------------");
-                    for (key, _data) in s.global_scope.iter() {
-                        ui.label(format!("{:?}", key));
+                stack_column(&mut columns[4], "global scope", |ui| {
+                    let mut names: Vec<_> = d.global_scope().keys().collect();
+                    names.sort();
+                    for name in names {
+                        egui::CollapsingHeader::new(name)
+                            .id_source(("global", name))
+                            .show(ui, |ui| ui.label(d.global_scope()[name].to_syntax()));
                     }
                 });
             });
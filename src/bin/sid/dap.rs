@@ -0,0 +1,491 @@
+//! `sid dap`: a Debug Adapter Protocol server on stdin and stdout, so editors
+//! can debug sid programs through [`Debugger`].
+//!
+//! There is one thread.  Stack frame 0 is where the program is paused and
+//! each active substack call adds the frame it was called from.  Every frame
+//! has its local scope as a variable scope; the data stack and the global
+//! scope are shown alongside.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::TryRecvError;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use sid::*;
+
+use crate::message;
+
+/// How many program values run between checks for new requests, so a
+/// `pause` gets through to a runaway program.
+const STEPS_PER_SLICE: usize = 10_000;
+
+const THREAD_ID: i64 = 1;
+
+/// `variablesReference`s: the data stack, the global scope, and the local
+/// scope of frame `n` at `LOCALS + n`.
+const DATA_STACK: i64 = 1;
+const GLOBALS: i64 = 2;
+const LOCALS: i64 = 3;
+
+pub fn run() -> anyhow::Result<()> {
+    let messages = message::spawn_stdin_reader();
+    let mut server = Server::new();
+    loop {
+        let request = if server.debugger.is_running() {
+            server.advance()?;
+            match messages.try_recv() {
+                Ok(request) => request,
+                Err(TryRecvError::Empty) => continue,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        } else {
+            match messages.recv() {
+                Ok(request) => request,
+                Err(_) => return Ok(()),
+            }
+        };
+        if !server.handle(&request)? {
+            return Ok(());
+        }
+    }
+}
+
+/// What the program wrote to stdout and stderr, waiting to be sent as
+/// `output` events.  Stdin and stdout belong to the protocol, so the
+/// program reads an empty stdin; everything else is real.
+#[derive(Clone, Default)]
+struct CapturedOutput {
+    pending: Arc<Mutex<Vec<(&'static str, String)>>>,
+}
+
+impl CapturedOutput {
+    fn push(&self, category: &'static str, bytes: &[u8]) {
+        let text = String::from_utf8_lossy(bytes).into_owned();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((category, text));
+    }
+
+    fn take(&self) -> Vec<(&'static str, String)> {
+        std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl SideEffects for CapturedOutput {
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.push("stdout", bytes);
+        Ok(())
+    }
+
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.push("stderr", bytes);
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        Ok(String::new())
+    }
+
+    fn read_all_stdin(&mut self) -> io::Result<Vec<u8>> {
+        Ok(Vec::new())
+    }
+
+    fn read_file(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        RealSideEffects.read_file(path)
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        RealSideEffects.write_file(path, contents)
+    }
+
+    fn append_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        RealSideEffects.append_file(path, contents)
+    }
+
+    fn exists(&mut self, path: &Path) -> bool {
+        RealSideEffects.exists(path)
+    }
+
+    fn list_dir(&mut self, path: &Path) -> io::Result<Vec<String>> {
+        RealSideEffects.list_dir(path)
+    }
+
+    fn now(&mut self) -> std::time::SystemTime {
+        RealSideEffects.now()
+    }
+
+    fn random_u64(&mut self) -> u64 {
+        RealSideEffects.random_u64()
+    }
+
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        RealSideEffects.env_var(name)
+    }
+
+    fn open_library(&mut self, path: &str) -> anyhow::Result<Arc<libloading::Library>> {
+        RealSideEffects.open_library(path)
+    }
+}
+
+struct Server {
+    debugger: Debugger,
+    output: CapturedOutput,
+    /// Path of the launched program, as the client gave it.
+    program: Option<String>,
+    stop_on_entry: bool,
+    configured: bool,
+    started: bool,
+    seq: i64,
+    stdout: io::Stdout,
+}
+
+impl Server {
+    fn new() -> Self {
+        let output = CapturedOutput::default();
+        Self {
+            debugger: Debugger::with_side_effects(Box::new(output.clone())),
+            output,
+            program: None,
+            stop_on_entry: false,
+            configured: false,
+            started: false,
+            seq: 0,
+            stdout: io::stdout(),
+        }
+    }
+
+    fn send(&mut self, mut message: Value) -> anyhow::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        message::write(&mut self.stdout.lock(), &message)?;
+        Ok(())
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> anyhow::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) -> anyhow::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response)
+    }
+
+    /// Answer `request`.  Returns `false` once the client has disconnected.
+    fn handle(&mut self, request: &Value) -> anyhow::Result<bool> {
+        let arguments = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+        let result = match command {
+            "initialize" => {
+                self.respond(
+                    request,
+                    Ok(json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsFunctionBreakpoints": true,
+                        "supportsEvaluateForHovers": true,
+                    })),
+                )?;
+                self.event("initialized", json!({}))?;
+                return Ok(true);
+            }
+            "launch" => {
+                let result = self.launch(arguments);
+                self.respond(request, result)?;
+                self.start()?;
+                return Ok(true);
+            }
+            "setBreakpoints" => Ok(self.set_breakpoints(arguments)),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(request, Ok(json!({})))?;
+                self.start()?;
+                return Ok(true);
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(self.scopes(arguments)),
+            "variables" => Ok(self.variables(arguments)),
+            "evaluate" => self.evaluate(arguments),
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                self.respond(request, Ok(json!({ "allThreadsContinued": true })))?;
+                let resume = match command {
+                    "continue" => Resume::Continue,
+                    "next" => Resume::StepOver,
+                    "stepIn" => Resume::StepInto,
+                    _ => Resume::StepOut,
+                };
+                self.resume(resume)?;
+                return Ok(true);
+            }
+            "pause" => {
+                self.respond(request, Ok(json!({})))?;
+                if self.debugger.is_running() {
+                    self.debugger.pause();
+                    self.stopped("pause", None)?;
+                }
+                return Ok(true);
+            }
+            "terminate" => {
+                self.respond(request, Ok(json!({})))?;
+                self.debugger.pause();
+                self.event("terminated", json!({}))?;
+                return Ok(true);
+            }
+            "disconnect" => {
+                self.respond(request, Ok(json!({})))?;
+                return Ok(false);
+            }
+            other => Err(format!("unsupported request '{}'", other)),
+        };
+        self.respond(request, result)?;
+        Ok(true)
+    }
+
+    fn launch(&mut self, arguments: &Value) -> Result<Value, String> {
+        let Some(program) = arguments["program"].as_str() else {
+            return Err("launch needs a 'program' path".to_owned());
+        };
+        let source = std::fs::read_to_string(program)
+            .map_err(|e| format!("failed to read {}: {}", program, e))?;
+        if arguments["sandbox"].as_bool() == Some(true) {
+            self.debugger.sandbox = SandboxPolicy::deny_all();
+        }
+        self.debugger.load(&source).map_err(|e| {
+            let location = e
+                .downcast_ref::<SidError>()
+                .and_then(SidError::span)
+                .map(|span| format!("{}:{}: ", program, span))
+                .unwrap_or_default();
+            format!("{}{:#}", location, e)
+        })?;
+        self.program = Some(program.to_owned());
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool() == Some(true);
+        Ok(json!({}))
+    }
+
+    /// Start running once the program is loaded and the client has sent its
+    /// breakpoints.
+    fn start(&mut self) -> anyhow::Result<()> {
+        if self.started || !self.configured || self.program.is_none() {
+            return Ok(());
+        }
+        self.started = true;
+        if self.stop_on_entry {
+            self.stopped("entry", None)
+        } else {
+            self.resume(Resume::Continue)
+        }
+    }
+
+    fn resume(&mut self, resume: Resume) -> anyhow::Result<()> {
+        if self.debugger.error().is_some() {
+            // Nothing left to run after an exception.
+            return self.exited(1);
+        }
+        self.debugger.start(resume);
+        Ok(())
+    }
+
+    /// Run a slice of the current command and report how it ended.
+    fn advance(&mut self) -> anyhow::Result<()> {
+        let result = self.debugger.advance(STEPS_PER_SLICE);
+        for (category, text) in self.output.take() {
+            self.event("output", json!({ "category": category, "output": text }))?;
+        }
+        match result {
+            Ok(Stop::Running) => Ok(()),
+            Ok(Stop::Paused) => self.stopped("step", None),
+            Ok(Stop::Breakpoint(_)) => self.stopped("breakpoint", None),
+            Ok(Stop::Finished) => self.exited(0),
+            Err(e) => {
+                let mut text = format!("error: {}\n", e);
+                if let Some(backtrace) = e.backtrace() {
+                    text = format!("{}backtrace (most recent call first):\n{}", text, backtrace);
+                }
+                self.event("output", json!({ "category": "stderr", "output": text }))?;
+                self.stopped("exception", Some(e.to_string()))
+            }
+        }
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<String>) -> anyhow::Result<()> {
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if let Some(text) = text {
+            body["description"] = json!(text);
+            body["text"] = json!(text);
+        }
+        self.event("stopped", body)
+    }
+
+    fn exited(&mut self, exit_code: i64) -> anyhow::Result<()> {
+        self.event("exited", json!({ "exitCode": exit_code }))?;
+        self.event("terminated", json!({}))
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Value {
+        self.debugger
+            .breakpoints
+            .retain(|b| !matches!(b, Breakpoint::Location { .. }));
+        let mut verified = Vec::new();
+        for breakpoint in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let Some(line) = breakpoint["line"].as_u64() else {
+                continue;
+            };
+            let col = breakpoint["column"].as_u64().map(|col| col as usize);
+            self.debugger.breakpoints.push(Breakpoint::Location {
+                line: line as usize,
+                col,
+            });
+            verified.push(json!({ "verified": true, "line": line, "column": col }));
+        }
+        json!({ "breakpoints": verified })
+    }
+
+    /// Function breakpoints take the same names as `sid debug`'s `break`:
+    /// a label, or `builtin NAME`.
+    fn set_function_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        self.debugger
+            .breakpoints
+            .retain(|b| matches!(b, Breakpoint::Location { .. }));
+        let mut verified = Vec::new();
+        for breakpoint in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let name = breakpoint["name"].as_str().unwrap_or_default();
+            match name.parse::<Breakpoint>() {
+                Ok(breakpoint @ (Breakpoint::Label(_) | Breakpoint::Builtin(_))) => {
+                    self.debugger.breakpoints.push(breakpoint);
+                    verified.push(json!({ "verified": true }));
+                }
+                Ok(_) => {
+                    verified.push(json!({ "verified": false, "message": "not a function name" }))
+                }
+                Err(e) => verified.push(json!({ "verified": false, "message": e })),
+            }
+        }
+        Ok(json!({ "breakpoints": verified }))
+    }
+
+    fn stack_trace(&self) -> Value {
+        let calls: Vec<&Frame> = self.debugger.scope_stack().iter().rev().collect();
+        let source = self.program.as_ref().map(|path| {
+            let name = Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            json!({ "name": name, "path": path })
+        });
+        let frames: Vec<Value> = (0..=calls.len())
+            .map(|i| {
+                let name = match calls.get(i) {
+                    Some(frame) => frame.call.callee.as_deref().unwrap_or("<substack>"),
+                    None => "<main>",
+                };
+                let span = match i {
+                    0 => self.debugger.span(),
+                    _ => calls[i - 1].call.call_site,
+                };
+                json!({
+                    "id": i,
+                    "name": name,
+                    "source": source,
+                    "line": span.map_or(0, |span| span.line),
+                    "column": span.map_or(0, |span| span.col),
+                })
+            })
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn scopes(&self, arguments: &Value) -> Value {
+        let frame = arguments["frameId"].as_i64().unwrap_or(0);
+        json!({ "scopes": [
+            { "name": "Locals", "variablesReference": LOCALS + frame, "expensive": false },
+            { "name": "Data stack", "variablesReference": DATA_STACK, "expensive": false },
+            { "name": "Globals", "variablesReference": GLOBALS, "expensive": true },
+        ]})
+    }
+
+    fn variables(&self, arguments: &Value) -> Value {
+        let reference = arguments["variablesReference"].as_i64().unwrap_or(0);
+        let variables: Vec<Value> = match reference {
+            DATA_STACK => self
+                .debugger
+                .data_stack()
+                .iter()
+                .enumerate()
+                .map(|(i, value)| variable(&i.to_string(), &value.to_syntax()))
+                .collect(),
+            GLOBALS => scope_variables(self.debugger.global_scope()),
+            locals => match self.debugger.frame_scope((locals - LOCALS) as usize) {
+                Some(scope) => scope_variables(scope),
+                None => Vec::new(),
+            },
+        };
+        json!({ "variables": variables })
+    }
+
+    /// Evaluate in the given frame, or the innermost one.  A label that ends
+    /// up on the stack shows the value bound to it, as a hover should.
+    fn evaluate(&self, arguments: &Value) -> Result<Value, String> {
+        let expression = arguments["expression"].as_str().unwrap_or_default();
+        let frame = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+        let values = self
+            .debugger
+            .evaluate_in(expression, frame)
+            .map_err(|e| format!("{:#}", e))?;
+        let local_scope = self.debugger.frame_scope(frame);
+        let shown: Vec<String> = values
+            .iter()
+            .map(|value| {
+                let bound = match value {
+                    DataValue::Label(name) => local_scope
+                        .and_then(|scope| scope.get(name))
+                        .or_else(|| self.debugger.global_scope().get(name)),
+                    _ => None,
+                };
+                one_line(&bound.unwrap_or(value).to_syntax())
+            })
+            .collect();
+        Ok(json!({ "result": shown.join(" "), "variablesReference": 0 }))
+    }
+}
+
+fn scope_variables(scope: &HashMap<String, DataValue>) -> Vec<Value> {
+    let mut entries: Vec<_> = scope.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+        .into_iter()
+        .map(|(name, value)| variable(name, &value.to_syntax()))
+        .collect()
+}
+
+fn variable(name: &str, syntax: &str) -> Value {
+    json!({ "name": name, "value": one_line(syntax), "variablesReference": 0 })
+}
+
+/// `to_syntax` output puts container items on lines of their own; the
+/// variables view wants one line.
+fn one_line(syntax: &str) -> String {
+    syntax
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
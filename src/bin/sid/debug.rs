@@ -0,0 +1,165 @@
+//! `sid debug`: step through a program from the terminal, one command per
+//! line on stdin.
+
+use std::io::BufRead;
+
+use sid::*;
+
+const HELP: &str = "\
+step, s             run to the next instruction, entering calls
+next, n             run to the next instruction in this call
+out, o              run until this call returns
+continue, c         run until a breakpoint or the end
+break, b <where>    pause at LINE, LINE:COL, a label being invoked, or
+                    `builtin NAME`
+delete, d <n>       remove breakpoint n
+breakpoints         list breakpoints
+watch, w <expr>     evaluate expr at every pause
+unwatch <n>         remove watch n
+print, p <expr>     evaluate expr once
+stack               print the data stack
+locals              print the local scope
+backtrace, bt       print the active calls
+help                print this message
+quit, q             stop debugging
+";
+
+pub fn run(path: &str, source: &str, sandbox: SandboxPolicy) -> anyhow::Result<()> {
+    let mut debugger = Debugger::new();
+    debugger.sandbox = sandbox;
+    if let Err(e) = debugger.load(source) {
+        crate::report(path, source, &e);
+        std::process::exit(1);
+    }
+    show_position(&debugger, path, source);
+
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let (name, arg) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let arg = arg.trim();
+        let resume = match name {
+            "" => continue,
+            "step" | "s" => Resume::StepInto,
+            "next" | "n" => Resume::StepOver,
+            "out" | "o" => Resume::StepOut,
+            "continue" | "c" => Resume::Continue,
+            "break" | "b" => {
+                match arg.parse::<Breakpoint>() {
+                    Ok(breakpoint) => {
+                        println!("breakpoint {}: {}", debugger.breakpoints.len(), breakpoint);
+                        debugger.breakpoints.push(breakpoint);
+                    }
+                    Err(e) => eprintln!("error: {}", e),
+                }
+                continue;
+            }
+            "delete" | "d" => {
+                match arg.parse::<usize>() {
+                    Ok(i) if i < debugger.breakpoints.len() => {
+                        debugger.breakpoints.remove(i);
+                    }
+                    _ => eprintln!("error: no breakpoint '{}'", arg),
+                }
+                continue;
+            }
+            "breakpoints" => {
+                for (i, breakpoint) in debugger.breakpoints.iter().enumerate() {
+                    println!("{}: {}", i, breakpoint);
+                }
+                continue;
+            }
+            "watch" | "w" => {
+                debugger.watches.push(arg.to_owned());
+                show_watches(&debugger);
+                continue;
+            }
+            "unwatch" => {
+                match arg.parse::<usize>() {
+                    Ok(i) if i < debugger.watches.len() => {
+                        debugger.watches.remove(i);
+                    }
+                    _ => eprintln!("error: no watch '{}'", arg),
+                }
+                continue;
+            }
+            "print" | "p" => {
+                match debugger.evaluate(arg) {
+                    Ok(values) => println!("{}", values_syntax(&values)),
+                    Err(e) => eprintln!("error: {}", e),
+                }
+                continue;
+            }
+            "stack" => {
+                for value in debugger.data_stack() {
+                    println!("{}", value.to_syntax().trim_end());
+                }
+                continue;
+            }
+            "locals" => {
+                let mut locals: Vec<_> = debugger.local_scope().iter().collect();
+                locals.sort_by(|a, b| a.0.cmp(b.0));
+                for (name, value) in locals {
+                    println!("{}: {}", name, value.to_syntax().trim_end());
+                }
+                continue;
+            }
+            "backtrace" | "bt" => {
+                print!("{}", debugger.backtrace());
+                continue;
+            }
+            "help" => {
+                print!("{}", HELP);
+                continue;
+            }
+            "quit" | "q" => return Ok(()),
+            other => {
+                eprintln!("error: unknown command '{}', try `help`", other);
+                continue;
+            }
+        };
+
+        match debugger.resume(resume) {
+            Ok(Stop::Finished) => {
+                println!("program finished");
+                return Ok(());
+            }
+            Ok(Stop::Breakpoint(i)) => {
+                println!("breakpoint {}: {}", i, debugger.breakpoints[i]);
+                show_position(&debugger, path, source);
+            }
+            Ok(Stop::Paused | Stop::Running) => show_position(&debugger, path, source),
+            // Stay paused where it failed, so the state can be inspected.
+            Err(e) => crate::report(path, source, &e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Print where the program is paused, followed by the watches.
+fn show_position(debugger: &Debugger, path: &str, source: &str) {
+    match (debugger.span(), debugger.current()) {
+        (Some(span), _) => print!("{}", render_snippet(path, source, span)),
+        (None, Some(next)) => println!("next: {}", next.to_syntax().trim_end()),
+        (None, None) => {}
+    }
+    show_watches(debugger);
+}
+
+fn show_watches(debugger: &Debugger) {
+    for (i, (watch, values)) in debugger.watch_values().into_iter().enumerate() {
+        match values {
+            Ok(values) => println!("{}: {} = {}", i, watch, values_syntax(&values)),
+            Err(e) => println!("{}: {} = error: {}", i, watch, e),
+        }
+    }
+}
+
+/// `values` on one line, bottom of the stack first.
+fn values_syntax(values: &[DataValue]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_syntax().trim_end().to_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
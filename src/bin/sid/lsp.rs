@@ -0,0 +1,597 @@
+//! `sid lsp`: a Language Server Protocol server on stdin and stdout, giving
+//! editors diagnostics, hovers, go-to-definition, completion and document
+//! symbols for sid source.
+//!
+//! Documents are analysed whole on every change: parsed with
+//! [`parse_str_recovering`], so a document with errors still has a tree to
+//! work with, then run through the comptime pass with every capability
+//! denied, so an open file can't touch the world outside the editor.
+//! Bindings are found syntactically, as `name value local!` and as the label
+//! keys of a `{…} load_scope!` or `{…} load_local!` map.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use serde_json::{json, Value};
+use sid::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::message;
+
+/// JSON-RPC error codes.
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+/// LSP `DiagnosticSeverity.Error`.
+const SEVERITY_ERROR: i64 = 1;
+/// LSP `CompletionItemKind`s.
+const COMPLETION_FUNCTION: i64 = 3;
+const COMPLETION_VARIABLE: i64 = 6;
+/// LSP `SymbolKind`s.
+const SYMBOL_FUNCTION: i64 = 12;
+const SYMBOL_VARIABLE: i64 = 13;
+
+/// The built-ins that bind a name, and whether they take a `name value` pair
+/// (`true`) or a map of names (`false`).
+const BINDERS: [(&str, bool); 3] = [
+    ("local", true),
+    ("load_local", false),
+    ("load_scope", false),
+];
+
+pub fn run() -> anyhow::Result<()> {
+    let messages = message::spawn_stdin_reader();
+    let mut server = Server::new();
+    while let Ok(message) = messages.recv() {
+        if !server.handle(&message)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// A name bound in a document.
+struct Binding {
+    name: String,
+    /// Where the name is bound: its label for `local`, the map for
+    /// `load_scope` and `load_local`.
+    span: Span,
+    /// Whether the bound value is a substack, i.e. a function.
+    function: bool,
+    /// Bindings made inside the bound value.
+    children: Vec<Binding>,
+}
+
+/// What a document's source says, recomputed on every change.
+#[derive(Default)]
+struct Analysis {
+    /// Every label in the source with its span, including `$name` and
+    /// `@name` accesses (whose spans include the sigil).
+    labels: Vec<(Span, String)>,
+    bindings: Vec<Binding>,
+    /// The global scope after the comptime pass.
+    scope: HashMap<String, DataValue>,
+    /// Parse and comptime errors, located where the error says.
+    errors: Vec<(Option<Span>, String)>,
+}
+
+impl Analysis {
+    fn of(source: &str, comptime_builtins: &HashMap<String, BuiltinEntry>) -> Self {
+        let mut analysis = Analysis {
+            scope: default_scope(),
+            ..Default::default()
+        };
+        let (values, _, spans, diagnostics) = parse_str_recovering(source);
+        analysis.bindings = analysis.walk(&values, &spans);
+        let comptime = comptime_pass_spanned(
+            values,
+            spans,
+            comptime_builtins,
+            &mut analysis.scope,
+            SandboxPolicy::deny_all(),
+        );
+        if diagnostics.is_empty() {
+            if let Err(e) = comptime {
+                analysis.errors.push(located(&e));
+            }
+        } else {
+            // The comptime pass still fills in the scope, but its errors
+            // could come from what the parser left out.
+            analysis.errors = diagnostics
+                .into_iter()
+                .map(|d| (Some(d.span), d.message))
+                .collect();
+        }
+        analysis
+    }
+
+    /// Record the labels in `values` and return the bindings they make.
+    fn walk(&mut self, values: &[TemplateValue], spans: &SpanTable) -> Vec<Binding> {
+        // The bindings made inside each value, claimed by the binding the
+        // value belongs to or else passed up as they are.
+        let mut nested: Vec<Vec<Binding>> = Vec::with_capacity(values.len());
+        for (i, value) in values.iter().enumerate() {
+            nested.push(Vec::new());
+            match value {
+                TemplateValue::Literal(ProgramValue::Data(DataValue::Label(name)))
+                | TemplateValue::ParentLabel(name)
+                | TemplateValue::ComptimeLabel(name) => {
+                    if let Some(span) = spans.get(i) {
+                        self.labels.push((span, name.clone()));
+                    }
+                }
+                TemplateValue::Literal(ProgramValue::Template(t)) => {
+                    nested[i] = self.walk_template(t);
+                }
+                _ => {}
+            }
+        }
+
+        let mut bindings = Vec::new();
+        let mut claimed = vec![false; values.len()];
+        for i in 0..values.len() {
+            let Some(&(_, pair)) = BINDERS.iter().find(|(binder, _)| {
+                is_label(&values[i], binder)
+                    && matches!(
+                        values.get(i + 1),
+                        Some(TemplateValue::Literal(
+                            ProgramValue::Invoke | ProgramValue::ComptimeInvoke
+                        ))
+                    )
+            }) else {
+                continue;
+            };
+            if pair {
+                // `name value local!`
+                let Some(j) = i.checked_sub(2) else {
+                    continue;
+                };
+                let (Some(name), Some(span)) = (label(&values[j]), spans.get(j)) else {
+                    continue;
+                };
+                claimed[j + 1] = true;
+                bindings.push(Binding {
+                    name: name.to_owned(),
+                    span,
+                    function: is_substack(&values[j + 1]),
+                    children: std::mem::take(&mut nested[j + 1]),
+                });
+            } else {
+                // `{name: value, …} load_scope!`
+                let Some(j) = i.checked_sub(1) else {
+                    continue;
+                };
+                let (TemplateValue::Literal(ProgramValue::Template(t)), Some(span)) =
+                    (&values[j], spans.get(j))
+                else {
+                    continue;
+                };
+                let TemplateData::Map(pairs) = &t.data else {
+                    continue;
+                };
+                claimed[j] = true;
+                for (key, value) in pairs {
+                    let [key] = key.as_slice() else {
+                        continue;
+                    };
+                    let Some(name) = label(key) else {
+                        continue;
+                    };
+                    bindings.push(Binding {
+                        name: name.to_owned(),
+                        span,
+                        function: matches!(value.as_slice(), [v] if is_substack(v)),
+                        // The map was walked for labels above already.
+                        children: Analysis::default().walk(value, &SpanTable::default()),
+                    });
+                }
+            }
+        }
+        for (i, inner) in nested.into_iter().enumerate() {
+            if !claimed[i] {
+                bindings.extend(inner);
+            }
+        }
+        bindings.sort_by_key(|b| (b.span.line, b.span.col));
+        bindings
+    }
+
+    fn walk_template(&mut self, template: &Template) -> Vec<Binding> {
+        match &template.data {
+            TemplateData::Substack(body)
+            | TemplateData::List(body)
+            | TemplateData::Script(body)
+            | TemplateData::Set(body) => self.walk(body, &template.spans),
+            // Map entries carry no spans, but templates inside them do.
+            TemplateData::Map(pairs) => pairs
+                .iter()
+                .flat_map(|(key, value)| [key, value])
+                .flat_map(|values| self.walk(values, &SpanTable::default()))
+                .collect(),
+        }
+    }
+
+    /// The label at 1-based `line`:`col`, with its span.
+    fn label_at(&self, line: usize, col: usize) -> Option<(Span, &str)> {
+        self.labels
+            .iter()
+            .find(|(span, _)| span.line == line && (span.col..span.col + span.len).contains(&col))
+            .map(|(span, name)| (*span, name.as_str()))
+    }
+
+    /// Every binding, depth first.
+    fn all_bindings(&self) -> Vec<&Binding> {
+        fn collect<'a>(bindings: &'a [Binding], out: &mut Vec<&'a Binding>) {
+            for binding in bindings {
+                out.push(binding);
+                collect(&binding.children, out);
+            }
+        }
+        let mut out = Vec::new();
+        collect(&self.bindings, &mut out);
+        out
+    }
+
+    /// The binding `name` refers to at `line`:`col`: the last one before it,
+    /// or else the first one after it (a substack may be called before the
+    /// binding it uses was made, as long as it runs after).
+    fn binding_of(&self, name: &str, line: usize, col: usize) -> Option<&Binding> {
+        let root = name.split('.').next().unwrap_or(name);
+        let candidates: Vec<&Binding> = self
+            .all_bindings()
+            .into_iter()
+            .filter(|b| b.name == root)
+            .collect();
+        candidates
+            .iter()
+            .rev()
+            .find(|b| (b.span.line, b.span.col) <= (line, col))
+            .or_else(|| candidates.first())
+            .copied()
+    }
+}
+
+fn is_label(value: &TemplateValue, name: &str) -> bool {
+    label(value) == Some(name)
+}
+
+fn label(value: &TemplateValue) -> Option<&str> {
+    match value {
+        TemplateValue::Literal(ProgramValue::Data(DataValue::Label(name))) => Some(name),
+        _ => None,
+    }
+}
+
+fn is_substack(value: &TemplateValue) -> bool {
+    matches!(
+        value,
+        TemplateValue::Literal(ProgramValue::Template(Template {
+            data: TemplateData::Substack(_),
+            ..
+        }))
+    )
+}
+
+/// `e`'s message and source location, if it has one.
+fn located(e: &anyhow::Error) -> (Option<Span>, String) {
+    let span = e.downcast_ref::<SidError>().and_then(SidError::span);
+    (span, format!("{:#}", e))
+}
+
+/// The stack effect of a built-in, e.g. `add ( types.any types.any --
+/// types.any )`.
+fn signature(entry: &BuiltinEntry) -> String {
+    let types = |types: &[SidType]| {
+        types
+            .iter()
+            .map(|t| format!(" {}", t.to_syntax()))
+            .collect::<String>()
+    };
+    format!(
+        "{} ({} --{} )",
+        entry.name,
+        types(&entry.args),
+        types(&entry.ret)
+    )
+}
+
+/// A value's syntax on one line.
+fn one_line(value: &impl ToSyntax) -> String {
+    value
+        .to_syntax()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+impl Document {
+    /// The LSP position (0-based, UTF-16 columns) of 1-based grapheme
+    /// `line`:`col`.
+    fn position(&self, line: usize, col: usize) -> Value {
+        let character: usize = self
+            .line(line)
+            .graphemes(true)
+            .take(col.saturating_sub(1))
+            .map(|g| g.encode_utf16().count())
+            .sum();
+        json!({ "line": line.saturating_sub(1), "character": character })
+    }
+
+    /// The 1-based grapheme line and column of an LSP position.
+    fn location(&self, position: &Value) -> (usize, usize) {
+        let line = position["line"].as_u64().unwrap_or(0) as usize + 1;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+        let mut units = 0;
+        let mut col = 1;
+        for g in self.line(line).graphemes(true) {
+            units += g.encode_utf16().count();
+            if units > character {
+                break;
+            }
+            col += 1;
+        }
+        (line, col)
+    }
+
+    fn line(&self, line: usize) -> &str {
+        let text = self
+            .text
+            .split('\n')
+            .nth(line.saturating_sub(1))
+            .unwrap_or("");
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    fn range(&self, span: Span) -> Value {
+        json!({
+            "start": self.position(span.line, span.col),
+            "end": self.position(span.line, span.col + span.len),
+        })
+    }
+
+    fn diagnostics(&self) -> Value {
+        let diagnostics: Vec<Value> = self
+            .analysis
+            .errors
+            .iter()
+            .map(|(span, message)| {
+                // Without a location, point at the start of the document.
+                let range = match span {
+                    Some(span) => self.range(*span),
+                    None => json!({
+                        "start": { "line": 0, "character": 0 },
+                        "end": { "line": 0, "character": 0 },
+                    }),
+                };
+                json!({
+                    "range": range,
+                    "severity": SEVERITY_ERROR,
+                    "source": "sid",
+                    "message": message,
+                })
+            })
+            .collect();
+        json!(diagnostics)
+    }
+
+    fn symbols(&self, bindings: &[Binding]) -> Value {
+        let symbols: Vec<Value> = bindings
+            .iter()
+            .map(|binding| {
+                let range = self.range(binding.span);
+                json!({
+                    "name": binding.name,
+                    "kind": if binding.function { SYMBOL_FUNCTION } else { SYMBOL_VARIABLE },
+                    "range": range,
+                    "selectionRange": range,
+                    "children": self.symbols(&binding.children),
+                })
+            })
+            .collect();
+        json!(symbols)
+    }
+}
+
+struct Server {
+    documents: HashMap<String, Document>,
+    builtins: HashMap<String, BuiltinEntry>,
+    comptime_builtins: HashMap<String, BuiltinEntry>,
+    shutting_down: bool,
+    stdout: io::Stdout,
+}
+
+impl Server {
+    fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            builtins: get_interpret_builtins(),
+            comptime_builtins: get_comptime_builtins(),
+            shutting_down: false,
+            stdout: io::stdout(),
+        }
+    }
+
+    fn send(&mut self, mut message: Value) -> anyhow::Result<()> {
+        message["jsonrpc"] = json!("2.0");
+        message::write(&mut self.stdout.lock(), &message)?;
+        Ok(())
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> anyhow::Result<()> {
+        self.send(json!({ "method": method, "params": params }))
+    }
+
+    /// Handle one request or notification.  Returns `false` on `exit`.
+    fn handle(&mut self, message: &Value) -> anyhow::Result<bool> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+        let result = if self.shutting_down {
+            Err((INVALID_REQUEST, "the server is shutting down".to_owned()))
+        } else {
+            self.request(method, params)
+        };
+        let response = match result {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err((code, message)) => {
+                json!({ "id": id, "error": { "code": code, "message": message } })
+            }
+        };
+        self.send(response)?;
+        Ok(true)
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> anyhow::Result<bool> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "exit" => return Ok(false),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.update(uri, text.to_owned())?;
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str())
+                {
+                    self.update(uri, text.to_owned())?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                self.notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )?;
+            }
+            // `initialized`, `$/…` and anything else needs no answer.
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Re-analyse `uri` as `text` and publish its diagnostics.
+    fn update(&mut self, uri: &str, text: String) -> anyhow::Result<()> {
+        let analysis = Analysis::of(&text, &self.comptime_builtins);
+        let document = Document { text, analysis };
+        let diagnostics = document.diagnostics();
+        self.documents.insert(uri.to_owned(), document);
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if method == "initialize" {
+            return Ok(json!({
+                "capabilities": {
+                    // Full document sync.
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": {},
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "sid", "version": env!("CARGO_PKG_VERSION") },
+            }));
+        }
+        if method == "shutdown" {
+            self.shutting_down = true;
+            return Ok(Value::Null);
+        }
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_REQUEST, format!("unknown document '{}'", uri)))?;
+        let (line, col) = document.location(&params["position"]);
+        Ok(match method {
+            "textDocument/hover" => self.hover(document, line, col),
+            "textDocument/definition" => {
+                let analysis = &document.analysis;
+                match analysis
+                    .label_at(line, col)
+                    .and_then(|(_, name)| analysis.binding_of(name, line, col))
+                {
+                    Some(binding) => json!({ "uri": uri, "range": document.range(binding.span) }),
+                    None => Value::Null,
+                }
+            }
+            "textDocument/completion" => json!(self.completions(document)),
+            "textDocument/documentSymbol" => document.symbols(&document.analysis.bindings),
+            other => return Err((METHOD_NOT_FOUND, format!("unsupported method '{}'", other))),
+        })
+    }
+
+    /// What the label under the cursor refers to: a binding in the document,
+    /// else whatever it resolves to in the global scope or the built-ins.
+    fn hover(&self, document: &Document, line: usize, col: usize) -> Value {
+        let analysis = &document.analysis;
+        let Some((span, name)) = analysis.label_at(line, col) else {
+            return Value::Null;
+        };
+        let text = if let Some(binding) = analysis.binding_of(name, line, col) {
+            format!("`{}`, bound at {}", name, binding.span)
+        } else {
+            let builtin_names: HashSet<&str> = self.builtins.keys().map(String::as_str).collect();
+            match get_from_scope(name, None, Some(&analysis.scope), Some(&builtin_names)) {
+                Ok(DataValue::BuiltIn(builtin)) => {
+                    format!(
+                        "```sid\n{}\n```\nbuilt-in",
+                        signature(&self.builtins[&builtin])
+                    )
+                }
+                Ok(value) => format!("```sid\n{} = {}\n```", name, one_line(&value)),
+                Err(_) => return Value::Null,
+            }
+        };
+        json!({
+            "contents": { "kind": "markdown", "value": text },
+            "range": document.range(span),
+        })
+    }
+
+    /// The built-ins, the global scope and the document's bindings, sorted by
+    /// name; the editor filters them by what has been typed.
+    fn completions(&self, document: &Document) -> Vec<Value> {
+        let mut items: Vec<(String, i64, String)> = Vec::new();
+        for (name, entry) in &self.builtins {
+            items.push((name.clone(), COMPLETION_FUNCTION, signature(entry)));
+        }
+        for (name, value) in &document.analysis.scope {
+            items.push((name.clone(), COMPLETION_VARIABLE, one_line(value)));
+        }
+        let mut seen: HashSet<&str> = items.iter().map(|(name, ..)| name.as_str()).collect();
+        let mut bound = Vec::new();
+        for binding in document.analysis.all_bindings() {
+            if seen.insert(&binding.name) {
+                let kind = if binding.function {
+                    COMPLETION_FUNCTION
+                } else {
+                    COMPLETION_VARIABLE
+                };
+                bound.push((
+                    binding.name.clone(),
+                    kind,
+                    format!("bound at {}", binding.span),
+                ));
+            }
+        }
+        items.extend(bound);
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items
+            .into_iter()
+            .map(|(label, kind, detail)| json!({ "label": label, "kind": kind, "detail": detail }))
+            .collect()
+    }
+}
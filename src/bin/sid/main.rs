@@ -0,0 +1,217 @@
+use sid::*;
+
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+
+mod dap;
+mod debug;
+mod lsp;
+mod message;
+mod repl;
+
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct CliArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Path to file to execute code from or `-` for stdin
+    #[arg(required = true)]
+    file: Option<String>,
+    #[command(flatten)]
+    sandbox: SandboxArgs,
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start an interactive session
+    Repl {
+        #[command(flatten)]
+        sandbox: SandboxArgs,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// Step through a program, reading debugger commands from stdin
+    Debug {
+        /// Path to the file to debug
+        file: String,
+        #[command(flatten)]
+        sandbox: SandboxArgs,
+    },
+    /// Serve the Debug Adapter Protocol on stdin and stdout
+    Dap,
+    /// Serve the Language Server Protocol on stdin and stdout
+    Lsp,
+}
+
+#[derive(Args)]
+struct SandboxArgs {
+    /// Deny FFI, raw pointers, files and environment variables, except as
+    /// re-allowed by the --allow-* flags
+    #[arg(long)]
+    sandbox: bool,
+    /// With --sandbox, still allow loading C headers and libraries and calling C
+    #[arg(long, requires = "sandbox")]
+    allow_ffi: bool,
+    /// With --sandbox, still allow ptr_cast and ptr_read_cstr
+    #[arg(long, requires = "sandbox")]
+    allow_pointers: bool,
+    /// With --sandbox, still allow reading and writing files
+    #[arg(long, requires = "sandbox")]
+    allow_fs: bool,
+    /// With --sandbox, still allow reading environment variables
+    #[arg(long, requires = "sandbox")]
+    allow_env: bool,
+}
+
+impl SandboxArgs {
+    fn policy(&self) -> SandboxPolicy {
+        if !self.sandbox {
+            return SandboxPolicy::allow_all();
+        }
+        SandboxPolicy {
+            ffi: self.allow_ffi,
+            pointers: self.allow_pointers,
+            filesystem: self.allow_fs,
+            env: self.allow_env,
+        }
+    }
+}
+
+// Bounds on the run phase.  In the repl they apply to each line afresh.
+// (A `///` here would become the about text of every command using them.)
+#[derive(Args, Clone, Default)]
+struct LimitArgs {
+    /// Stop after this many interpreter steps
+    #[arg(long, value_name = "STEPS")]
+    fuel: Option<u64>,
+    /// Fail if the data stack grows beyond this many values
+    #[arg(long, value_name = "VALUES")]
+    max_data_stack: Option<usize>,
+    /// Fail if the program stack grows beyond this many entries, which bounds
+    /// recursion depth
+    #[arg(long, value_name = "ENTRIES")]
+    max_program_stack: Option<usize>,
+    /// Stop once this many seconds have passed
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    timeout: Option<std::time::Duration>,
+}
+
+impl LimitArgs {
+    /// These limits, with the timeout starting at `now`.
+    fn limits(&self, now: std::time::SystemTime) -> Limits {
+        Limits {
+            fuel: self.fuel,
+            max_data_stack: self.max_data_stack,
+            max_program_stack: self.max_program_stack,
+            deadline: self.timeout.map(|timeout| now + timeout),
+        }
+    }
+}
+
+fn parse_seconds(arg: &str) -> Result<std::time::Duration, String> {
+    let secs: f64 = arg.parse().map_err(|e| format!("{}", e))?;
+    std::time::Duration::try_from_secs_f64(secs).map_err(|e| format!("{}", e))
+}
+
+fn main() {
+    let cli = CliArgs::parse();
+    match (cli.command, cli.file) {
+        (Some(Command::Repl { sandbox, limits }), _) => {
+            if let Err(e) = repl::run(sandbox.policy(), limits) {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        (Some(Command::Debug { file, sandbox }), _) => {
+            let source = match read_source(&file) {
+                Ok(source) => source,
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+            if let Err(e) = debug::run(&file, &source, sandbox.policy()) {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        (Some(Command::Dap), _) => {
+            if let Err(e) = dap::run() {
+                eprintln!("error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        (Some(Command::Lsp), _) => {
+            if let Err(e) = lsp::run() {
+                eprintln!("error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        (None, Some(file)) => run_file(&file, cli.sandbox.policy(), &cli.limits),
+        // clap requires `file` unless a subcommand is given.
+        (None, None) => unreachable!(),
+    }
+}
+
+fn run_file(path: &str, sandbox: SandboxPolicy, limits: &LimitArgs) {
+    let file_content = match read_source(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    // Name stdin the way diagnostics expect a file name.
+    let name = if path == "-" { "<stdin>" } else { path };
+    if let Err(e) = run(&file_content, sandbox, limits) {
+        report(name, &file_content, &e);
+        std::process::exit(1);
+    }
+}
+
+/// Read the program at `path`, or all of stdin if `path` is `-`.
+///
+/// A `#!` line at the top of a file needs no special handling: `#` starts a
+/// comment, so the parser skips it.
+fn read_source(path: &str) -> anyhow::Result<String> {
+    if path == "-" {
+        let mut source = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut source)
+            .context("failed to read program from stdin")?;
+        Ok(source)
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path))
+    }
+}
+
+/// Print `e` to stderr, followed by a snippet of the offending source line if
+/// the error is located.  A parse error is printed as each of its diagnostics
+/// in turn.
+fn report(path: &str, source: &str, e: &anyhow::Error) {
+    if let Some(error) = e.downcast_ref::<ParseError>() {
+        for diagnostic in &error.diagnostics {
+            eprintln!("error: {}", diagnostic.message);
+            eprint!("{}", render_snippet(path, source, diagnostic.span));
+        }
+        return;
+    }
+    eprintln!("error: {}", e);
+    let Some(error) = e.downcast_ref::<SidError>() else {
+        return;
+    };
+    if let Some(span) = error.span() {
+        eprint!("{}", render_snippet(path, source, span));
+    }
+    if let Some(backtrace) = error.backtrace() {
+        eprint!("backtrace (most recent call first):\n{}", backtrace);
+    }
+}
+
+fn run(source: &str, sandbox: SandboxPolicy, limits: &LimitArgs) -> anyhow::Result<()> {
+    let mut engine = Engine::new();
+    engine.sandbox = sandbox;
+    engine.limits = limits.limits(std::time::SystemTime::now());
+    engine.eval_str(source)
+}
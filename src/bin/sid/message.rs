@@ -0,0 +1,70 @@
+//! `Content-Length` framed JSON messages, the base protocol shared by the
+//! Debug Adapter Protocol and the Language Server Protocol.
+
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc;
+
+use anyhow::{bail, Context};
+use serde_json::Value;
+
+/// Read one message, or `None` at end of input.
+pub fn read(input: &mut impl BufRead) -> anyhow::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .context("bad Content-Length")?,
+                );
+            }
+        }
+    }
+    let Some(length) = length else {
+        bail!("message without a Content-Length header");
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub fn write(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Read messages from stdin on a thread of their own, so the caller can keep
+/// working while waiting for the next one.  The channel closes at end of
+/// input or on a malformed message.
+pub fn spawn_stdin_reader() -> mpsc::Receiver<Value> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        loop {
+            match read(&mut stdin) {
+                Ok(Some(message)) => {
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    break;
+                }
+            }
+        }
+    });
+    receiver
+}
@@ -0,0 +1,246 @@
+//! `sid repl`: an interactive session that keeps the data stack, the local
+//! scope and the global state alive between lines.
+
+use anyhow::{bail, Context};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use sid::*;
+
+use crate::LimitArgs;
+
+const HELP: &str = "\
+:stack        print the data stack
+:scope        print the local scope and the names in the global scope
+:clear        empty the data stack
+:load <file>  run a file in this session
+:help         print this message
+:quit         leave the repl (or press Ctrl-D)
+";
+
+/// The state carried from one line to the next.
+struct Session {
+    engine: Engine,
+    limits: LimitArgs,
+}
+
+impl Session {
+    fn new(sandbox: SandboxPolicy, limits: LimitArgs) -> Self {
+        let mut engine = Engine::new();
+        engine.sandbox = sandbox;
+        Self { engine, limits }
+    }
+
+    /// Run `source` on top of the session's stack, with fresh limits.
+    ///
+    /// Labels it defines with `local` stay visible to later lines.  If it
+    /// fails the data stack and local scope are put back as they were before
+    /// it started.
+    fn eval(&mut self, source: &str) -> anyhow::Result<()> {
+        self.engine.limits = self.limits.limits(std::time::SystemTime::now());
+        self.engine.eval_str(source)
+    }
+
+    /// The data stack, one value per line with the top of the stack last.
+    fn stack_syntax(&self) -> String {
+        self.engine
+            .data_stack()
+            .iter()
+            .map(|v| format!("{}\n", v.to_syntax().trim_end()))
+            .collect()
+    }
+
+    fn scope_syntax(&self) -> String {
+        let mut locals: Vec<_> = self.engine.local_scope().iter().collect();
+        locals.sort_by(|a, b| a.0.cmp(b.0));
+        let mut globals: Vec<_> = self.engine.global_scope().keys().cloned().collect();
+        globals.sort();
+        let mut out = String::from("local:\n");
+        for (name, value) in locals {
+            out.push_str(&format!("  {}: {}\n", name, value.to_syntax().trim_end()));
+        }
+        out.push_str(&format!("global: {}\n", globals.join(" ")));
+        out
+    }
+}
+
+/// Whether `source` closes every bracket it opens, so that it can be parsed
+/// without waiting for more lines.  Brackets inside strings, chars and
+/// comments don't count.
+fn is_complete(source: &str) -> bool {
+    let chars: Vec<char> = source.chars().collect();
+    let mut depth = 0i64;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let token_start = i == 0 || chars[i - 1].is_whitespace() || "([{<,".contains(chars[i - 1]);
+        i += 1;
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth -= 1,
+            'r' if token_start && matches!(chars.get(i), Some('"') | Some('#')) => {
+                // r"…" or r#"…"#: no escapes, closed by `"` and as many `#`s.
+                let hashes = chars[i..].iter().take_while(|&&c| c == '#').count();
+                i += hashes + 1;
+                let closing: Vec<char> = std::iter::once('"')
+                    .chain(std::iter::repeat_n('#', hashes))
+                    .collect();
+                match chars[i.min(chars.len())..]
+                    .windows(closing.len())
+                    .position(|w| w == closing.as_slice())
+                {
+                    Some(end) => i += end + closing.len(),
+                    None => return false,
+                }
+            }
+            '"' | '\'' => loop {
+                match chars.get(i) {
+                    None => return false,
+                    Some('\\') => i += 2,
+                    Some(&q) => {
+                        i += 1;
+                        if q == c {
+                            break;
+                        }
+                    }
+                }
+            },
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    // Leave stray closing brackets for the parser to report.
+    depth <= 0
+}
+
+fn history_path() -> Option<std::path::PathBuf> {
+    std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".sid_history"))
+}
+
+/// Run the meta-command `line` (which starts with `:`).  Returns `false` when
+/// the session should end.
+fn command(session: &mut Session, line: &str) -> anyhow::Result<bool> {
+    let (name, arg) = line.split_once(' ').unwrap_or((line, ""));
+    let arg = arg.trim();
+    match name {
+        ":stack" => print!("{}", session.stack_syntax()),
+        ":scope" => print!("{}", session.scope_syntax()),
+        ":clear" => session.engine.clear_stack(),
+        ":load" => {
+            if arg.is_empty() {
+                bail!(":load expects a file path");
+            }
+            let source =
+                std::fs::read_to_string(arg).with_context(|| format!("failed to read {}", arg))?;
+            match session.eval(&source) {
+                Ok(()) => print!("{}", session.stack_syntax()),
+                Err(e) => crate::report(arg, &source, &e),
+            }
+        }
+        ":help" => print!("{}", HELP),
+        ":quit" | ":q" => return Ok(false),
+        other => bail!("unknown command {} (try :help)", other),
+    }
+    Ok(true)
+}
+
+/// Run the interactive loop until end of input or `:quit`.
+pub fn run(sandbox: SandboxPolicy, limits: LimitArgs) -> anyhow::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let history = history_path();
+    if let Some(path) = &history {
+        // No history file yet is fine.
+        let _ = editor.load_history(path);
+    }
+    let mut session = Session::new(sandbox, limits);
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { "sid> " } else { "...> " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl-C abandons the input entered so far.
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if buffer.is_empty() && line.trim_start().starts_with(':') {
+            editor.add_history_entry(line.trim())?;
+            match command(&mut session, line.trim()) {
+                Ok(true) => continue,
+                Ok(false) => break,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    continue;
+                }
+            }
+        }
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if !is_complete(&buffer) {
+            continue;
+        }
+        let source = std::mem::take(&mut buffer);
+        if source.trim().is_empty() {
+            continue;
+        }
+        editor.add_history_entry(source.trim_end())?;
+        match session.eval(&source) {
+            Ok(()) => print!("{}", session.stack_syntax()),
+            Err(e) => crate::report("<repl>", &source, &e),
+        }
+    }
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_persists_between_lines() {
+        let mut session = Session::new(SandboxPolicy::default(), LimitArgs::default());
+        session.eval("1 2").unwrap();
+        session.eval("add !").unwrap();
+        assert_eq!(session.stack_syntax(), "3\n");
+    }
+
+    #[test]
+    fn locals_persist_between_lines() {
+        let mut session = Session::new(SandboxPolicy::default(), LimitArgs::default());
+        session.eval("x 42 local !").unwrap();
+        session.eval("x get_local !").unwrap();
+        assert_eq!(session.stack_syntax(), "42\n");
+    }
+
+    #[test]
+    fn failed_line_leaves_stack_unchanged() {
+        let mut session = Session::new(SandboxPolicy::default(), LimitArgs::default());
+        session.eval("1 2").unwrap();
+        let err = session.eval("3 true add !").unwrap_err();
+        assert!(err.downcast_ref::<SidError>().unwrap().span().is_some());
+        assert_eq!(session.stack_syntax(), "1\n2\n");
+    }
+
+    #[test]
+    fn open_brackets_need_more_input() {
+        assert!(is_complete("1 2 add !\n"));
+        assert!(!is_complete("(1\n"));
+        assert!(!is_complete("[1 (2)\n"));
+        assert!(is_complete("\"(\" ')' # (\n"));
+        assert!(!is_complete("\"abc\n"));
+        assert!(is_complete("1 )\n"));
+        assert!(is_complete("'\\'' '(' \"\\\"(\"\n"));
+        assert!(is_complete("r\"(\\\" r#\"(\"\"# 1\n"));
+        assert!(!is_complete("r#\"(\"\n"));
+        assert!(!is_complete("r\"x\" (\n"));
+    }
+}
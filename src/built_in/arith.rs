@@ -0,0 +1,132 @@
+use crate::built_in::{pop_arg, BuiltinEntry};
+use crate::type_system::SidType;
+use crate::{DataValue, SidError};
+
+/// `{int, float}` — the union type accepted by every arithmetic built-in.
+fn number() -> SidType {
+    SidType::Literal(Box::new(DataValue::Set(vec![
+        DataValue::Type(SidType::Int),
+        DataValue::Type(SidType::Float),
+    ])))
+}
+
+/// The operands of a binary operation after explicit promotion.
+///
+/// Two `Int`s stay integers; if either side is a `Float` the other side is
+/// converted with `as f64` and the operation is carried out in floating point.
+enum Operands {
+    Int(i64, i64),
+    Float(f64, f64),
+}
+
+fn promote(a: DataValue, b: DataValue, name: &str) -> Result<Operands, SidError> {
+    match (a, b) {
+        (DataValue::Int(a), DataValue::Int(b)) => Ok(Operands::Int(a, b)),
+        (DataValue::Int(a), DataValue::Float(b)) => Ok(Operands::Float(a as f64, b)),
+        (DataValue::Float(a), DataValue::Int(b)) => Ok(Operands::Float(a, b as f64)),
+        (DataValue::Float(a), DataValue::Float(b)) => Ok(Operands::Float(a, b)),
+        (a, b) => Err(SidError::TypeMismatch(format!(
+            "{}: expected Int or Float operands, got {:?} and {:?}",
+            name, a, b
+        ))),
+    }
+}
+
+/// A float result is only accepted if it is finite or one of the inputs was
+/// already non-finite; otherwise the operation overflowed.
+fn checked_float(result: f64, a: f64, b: f64, name: &str) -> Result<DataValue, SidError> {
+    if !result.is_finite() && a.is_finite() && b.is_finite() {
+        return Err(SidError::Arithmetic(format!(
+            "{}: float overflow ({} and {})",
+            name, a, b
+        )));
+    }
+    Ok(DataValue::Float(result))
+}
+
+/// Shared implementation of the binary operators.
+///
+/// `int_op` returns `None` on overflow; division by zero must be rejected by
+/// the caller-supplied `divides` flag before either op is attempted.
+fn binary(
+    mut args: Vec<DataValue>,
+    name: &str,
+    divides: bool,
+    int_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+) -> Result<Vec<DataValue>, SidError> {
+    let b = pop_arg(&mut args, name)?;
+    let a = pop_arg(&mut args, name)?;
+    let division_by_zero = || SidError::Arithmetic(format!("{}: division by zero", name));
+    Ok(vec![match promote(a, b, name)? {
+        Operands::Int(_, 0) if divides => return Err(division_by_zero()),
+        Operands::Int(a, b) => DataValue::Int(int_op(a, b).ok_or_else(|| {
+            SidError::Arithmetic(format!("{}: integer overflow ({} and {})", name, a, b))
+        })?),
+        Operands::Float(_, b) if divides && b == 0.0 => return Err(division_by_zero()),
+        Operands::Float(a, b) => checked_float(float_op(a, b), a, b, name)?,
+    }])
+}
+
+pub fn add() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "add".to_owned(),
+        args: vec![number(), number()],
+        ret: vec![number()],
+        exec: Box::new(|_state, args| binary(args, "add", false, i64::checked_add, |a, b| a + b)),
+    }
+}
+
+pub fn sub() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "sub".to_owned(),
+        args: vec![number(), number()],
+        ret: vec![number()],
+        exec: Box::new(|_state, args| binary(args, "sub", false, i64::checked_sub, |a, b| a - b)),
+    }
+}
+
+pub fn mul() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "mul".to_owned(),
+        args: vec![number(), number()],
+        ret: vec![number()],
+        exec: Box::new(|_state, args| binary(args, "mul", false, i64::checked_mul, |a, b| a * b)),
+    }
+}
+
+pub fn div() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "div".to_owned(),
+        args: vec![number(), number()],
+        ret: vec![number()],
+        exec: Box::new(|_state, args| binary(args, "div", true, i64::checked_div, |a, b| a / b)),
+    }
+}
+
+pub fn mod_builtin() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "mod".to_owned(),
+        args: vec![number(), number()],
+        ret: vec![number()],
+        exec: Box::new(|_state, args| binary(args, "mod", true, i64::checked_rem, |a, b| a % b)),
+    }
+}
+
+pub fn neg() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "neg".to_owned(),
+        args: vec![number()],
+        ret: vec![number()],
+        exec: Box::new(|_state, mut args| match pop_arg(&mut args, "neg")? {
+            DataValue::Int(n) => Ok(vec![DataValue::Int(n.checked_neg().ok_or_else(|| {
+                SidError::Arithmetic(format!("neg: integer overflow ({})", n))
+            })?)]),
+            DataValue::Float(f) => Ok(vec![DataValue::Float(-f)]),
+            other => Err(SidError::TypeMismatch(format!(
+                "neg expects Int or Float, got {:?}",
+                other
+            ))),
+        }),
+    }
+}
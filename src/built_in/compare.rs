@@ -0,0 +1,83 @@
+use std::cmp::Ordering;
+
+use crate::built_in::{pop_arg, BuiltinEntry};
+use crate::type_system::SidType;
+use crate::{DataValue, SidError};
+
+/// Order two values of the same kind.
+///
+/// - `Int` / `Float`: numeric order; mixed operands are promoted to `Float`.
+///   Any comparison involving NaN is unordered and returns `None`.
+/// - `Char`, `Str`, `Label`: bytewise order of the UTF-8 (or C string) bytes.
+///
+/// Every other pairing, including comparisons across kinds, is a type error.
+pub(super) fn compare(
+    a: &DataValue,
+    b: &DataValue,
+    name: &str,
+) -> Result<Option<Ordering>, SidError> {
+    Ok(match (a, b) {
+        (DataValue::Int(a), DataValue::Int(b)) => Some(a.cmp(b)),
+        (DataValue::Int(a), DataValue::Float(b)) => (*a as f64).partial_cmp(b),
+        (DataValue::Float(a), DataValue::Int(b)) => a.partial_cmp(&(*b as f64)),
+        (DataValue::Float(a), DataValue::Float(b)) => a.partial_cmp(b),
+        (DataValue::Char(a), DataValue::Char(b)) => Some(a.as_bytes().cmp(b.as_bytes())),
+        (DataValue::Str(a), DataValue::Str(b)) => Some(a.as_bytes().cmp(b.as_bytes())),
+        (DataValue::Label(a), DataValue::Label(b)) => Some(a.as_bytes().cmp(b.as_bytes())),
+        (a, b) => {
+            return Err(SidError::TypeMismatch(format!(
+                "{}: cannot compare {:?} with {:?}",
+                name, a, b
+            )))
+        }
+    })
+}
+
+/// Shared implementation of the ordering built-ins: pops `a b` and pushes
+/// whether `compare(a, b)` is one of the `accepted` orderings.
+fn ordering(
+    mut args: Vec<DataValue>,
+    name: &str,
+    accepted: &[Ordering],
+) -> Result<Vec<DataValue>, SidError> {
+    let b = pop_arg(&mut args, name)?;
+    let a = pop_arg(&mut args, name)?;
+    let result = compare(&a, &b, name)?.is_some_and(|o| accepted.contains(&o));
+    Ok(vec![DataValue::Bool(result)])
+}
+
+pub fn lt() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "lt".to_owned(),
+        args: vec![SidType::Any, SidType::Any],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| ordering(args, "lt", &[Ordering::Less])),
+    }
+}
+
+pub fn le() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "le".to_owned(),
+        args: vec![SidType::Any, SidType::Any],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| ordering(args, "le", &[Ordering::Less, Ordering::Equal])),
+    }
+}
+
+pub fn gt() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "gt".to_owned(),
+        args: vec![SidType::Any, SidType::Any],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| ordering(args, "gt", &[Ordering::Greater])),
+    }
+}
+
+pub fn ge() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "ge".to_owned(),
+        args: vec![SidType::Any, SidType::Any],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| ordering(args, "ge", &[Ordering::Greater, Ordering::Equal])),
+    }
+}
@@ -1,55 +1,49 @@
-use crate::built_in::BuiltinEntry;
+use crate::built_in::{pop_arg, BuiltinEntry};
+use crate::invoke::schedule_body;
 use crate::type_system::SidType;
-use crate::{DataValue, ProgramValue};
+use crate::{DataValue, ProgramValue, SidError};
 
-fn pop_arg(args: &mut Vec<DataValue>, name: &str) -> DataValue {
-    args.pop()
-        .unwrap_or_else(|| panic!("{}: expected an argument", name))
+fn require_substack(v: &DataValue, what: &str) -> Result<(), SidError> {
+    match v {
+        DataValue::Substack { .. } => Ok(()),
+        other => Err(SidError::TypeMismatch(format!(
+            "{} must be a Substack, got {:?}",
+            what, other
+        ))),
+    }
 }
 
 pub fn while_do() -> BuiltinEntry {
     BuiltinEntry {
-        name: "while_do",
+        name: "while_do".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![],
-        exec: |state, mut args| {
-            let body = pop_arg(&mut args, "while_do");
-            let cond = pop_arg(&mut args, "while_do");
-            match &body {
-                DataValue::Substack { .. } => {}
-                other => panic!("while_do: body must be a Substack, got {:?}", other),
-            }
-            match &cond {
-                DataValue::Substack { .. } => {}
-                other => panic!("while_do: condition must be a Substack, got {:?}", other),
-            }
+        exec: Box::new(|state, mut args| {
+            let body = pop_arg(&mut args, "while_do")?;
+            let cond = pop_arg(&mut args, "while_do")?;
+            require_substack(&body, "while_do: body")?;
+            require_substack(&cond, "while_do: condition")?;
             state.program_stack.push(ProgramValue::CondLoopStart {
                 cond: cond.clone(),
                 body,
             });
             state.program_stack.push(ProgramValue::Invoke);
             state.program_stack.push(ProgramValue::Data(cond));
-            vec![]
-        },
+            Ok(vec![])
+        }),
     }
 }
 
 pub fn do_while() -> BuiltinEntry {
     BuiltinEntry {
-        name: "do_while",
+        name: "do_while".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![],
-        exec: |state, mut args| {
-            let cond = pop_arg(&mut args, "do_while");
-            let body = pop_arg(&mut args, "do_while");
-            match &cond {
-                DataValue::Substack { .. } => {}
-                other => panic!("do_while: condition must be a Substack, got {:?}", other),
-            }
-            match &body {
-                DataValue::Substack { .. } => {}
-                other => panic!("do_while: body must be a Substack, got {:?}", other),
-            }
+        exec: Box::new(|state, mut args| {
+            let cond = pop_arg(&mut args, "do_while")?;
+            let body = pop_arg(&mut args, "do_while")?;
+            require_substack(&cond, "do_while: condition")?;
+            require_substack(&body, "do_while: body")?;
             let expected_len = state.data_stack.len();
             state.program_stack.push(ProgramValue::CondLoop {
                 cond: cond.clone(),
@@ -60,37 +54,48 @@ pub fn do_while() -> BuiltinEntry {
             state.program_stack.push(ProgramValue::Data(cond));
             state.program_stack.push(ProgramValue::Invoke);
             state.program_stack.push(ProgramValue::Data(body));
-            vec![]
-        },
+            Ok(vec![])
+        }),
     }
 }
 
 pub fn match_builtin() -> BuiltinEntry {
     BuiltinEntry {
-        name: "match",
+        name: "match".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![],
-        exec: |state, mut args| {
-            let cases = pop_arg(&mut args, "match");
-            let value = pop_arg(&mut args, "match");
+        exec: Box::new(|state, mut args| {
+            let cases = pop_arg(&mut args, "match")?;
+            let value = pop_arg(&mut args, "match")?;
             let entries = match cases {
                 DataValue::Map(e) => e,
-                other => panic!("match: cases must be a Map, got {:?}", other),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "match: cases must be a Map, got {:?}",
+                        other
+                    )))
+                }
             };
             for (pattern, action) in entries {
                 if pattern.pattern_matches(&value) {
-                    let body = match action {
-                        DataValue::Substack { body, .. } | DataValue::Script { body, .. } => body,
-                        other => panic!(
-                            "match: action must be a Substack or Script, got {:?}",
-                            other
-                        ),
+                    let (body, spans) = match action {
+                        DataValue::Substack { body, spans, .. }
+                        | DataValue::Script { body, spans, .. } => (body, spans),
+                        other => {
+                            return Err(SidError::TypeMismatch(format!(
+                                "match: action must be a Substack or Script, got {:?}",
+                                other
+                            )))
+                        }
                     };
-                    state.program_stack.extend(body.into_iter().rev());
-                    return vec![];
+                    schedule_body(&mut state.program_stack, body, &spans);
+                    return Ok(vec![]);
                 }
             }
-            panic!("match: no case matched value {:?}", value);
-        },
+            Err(SidError::InvalidArgument(format!(
+                "match: no case matched value {:?}",
+                value
+            )))
+        }),
     }
 }
@@ -1,12 +1,7 @@
-use crate::built_in::BuiltinEntry;
-use crate::c_ffi::{open_library, parse_c_header};
+use crate::built_in::{pop_arg, BuiltinEntry};
+use crate::c_ffi::parse_c_header;
 use crate::type_system::SidType;
-use crate::DataValue;
-
-fn pop_arg(args: &mut Vec<DataValue>, name: &str) -> DataValue {
-    args.pop()
-        .unwrap_or_else(|| panic!("{}: expected an argument", name))
-}
+use crate::{Capability, DataValue, SidError};
 
 fn cstring_to_string(cs: std::ffi::CString) -> String {
     cs.into_string()
@@ -23,11 +18,15 @@ fn stem_of(path: &str) -> String {
 
 pub fn c_load_header() -> BuiltinEntry {
     BuiltinEntry {
-        name: "c_load_header",
+        name: "c_load_header".to_owned(),
         args: vec![SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let arg = pop_arg(&mut args, "c_load_header");
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Ffi, "c_load_header")?;
+            let arg = pop_arg(&mut args, "c_load_header")?;
             let (header_path, lib_name) = match arg {
                 DataValue::Str(path) => {
                     let lib_name = stem_of(&path.to_string_lossy());
@@ -36,39 +35,57 @@ pub fn c_load_header() -> BuiltinEntry {
                 DataValue::List(mut items) if items.len() == 2 => {
                     let path = match items.remove(0) {
                         DataValue::Str(s) => cstring_to_string(s),
-                        other => panic!(
-                            "c_load_header: first list element must be Str, got {:?}",
-                            other
-                        ),
+                        other => {
+                            return Err(SidError::TypeMismatch(format!(
+                                "c_load_header: first list element must be Str, got {:?}",
+                                other
+                            )))
+                        }
                     };
                     let lib_name = match items.remove(0) {
                         DataValue::Str(s) => cstring_to_string(s),
-                        other => panic!(
-                            "c_load_header: second list element must be Str, got {:?}",
-                            other
-                        ),
+                        other => {
+                            return Err(SidError::TypeMismatch(format!(
+                                "c_load_header: second list element must be Str, got {:?}",
+                                other
+                            )))
+                        }
                     };
                     (path, lib_name)
                 }
-                other => panic!("c_load_header expects Str or [Str, Str], got {:?}", other),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "c_load_header expects Str or [Str, Str], got {:?}",
+                        other
+                    )))
+                }
             };
-            let sigs = parse_c_header(&header_path, &lib_name).unwrap();
+            let sigs = parse_c_header(&header_path, &lib_name).map_err(|e| {
+                SidError::FfiError(format!(
+                    "c_load_header: failed to load '{}': {}",
+                    header_path, e
+                ))
+            })?;
             let out_fields: Vec<(DataValue, DataValue)> = sigs
                 .into_iter()
                 .map(|s| (DataValue::Label(s.name.clone()), DataValue::CFuncSig(s)))
                 .collect();
-            vec![DataValue::Map(out_fields)]
-        },
+            Ok(vec![DataValue::Map(out_fields)])
+        }),
     }
 }
 
 pub fn c_link_lib() -> BuiltinEntry {
     BuiltinEntry {
-        name: "c_link_lib",
+        name: "c_link_lib".to_owned(),
         args: vec![SidType::Any],
         ret: vec![],
-        exec: |state, mut args| {
-            let arg = pop_arg(&mut args, "c_link_lib");
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Ffi, "c_link_lib")?;
+            let arg = pop_arg(&mut args, "c_link_lib")?;
             let (lib_path, lib_name) = match arg {
                 DataValue::Str(path) => {
                     let s = cstring_to_string(path.clone());
@@ -77,70 +94,105 @@ pub fn c_link_lib() -> BuiltinEntry {
                 DataValue::List(mut items) if items.len() == 2 => {
                     let path = match items.remove(0) {
                         DataValue::Str(s) => cstring_to_string(s),
-                        other => panic!(
-                            "c_link_lib: first list element must be Str, got {:?}",
-                            other
-                        ),
+                        other => {
+                            return Err(SidError::TypeMismatch(format!(
+                                "c_link_lib: first list element must be Str, got {:?}",
+                                other
+                            )))
+                        }
                     };
                     let name = match items.remove(0) {
                         DataValue::Str(s) => cstring_to_string(s),
-                        other => panic!(
-                            "c_link_lib: second list element must be Str, got {:?}",
-                            other
-                        ),
+                        other => {
+                            return Err(SidError::TypeMismatch(format!(
+                                "c_link_lib: second list element must be Str, got {:?}",
+                                other
+                            )))
+                        }
                     };
                     (path, name)
                 }
-                other => panic!("c_link_lib expects Str or [Str, Str], got {:?}", other),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "c_link_lib expects Str or [Str, Str], got {:?}",
+                        other
+                    )))
+                }
             };
             if !state.global_state.libraries.contains_key(lib_name.as_str()) {
-                let lib = open_library(&lib_path).unwrap();
+                let lib = state
+                    .global_state
+                    .side_effects
+                    .open_library(&lib_path)
+                    .map_err(|e| SidError::FfiError(format!("c_link_lib: {}", e)))?;
                 state.global_state.libraries.insert(lib_name, lib);
             }
-            vec![]
-        },
+            Ok(vec![])
+        }),
     }
 }
 
 pub fn ptr_cast() -> BuiltinEntry {
     BuiltinEntry {
-        name: "ptr_cast",
+        name: "ptr_cast".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let new_type = pop_arg(&mut args, "ptr_cast");
-            let pointer = pop_arg(&mut args, "ptr_cast");
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Pointers, "ptr_cast")?;
+            let new_type = pop_arg(&mut args, "ptr_cast")?;
+            let pointer = pop_arg(&mut args, "ptr_cast")?;
             let addr = match pointer {
                 DataValue::Pointer { addr, .. } => addr,
-                other => panic!(
-                    "ptr_cast: first argument must be a Pointer, got {:?}",
-                    other
-                ),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "ptr_cast: first argument must be a Pointer, got {:?}",
+                        other
+                    )))
+                }
             };
             let pointee_ty = match new_type {
                 DataValue::Type(ty) => ty,
-                other => panic!("ptr_cast: type argument must be a Type, got {:?}", other),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "ptr_cast: type argument must be a Type, got {:?}",
+                        other
+                    )))
+                }
             };
-            vec![DataValue::Pointer { addr, pointee_ty }]
-        },
+            Ok(vec![DataValue::Pointer { addr, pointee_ty }])
+        }),
     }
 }
 
 pub fn ptr_read_cstr() -> BuiltinEntry {
     BuiltinEntry {
-        name: "ptr_read_cstr",
+        name: "ptr_read_cstr".to_owned(),
         args: vec![SidType::Any],
         ret: vec![SidType::Str],
-        exec: |_state, mut args| match pop_arg(&mut args, "ptr_read_cstr") {
-            DataValue::Pointer { addr, .. } => {
-                let ptr = addr as *const std::ffi::c_char;
-                if ptr.is_null() {
-                    panic!("ptr_read_cstr: pointer is null");
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Pointers, "ptr_read_cstr")?;
+            match pop_arg(&mut args, "ptr_read_cstr")? {
+                DataValue::Pointer { addr, .. } => {
+                    let ptr = addr as *const std::ffi::c_char;
+                    if ptr.is_null() {
+                        return Err(SidError::FfiError(
+                            "ptr_read_cstr: pointer is null".to_owned(),
+                        ));
+                    }
+                    let cs = unsafe { std::ffi::CStr::from_ptr(ptr) }.to_owned();
+                    Ok(vec![DataValue::Str(cs)])
                 }
-                let cs = unsafe { std::ffi::CStr::from_ptr(ptr) }.to_owned();
-                vec![DataValue::Str(cs)]
+                other => Err(SidError::TypeMismatch(format!(
+                    "ptr_read_cstr expects Pointer, got {:?}",
+                    other
+                ))),
             }
-            other => panic!("ptr_read_cstr expects Pointer, got {:?}", other),
-        },
+        }),
     }
 }
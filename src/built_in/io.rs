@@ -0,0 +1,226 @@
+use std::ffi::CString;
+use std::path::PathBuf;
+
+use crate::built_in::{pop_arg, pop_str, BuiltinEntry};
+use crate::type_system::SidType;
+use crate::{Capability, DataValue, ExeState, SidError, ToSyntax};
+
+fn pop_path(args: &mut Vec<DataValue>, name: &str) -> Result<PathBuf, SidError> {
+    let path = pop_str(args, name)?;
+    path.into_string().map(PathBuf::from).map_err(|e| {
+        SidError::InvalidArgument(format!(
+            "{}: path is not valid UTF-8: {:?}",
+            name,
+            e.into_cstring()
+        ))
+    })
+}
+
+/// Wrap bytes read from outside as a `Str`, which may not contain NUL.
+fn str_value(bytes: Vec<u8>, name: &str) -> Result<DataValue, SidError> {
+    CString::new(bytes)
+        .map(DataValue::Str)
+        .map_err(|_| SidError::InvalidArgument(format!("{}: input contains a NUL byte", name)))
+}
+
+fn io_error(name: &str, e: std::io::Error) -> SidError {
+    SidError::Io(format!("{}: {}", name, e))
+}
+
+/// The bytes `print` and friends write for `value`: the contents of a string
+/// or char, and the source syntax of anything else.
+fn printed(value: &DataValue) -> Vec<u8> {
+    match value {
+        DataValue::Str(s) => s.as_bytes().to_vec(),
+        DataValue::Char(c) => c.as_bytes().to_vec(),
+        other => other.to_syntax().into_bytes(),
+    }
+}
+
+fn print_with(
+    state: &mut ExeState,
+    mut args: Vec<DataValue>,
+    name: &str,
+    newline: bool,
+    stderr: bool,
+) -> Result<Vec<DataValue>, SidError> {
+    let mut bytes = printed(&pop_arg(&mut args, name)?);
+    if newline {
+        bytes.push(b'\n');
+    }
+    let effects = &mut state.global_state.side_effects;
+    if stderr {
+        effects.write_stderr(&bytes)
+    } else {
+        effects.write_stdout(&bytes)
+    }
+    .map_err(|e| io_error(name, e))?;
+    Ok(vec![])
+}
+
+pub fn print() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "print".to_owned(),
+        args: vec![SidType::Any],
+        ret: vec![],
+        exec: Box::new(|state, args| print_with(state, args, "print", false, false)),
+    }
+}
+
+pub fn println() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "println".to_owned(),
+        args: vec![SidType::Any],
+        ret: vec![],
+        exec: Box::new(|state, args| print_with(state, args, "println", true, false)),
+    }
+}
+
+pub fn eprint() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "eprint".to_owned(),
+        args: vec![SidType::Any],
+        ret: vec![],
+        exec: Box::new(|state, args| print_with(state, args, "eprint", false, true)),
+    }
+}
+
+pub fn read_line() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "read_line".to_owned(),
+        args: vec![],
+        ret: vec![SidType::Str],
+        exec: Box::new(|state, _args| {
+            let line = state
+                .global_state
+                .side_effects
+                .read_line()
+                .map_err(|e| io_error("read_line", e))?;
+            Ok(vec![str_value(line.into_bytes(), "read_line")?])
+        }),
+    }
+}
+
+pub fn read_all_stdin() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "read_all_stdin".to_owned(),
+        args: vec![],
+        ret: vec![SidType::Str],
+        exec: Box::new(|state, _args| {
+            let bytes = state
+                .global_state
+                .side_effects
+                .read_all_stdin()
+                .map_err(|e| io_error("read_all_stdin", e))?;
+            Ok(vec![str_value(bytes, "read_all_stdin")?])
+        }),
+    }
+}
+
+pub fn read_file() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "read_file".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::Str],
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Filesystem, "read_file")?;
+            let path = pop_path(&mut args, "read_file")?;
+            let bytes = state
+                .global_state
+                .side_effects
+                .read_file(&path)
+                .map_err(|e| io_error(&format!("read_file: {}", path.display()), e))?;
+            Ok(vec![str_value(bytes, "read_file")?])
+        }),
+    }
+}
+
+pub fn write_file() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "write_file".to_owned(),
+        args: vec![SidType::Str, SidType::Str],
+        ret: vec![],
+        exec: Box::new(|state, mut args| {
+            let contents = pop_str(&mut args, "write_file")?;
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Filesystem, "write_file")?;
+            let path = pop_path(&mut args, "write_file")?;
+            state
+                .global_state
+                .side_effects
+                .write_file(&path, contents.as_bytes())
+                .map_err(|e| io_error(&format!("write_file: {}", path.display()), e))?;
+            Ok(vec![])
+        }),
+    }
+}
+
+pub fn append_file() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "append_file".to_owned(),
+        args: vec![SidType::Str, SidType::Str],
+        ret: vec![],
+        exec: Box::new(|state, mut args| {
+            let contents = pop_str(&mut args, "append_file")?;
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Filesystem, "append_file")?;
+            let path = pop_path(&mut args, "append_file")?;
+            state
+                .global_state
+                .side_effects
+                .append_file(&path, contents.as_bytes())
+                .map_err(|e| io_error(&format!("append_file: {}", path.display()), e))?;
+            Ok(vec![])
+        }),
+    }
+}
+
+pub fn exists() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "exists".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Filesystem, "exists")?;
+            let path = pop_path(&mut args, "exists")?;
+            Ok(vec![DataValue::Bool(
+                state.global_state.side_effects.exists(&path),
+            )])
+        }),
+    }
+}
+
+pub fn list_dir() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "list_dir".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::List(Box::new(SidType::Str))],
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Filesystem, "list_dir")?;
+            let path = pop_path(&mut args, "list_dir")?;
+            let names = state
+                .global_state
+                .side_effects
+                .list_dir(&path)
+                .map_err(|e| io_error(&format!("list_dir: {}", path.display()), e))?;
+            let names = names
+                .into_iter()
+                .map(|n| str_value(n.into_bytes(), "list_dir"))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(vec![DataValue::List(names)])
+        }),
+    }
+}
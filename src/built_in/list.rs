@@ -0,0 +1,507 @@
+use std::cmp::Ordering;
+
+use crate::built_in::compare::compare;
+use crate::built_in::string;
+use crate::built_in::{pop_arg, BuiltinEntry};
+use crate::invoke::ExeState;
+use crate::type_system::SidType;
+use crate::{DataValue, ListIter, ListOp, ProgramValue, SidError, TemplateValue};
+
+fn pop_list(args: &mut Vec<DataValue>, name: &str) -> Result<Vec<DataValue>, SidError> {
+    match pop_arg(args, name)? {
+        DataValue::List(items) => Ok(items),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected List, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+fn pop_int(args: &mut Vec<DataValue>, name: &str) -> Result<i64, SidError> {
+    match pop_arg(args, name)? {
+        DataValue::Int(n) => Ok(n),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected Int, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+fn any_list() -> SidType {
+    SidType::List(Box::new(SidType::Any))
+}
+
+/// `{str, list}` — the sequences accepted by `len`, `concat` and `slice`.
+fn sequence() -> SidType {
+    SidType::Literal(Box::new(DataValue::Set(vec![
+        DataValue::Type(SidType::Str),
+        DataValue::Type(any_list()),
+    ])))
+}
+
+/// Check that `index` is an element of a list of `len` elements, or one past
+/// the end if `end_ok`.
+fn position(index: i64, len: usize, end_ok: bool, name: &str) -> Result<usize, SidError> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&i| i < len || (end_ok && i == len))
+        .ok_or_else(|| {
+            SidError::InvalidArgument(format!(
+                "{}: index {} is out of bounds for a list of {} element(s)",
+                name, index, len
+            ))
+        })
+}
+
+// `len`, `concat` and `slice` work on strings too; the string cases are
+// implemented in `string`.
+
+pub fn len() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "len".to_owned(),
+        args: vec![sequence()],
+        ret: vec![SidType::Int],
+        exec: Box::new(|_state, mut args| {
+            Ok(vec![match pop_arg(&mut args, "len")? {
+                DataValue::Str(s) => string::len_str(&s),
+                DataValue::List(items) => DataValue::Int(items.len() as i64),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "len: expected Str or List, got {:?}",
+                        other
+                    )))
+                }
+            }])
+        }),
+    }
+}
+
+pub fn concat() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "concat".to_owned(),
+        args: vec![sequence(), sequence()],
+        ret: vec![sequence()],
+        exec: Box::new(|_state, mut args| {
+            let b = pop_arg(&mut args, "concat")?;
+            let a = pop_arg(&mut args, "concat")?;
+            Ok(vec![match (a, b) {
+                (DataValue::Str(a), DataValue::Str(b)) => string::concat_str(a, b)?,
+                (DataValue::List(mut a), DataValue::List(b)) => {
+                    a.extend(b);
+                    DataValue::List(a)
+                }
+                (a, b) => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "concat: expected two Strs or two Lists, got {:?} and {:?}",
+                        a, b
+                    )))
+                }
+            }])
+        }),
+    }
+}
+
+pub fn slice() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "slice".to_owned(),
+        args: vec![SidType::Int, SidType::Int, sequence()],
+        ret: vec![sequence()],
+        exec: Box::new(|_state, mut args| {
+            let end = pop_int(&mut args, "slice")?;
+            let start = pop_int(&mut args, "slice")?;
+            Ok(vec![match pop_arg(&mut args, "slice")? {
+                DataValue::Str(s) => string::slice_str(&s, start, end)?,
+                DataValue::List(items) => {
+                    let start = position(start, items.len(), true, "slice")?;
+                    let end = position(end, items.len(), true, "slice")?;
+                    if start > end {
+                        return Err(SidError::InvalidArgument(format!(
+                            "slice: start {} is after end {}",
+                            start, end
+                        )));
+                    }
+                    DataValue::List(items[start..end].to_vec())
+                }
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "slice: expected Str or List, got {:?}",
+                        other
+                    )))
+                }
+            }])
+        }),
+    }
+}
+
+pub fn list_get() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "list_get".to_owned(),
+        args: vec![SidType::Int, any_list()],
+        ret: vec![SidType::Any],
+        exec: Box::new(|_state, mut args| {
+            let index = pop_int(&mut args, "list_get")?;
+            let mut items = pop_list(&mut args, "list_get")?;
+            let index = position(index, items.len(), false, "list_get")?;
+            Ok(vec![items.swap_remove(index)])
+        }),
+    }
+}
+
+pub fn list_set() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "list_set".to_owned(),
+        args: vec![SidType::Any, SidType::Int, any_list()],
+        ret: vec![any_list()],
+        exec: Box::new(|_state, mut args| {
+            let value = pop_arg(&mut args, "list_set")?;
+            let index = pop_int(&mut args, "list_set")?;
+            let mut items = pop_list(&mut args, "list_set")?;
+            let index = position(index, items.len(), false, "list_set")?;
+            items[index] = value;
+            Ok(vec![DataValue::List(items)])
+        }),
+    }
+}
+
+pub fn push() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "push".to_owned(),
+        args: vec![SidType::Any, any_list()],
+        ret: vec![any_list()],
+        exec: Box::new(|_state, mut args| {
+            let value = pop_arg(&mut args, "push")?;
+            let mut items = pop_list(&mut args, "push")?;
+            items.push(value);
+            Ok(vec![DataValue::List(items)])
+        }),
+    }
+}
+
+pub fn pop() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "pop".to_owned(),
+        args: vec![any_list()],
+        ret: vec![SidType::Any, any_list()],
+        exec: Box::new(|_state, mut args| {
+            let mut items = pop_list(&mut args, "pop")?;
+            let last = items
+                .pop()
+                .ok_or_else(|| SidError::InvalidArgument("pop: list is empty".to_owned()))?;
+            Ok(vec![DataValue::List(items), last])
+        }),
+    }
+}
+
+pub fn reverse() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "reverse".to_owned(),
+        args: vec![any_list()],
+        ret: vec![any_list()],
+        exec: Box::new(|_state, mut args| {
+            let mut items = pop_list(&mut args, "reverse")?;
+            items.reverse();
+            Ok(vec![DataValue::List(items)])
+        }),
+    }
+}
+
+pub fn sort() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "sort".to_owned(),
+        args: vec![any_list()],
+        ret: vec![any_list()],
+        exec: Box::new(|_state, mut args| {
+            let mut items = pop_list(&mut args, "sort")?;
+            // `sort_by` can't fail, so remember the first error and report it
+            // once the (then meaningless) sort is done.
+            let mut error = None;
+            items.sort_by(|a, b| match compare(a, b, "sort") {
+                Ok(Some(ordering)) => ordering,
+                Ok(None) => {
+                    error.get_or_insert_with(|| {
+                        SidError::InvalidArgument(format!(
+                            "sort: {:?} and {:?} are unordered",
+                            a, b
+                        ))
+                    });
+                    Ordering::Equal
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                    Ordering::Equal
+                }
+            });
+            match error {
+                Some(e) => Err(e),
+                None => Ok(vec![DataValue::List(items)]),
+            }
+        }),
+    }
+}
+
+/// The longest list `range` builds, so a script can't exhaust the host's
+/// memory (or abort it) with one call.
+const MAX_RANGE_LEN: i64 = 10_000_000;
+
+pub fn range() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "range".to_owned(),
+        args: vec![SidType::Int, SidType::Int],
+        ret: vec![SidType::List(Box::new(SidType::Int))],
+        exec: Box::new(|_state, mut args| {
+            let end = pop_int(&mut args, "range")?;
+            let start = pop_int(&mut args, "range")?;
+            // Overflowing `end - start` is far above the cap as well.
+            let len = end.checked_sub(start).unwrap_or(i64::MAX);
+            if len > MAX_RANGE_LEN {
+                return Err(SidError::InvalidArgument(format!(
+                    "range: {}..{} has more than {} elements",
+                    start, end, MAX_RANGE_LEN
+                )));
+            }
+            Ok(vec![DataValue::List(
+                (start..end).map(DataValue::Int).collect(),
+            )])
+        }),
+    }
+}
+
+// ── Higher-order built-ins ────────────────────────────────────────────────────
+//
+// These don't call their function from Rust.  Each call is scheduled on the
+// program stack above a `ListIter` sentinel, which collects the result and
+// schedules the next call, the same way `while_do` drives its loop with
+// `CondLoop`.
+
+impl ListOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            ListOp::Map => "map",
+            ListOp::Filter => "filter",
+            ListOp::Fold => "fold",
+            ListOp::Each => "each",
+            ListOp::Any => "any",
+            ListOp::All => "all",
+            ListOp::SortBy { .. } => "sort_by",
+        }
+    }
+}
+
+fn truth(value: DataValue, name: &str) -> Result<bool, SidError> {
+    match value {
+        DataValue::Bool(b) => Ok(b),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: function must return a Bool, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+impl ListIter {
+    /// Take the result of the last call (if any) off the data stack, then
+    /// schedule the next call, or push the final result if there is none.
+    pub(crate) fn step(
+        mut self,
+        data_stack: &mut Vec<TemplateValue>,
+        program_stack: &mut Vec<ProgramValue>,
+    ) -> Result<(), SidError> {
+        let name = self.op.name();
+        if let Some(current) = self.current.take() {
+            let expected = match self.op {
+                ListOp::Each => self.expected_len,
+                _ => self.expected_len + 1,
+            };
+            if data_stack.len() != expected {
+                return Err(SidError::LoopInvariant(format!(
+                    "{}: function must leave exactly {} value(s) (expected stack size {}, got {})",
+                    name,
+                    expected - self.expected_len,
+                    expected,
+                    data_stack.len()
+                )));
+            }
+            if !matches!(self.op, ListOp::Each | ListOp::Fold) {
+                let result = match data_stack.pop() {
+                    Some(TemplateValue::Literal(ProgramValue::Data(v))) => v,
+                    other => {
+                        return Err(SidError::TypeMismatch(format!(
+                            "{}: function result is not a concrete value: {:?}",
+                            name, other
+                        )))
+                    }
+                };
+                match self.op {
+                    ListOp::Map => self.done.push(result),
+                    ListOp::Filter => {
+                        if truth(result, name)? {
+                            self.done.push(current);
+                        }
+                    }
+                    ListOp::Any | ListOp::All => {
+                        // Stop at the first element that decides the answer.
+                        let found = truth(result, name)?;
+                        if found == (self.op == ListOp::Any) {
+                            data_stack.push(DataValue::Bool(found).into());
+                            return Ok(());
+                        }
+                    }
+                    ListOp::SortBy { lo, hi } => {
+                        let mid = (lo + hi) / 2;
+                        let (lo, hi) = if truth(result, name)? {
+                            (lo, mid)
+                        } else {
+                            (mid + 1, hi)
+                        };
+                        if lo < hi {
+                            self.op = ListOp::SortBy { lo, hi };
+                            self.call(current, data_stack, program_stack);
+                            return Ok(());
+                        }
+                        self.done.insert(lo, current);
+                    }
+                    ListOp::Fold | ListOp::Each => unreachable!(),
+                }
+            }
+        }
+        while let Some(next) = self.pending.pop() {
+            if let ListOp::SortBy { .. } = self.op {
+                if self.done.is_empty() {
+                    self.done.push(next);
+                    continue;
+                }
+                self.op = ListOp::SortBy {
+                    lo: 0,
+                    hi: self.done.len(),
+                };
+            }
+            self.call(next, data_stack, program_stack);
+            return Ok(());
+        }
+        match self.op {
+            ListOp::Map | ListOp::Filter | ListOp::SortBy { .. } => {
+                data_stack.push(DataValue::List(self.done).into())
+            }
+            ListOp::Any => data_stack.push(DataValue::Bool(false).into()),
+            ListOp::All => data_stack.push(DataValue::Bool(true).into()),
+            ListOp::Fold | ListOp::Each => {}
+        }
+        Ok(())
+    }
+
+    /// Push the arguments for calling `f` on `element` and schedule the call,
+    /// with this sentinel below it.  `sort_by` also passes the element of the
+    /// sorted prefix that `element` is compared with.
+    fn call(
+        mut self,
+        element: DataValue,
+        data_stack: &mut Vec<TemplateValue>,
+        program_stack: &mut Vec<ProgramValue>,
+    ) {
+        self.expected_len = match self.op {
+            // Don't count the accumulator on top of the stack.
+            ListOp::Fold => data_stack.len().saturating_sub(1),
+            _ => data_stack.len(),
+        };
+        data_stack.push(element.clone().into());
+        if let ListOp::SortBy { lo, hi } = self.op {
+            data_stack.push(self.done[(lo + hi) / 2].clone().into());
+        }
+        let f = self.f.clone();
+        self.current = Some(element);
+        program_stack.push(ProgramValue::ListIter(self));
+        program_stack.push(ProgramValue::Invoke);
+        program_stack.push(ProgramValue::Data(f));
+    }
+}
+
+/// Shared implementation of the higher-order built-ins: pops `list f` and
+/// starts running `op` over the list.
+fn higher_order(
+    state: &mut ExeState,
+    mut args: Vec<DataValue>,
+    op: ListOp,
+) -> Result<Vec<DataValue>, SidError> {
+    let f = pop_arg(&mut args, op.name())?;
+    let mut pending = pop_list(&mut args, op.name())?;
+    pending.reverse();
+    ListIter {
+        op,
+        f,
+        pending,
+        current: None,
+        done: Vec::new(),
+        expected_len: 0,
+    }
+    .step(&mut state.data_stack, &mut state.program_stack)?;
+    Ok(vec![])
+}
+
+pub fn map() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "map".to_owned(),
+        args: vec![SidType::Any, any_list()],
+        ret: vec![],
+        exec: Box::new(|state, args| higher_order(state, args, ListOp::Map)),
+    }
+}
+
+pub fn filter() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "filter".to_owned(),
+        args: vec![SidType::Any, any_list()],
+        ret: vec![],
+        exec: Box::new(|state, args| higher_order(state, args, ListOp::Filter)),
+    }
+}
+
+pub fn each() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "each".to_owned(),
+        args: vec![SidType::Any, any_list()],
+        ret: vec![],
+        exec: Box::new(|state, args| higher_order(state, args, ListOp::Each)),
+    }
+}
+
+pub fn any() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "any".to_owned(),
+        args: vec![SidType::Any, any_list()],
+        ret: vec![],
+        exec: Box::new(|state, args| higher_order(state, args, ListOp::Any)),
+    }
+}
+
+pub fn all() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "all".to_owned(),
+        args: vec![SidType::Any, any_list()],
+        ret: vec![],
+        exec: Box::new(|state, args| higher_order(state, args, ListOp::All)),
+    }
+}
+
+pub fn sort_by() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "sort_by".to_owned(),
+        args: vec![SidType::Any, any_list()],
+        ret: vec![],
+        exec: Box::new(|state, args| higher_order(state, args, ListOp::SortBy { lo: 0, hi: 0 })),
+    }
+}
+
+pub fn fold() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "fold".to_owned(),
+        args: vec![SidType::Any, SidType::Any, any_list()],
+        ret: vec![],
+        exec: Box::new(|state, mut args| {
+            // Leave the initial accumulator on the stack for the first call.
+            let f = pop_arg(&mut args, "fold")?;
+            let init = pop_arg(&mut args, "fold")?;
+            state.data_stack.push(init.into());
+            args.push(f);
+            higher_order(state, args, ListOp::Fold)
+        }),
+    }
+}
@@ -0,0 +1,106 @@
+use crate::built_in::{pop_arg, BuiltinEntry};
+use crate::invoke::ExeState;
+use crate::type_system::SidType;
+use crate::{DataValue, ProgramValue, SidError};
+
+fn pop_bool(args: &mut Vec<DataValue>, name: &str) -> Result<bool, SidError> {
+    match pop_arg(args, name)? {
+        DataValue::Bool(b) => Ok(b),
+        other => Err(SidError::TypeMismatch(format!(
+            "{} expects Bool, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+/// Shared implementation of the strict binary operators.
+fn binary(
+    mut args: Vec<DataValue>,
+    name: &str,
+    op: fn(bool, bool) -> bool,
+) -> Result<Vec<DataValue>, SidError> {
+    let b = pop_bool(&mut args, name)?;
+    let a = pop_bool(&mut args, name)?;
+    Ok(vec![DataValue::Bool(op(a, b))])
+}
+
+pub fn and() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "and".to_owned(),
+        args: vec![SidType::Bool, SidType::Bool],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| binary(args, "and", |a, b| a && b)),
+    }
+}
+
+pub fn or() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "or".to_owned(),
+        args: vec![SidType::Bool, SidType::Bool],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| binary(args, "or", |a, b| a || b)),
+    }
+}
+
+pub fn xor() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "xor".to_owned(),
+        args: vec![SidType::Bool, SidType::Bool],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| binary(args, "xor", |a, b| a ^ b)),
+    }
+}
+
+/// Shared implementation of `and_then` / `or_else`.
+///
+/// If the left-hand `Bool` equals `short_circuit_on` it is pushed back as the
+/// result and the substack never runs.  Otherwise the substack is scheduled
+/// on the program stack, followed by a `TypeCheck` sentinel asserting that it
+/// left a `Bool` on top.
+fn short_circuit(
+    state: &mut ExeState,
+    mut args: Vec<DataValue>,
+    name: &str,
+    short_circuit_on: bool,
+) -> Result<Vec<DataValue>, SidError> {
+    let rhs = pop_arg(&mut args, name)?;
+    let lhs = pop_bool(&mut args, name)?;
+    match &rhs {
+        DataValue::Substack { .. } => {}
+        other => {
+            return Err(SidError::TypeMismatch(format!(
+                "{}: right-hand side must be a Substack, got {:?}",
+                name, other
+            )))
+        }
+    }
+    if lhs == short_circuit_on {
+        return Ok(vec![DataValue::Bool(lhs)]);
+    }
+    state.program_stack.push(ProgramValue::TypeCheck {
+        types: Some(vec![SidType::Bool]),
+        context: format!("{} right-hand side", name),
+        block_placed: false,
+    });
+    state.program_stack.push(ProgramValue::Invoke);
+    state.program_stack.push(ProgramValue::Data(rhs));
+    Ok(vec![])
+}
+
+pub fn and_then() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "and_then".to_owned(),
+        args: vec![SidType::Any, SidType::Bool],
+        ret: vec![],
+        exec: Box::new(|state, args| short_circuit(state, args, "and_then", false)),
+    }
+}
+
+pub fn or_else() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "or_else".to_owned(),
+        args: vec![SidType::Any, SidType::Bool],
+        ret: vec![],
+        exec: Box::new(|state, args| short_circuit(state, args, "or_else", true)),
+    }
+}
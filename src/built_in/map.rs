@@ -0,0 +1,185 @@
+use crate::built_in::{label_or_any, pop_arg, BuiltinEntry};
+use crate::type_system::SidType;
+use crate::{DataValue, SidError};
+
+fn pop_map(args: &mut Vec<DataValue>, name: &str) -> Result<Vec<(DataValue, DataValue)>, SidError> {
+    match pop_arg(args, name)? {
+        DataValue::Map(entries) => Ok(entries),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected Map, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+fn any_map() -> SidType {
+    SidType::Map {
+        key: Box::new(SidType::Any),
+        value: Box::new(SidType::Any),
+    }
+}
+
+fn any_list() -> SidType {
+    SidType::List(Box::new(SidType::Any))
+}
+
+fn position(entries: &[(DataValue, DataValue)], key: &DataValue) -> Option<usize> {
+    entries.iter().position(|(k, _)| k == key)
+}
+
+/// Set `key` to `value`, in place if the key is present so that the order of
+/// the entries (which struct types match against) is kept, and at the end
+/// otherwise.
+fn insert(entries: &mut Vec<(DataValue, DataValue)>, key: DataValue, value: DataValue) {
+    match position(entries, &key) {
+        Some(i) => entries[i].1 = value,
+        None => entries.push((key, value)),
+    }
+}
+
+pub fn map_get() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "map_get".to_owned(),
+        args: vec![label_or_any(), any_map()],
+        ret: vec![SidType::Any],
+        exec: Box::new(|_state, mut args| {
+            let key = pop_arg(&mut args, "map_get")?;
+            let mut entries = pop_map(&mut args, "map_get")?;
+            match position(&entries, &key) {
+                Some(i) => Ok(vec![entries.swap_remove(i).1]),
+                None => Err(SidError::InvalidArgument(format!(
+                    "map_get: key {:?} not found",
+                    key
+                ))),
+            }
+        }),
+    }
+}
+
+pub fn map_insert() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "map_insert".to_owned(),
+        args: vec![SidType::Any, label_or_any(), any_map()],
+        ret: vec![any_map()],
+        exec: Box::new(|_state, mut args| {
+            let value = pop_arg(&mut args, "map_insert")?;
+            let key = pop_arg(&mut args, "map_insert")?;
+            let mut entries = pop_map(&mut args, "map_insert")?;
+            insert(&mut entries, key, value);
+            Ok(vec![DataValue::Map(entries)])
+        }),
+    }
+}
+
+pub fn map_remove() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "map_remove".to_owned(),
+        args: vec![label_or_any(), any_map()],
+        ret: vec![any_map()],
+        exec: Box::new(|_state, mut args| {
+            let key = pop_arg(&mut args, "map_remove")?;
+            let mut entries = pop_map(&mut args, "map_remove")?;
+            entries.retain(|(k, _)| *k != key);
+            Ok(vec![DataValue::Map(entries)])
+        }),
+    }
+}
+
+pub fn map_has() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "map_has".to_owned(),
+        args: vec![label_or_any(), any_map()],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, mut args| {
+            let key = pop_arg(&mut args, "map_has")?;
+            let entries = pop_map(&mut args, "map_has")?;
+            Ok(vec![DataValue::Bool(position(&entries, &key).is_some())])
+        }),
+    }
+}
+
+pub fn keys() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "keys".to_owned(),
+        args: vec![any_map()],
+        ret: vec![any_list()],
+        exec: Box::new(|_state, mut args| {
+            let entries = pop_map(&mut args, "keys")?;
+            Ok(vec![DataValue::List(
+                entries.into_iter().map(|(k, _)| k).collect(),
+            )])
+        }),
+    }
+}
+
+pub fn values() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "values".to_owned(),
+        args: vec![any_map()],
+        ret: vec![any_list()],
+        exec: Box::new(|_state, mut args| {
+            let entries = pop_map(&mut args, "values")?;
+            Ok(vec![DataValue::List(
+                entries.into_iter().map(|(_, v)| v).collect(),
+            )])
+        }),
+    }
+}
+
+pub fn entries() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "entries".to_owned(),
+        args: vec![any_map()],
+        ret: vec![any_list()],
+        exec: Box::new(|_state, mut args| {
+            let entries = pop_map(&mut args, "entries")?;
+            Ok(vec![DataValue::List(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| DataValue::List(vec![k, v]))
+                    .collect(),
+            )])
+        }),
+    }
+}
+
+pub fn merge() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "merge".to_owned(),
+        args: vec![any_map(), any_map()],
+        ret: vec![any_map()],
+        exec: Box::new(|_state, mut args| {
+            let b = pop_map(&mut args, "merge")?;
+            let mut a = pop_map(&mut args, "merge")?;
+            for (k, v) in b {
+                insert(&mut a, k, v);
+            }
+            Ok(vec![DataValue::Map(a)])
+        }),
+    }
+}
+
+pub fn with_field() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "with_field".to_owned(),
+        args: vec![SidType::Any, SidType::Label, any_map()],
+        ret: vec![any_map()],
+        exec: Box::new(|_state, mut args| {
+            let value = pop_arg(&mut args, "with_field")?;
+            let field = pop_arg(&mut args, "with_field")?;
+            let mut entries = pop_map(&mut args, "with_field")?;
+            // A struct update never adds a field, so the result still matches
+            // the struct type the input did (apart from the new value).
+            match position(&entries, &field) {
+                Some(i) => entries[i].1 = value,
+                None => {
+                    return Err(SidError::UndefinedLabel(format!(
+                        "with_field: struct has no field {:?}",
+                        field
+                    )))
+                }
+            }
+            Ok(vec![DataValue::Map(entries)])
+        }),
+    }
+}
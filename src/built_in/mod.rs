@@ -1,20 +1,64 @@
+mod arith;
+mod compare;
 mod control_flow;
 mod ffi;
+mod io;
+mod list;
+mod logic;
+mod map;
 mod scope;
+mod set;
 mod stack;
+mod string;
+mod system;
 mod type_ops;
 
 use std::collections::HashMap;
+use std::ffi::CString;
 
 use crate::invoke::ExeState;
 use crate::type_system::SidType;
-use crate::DataValue;
+use crate::{DataValue, SidError};
+
+/// The implementation of a built-in: takes its arguments in stack order (the
+/// top of the stack last) and returns the values to push, in the same order.
+pub type BuiltinFn = dyn Fn(&mut ExeState, Vec<DataValue>) -> Result<Vec<DataValue>, SidError>;
 
 pub struct BuiltinEntry {
-    pub name: &'static str,
+    pub name: String,
     pub args: Vec<SidType>,
     pub ret: Vec<SidType>,
-    pub exec: fn(&mut ExeState, Vec<DataValue>) -> Vec<DataValue>,
+    /// Boxed so hosts can register capturing closures next to the plain
+    /// functions the interpreter ships with.
+    pub exec: Box<BuiltinFn>,
+}
+
+/// Take the next argument off `args` (the top of the stack first), failing as
+/// built-in `name` would on a short stack.
+pub(super) fn pop_arg(args: &mut Vec<DataValue>, name: &str) -> Result<DataValue, SidError> {
+    args.pop()
+        .ok_or_else(|| SidError::StackUnderflow(format!("{}: expected an argument", name)))
+}
+
+/// [`pop_arg`] for an argument that must be a `Str`.
+pub(super) fn pop_str(args: &mut Vec<DataValue>, name: &str) -> Result<CString, SidError> {
+    match pop_arg(args, name)? {
+        DataValue::Str(s) => Ok(s),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected Str, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+/// `{label, any}`: an argument of any kind, with a label kept as the label
+/// rather than resolved through scope.  Used for map keys and set items,
+/// which are often labels.
+pub(super) fn label_or_any() -> SidType {
+    SidType::Literal(Box::new(DataValue::Set(vec![
+        DataValue::Type(SidType::Label),
+        DataValue::Type(SidType::Any),
+    ])))
 }
 
 pub fn default_scope() -> HashMap<String, DataValue> {
@@ -64,45 +108,119 @@ pub fn default_scope() -> HashMap<String, DataValue> {
     m
 }
 
-fn register_shared(m: &mut HashMap<&'static str, BuiltinEntry>) {
-    m.insert("get", scope::get());
-    m.insert("get_local", scope::get_local());
-    m.insert("get_global", scope::get_global());
-    m.insert("load_scope", scope::load_scope());
-    m.insert("local", scope::local());
-    m.insert("load_local", scope::load_local());
-    m.insert("clone", stack::clone());
-    m.insert("drop", stack::drop());
-    m.insert("eq", stack::eq());
-    m.insert("assert", stack::assert_builtin());
-    m.insert("not", stack::not());
-    m.insert("debug_stack", stack::debug_stack());
-    m.insert("c_load_header", ffi::c_load_header());
-    m.insert("ptr_cast", ffi::ptr_cast());
-    m.insert("fn", type_ops::fn_type());
-    m.insert("ptr", type_ops::ptr_type());
-    m.insert("list", type_ops::list_type());
-    m.insert("require", type_ops::require_type());
-    m.insert("exclude", type_ops::exclude_type());
-    m.insert("typed_args", type_ops::typed_args());
-    m.insert("typed_rets", type_ops::typed_rets());
-    m.insert("untyped_args", type_ops::untyped_args());
-    m.insert("untyped_rets", type_ops::untyped_rets());
+fn register_shared(m: &mut HashMap<String, BuiltinEntry>) {
+    m.insert("get".to_owned(), scope::get());
+    m.insert("get_local".to_owned(), scope::get_local());
+    m.insert("get_global".to_owned(), scope::get_global());
+    m.insert("load_scope".to_owned(), scope::load_scope());
+    m.insert("local".to_owned(), scope::local());
+    m.insert("load_local".to_owned(), scope::load_local());
+    m.insert("clone".to_owned(), stack::clone());
+    m.insert("drop".to_owned(), stack::drop());
+    m.insert("eq".to_owned(), stack::eq());
+    m.insert("assert".to_owned(), stack::assert_builtin());
+    m.insert("not".to_owned(), stack::not());
+    m.insert("lt".to_owned(), compare::lt());
+    m.insert("le".to_owned(), compare::le());
+    m.insert("gt".to_owned(), compare::gt());
+    m.insert("ge".to_owned(), compare::ge());
+    m.insert("and".to_owned(), logic::and());
+    m.insert("or".to_owned(), logic::or());
+    m.insert("xor".to_owned(), logic::xor());
+    m.insert("debug_stack".to_owned(), stack::debug_stack());
+    m.insert("add".to_owned(), arith::add());
+    m.insert("sub".to_owned(), arith::sub());
+    m.insert("mul".to_owned(), arith::mul());
+    m.insert("div".to_owned(), arith::div());
+    m.insert("mod".to_owned(), arith::mod_builtin());
+    m.insert("neg".to_owned(), arith::neg());
+    m.insert("len".to_owned(), list::len());
+    m.insert("concat".to_owned(), list::concat());
+    m.insert("slice".to_owned(), list::slice());
+    m.insert("list_get".to_owned(), list::list_get());
+    m.insert("list_set".to_owned(), list::list_set());
+    m.insert("push".to_owned(), list::push());
+    m.insert("pop".to_owned(), list::pop());
+    m.insert("reverse".to_owned(), list::reverse());
+    m.insert("sort".to_owned(), list::sort());
+    m.insert("range".to_owned(), list::range());
+    m.insert("map_get".to_owned(), map::map_get());
+    m.insert("map_insert".to_owned(), map::map_insert());
+    m.insert("map_remove".to_owned(), map::map_remove());
+    m.insert("map_has".to_owned(), map::map_has());
+    m.insert("keys".to_owned(), map::keys());
+    m.insert("values".to_owned(), map::values());
+    m.insert("entries".to_owned(), map::entries());
+    m.insert("merge".to_owned(), map::merge());
+    m.insert("with_field".to_owned(), map::with_field());
+    m.insert("union".to_owned(), set::union());
+    m.insert("intersection".to_owned(), set::intersection());
+    m.insert("difference".to_owned(), set::difference());
+    m.insert("contains".to_owned(), set::contains());
+    m.insert("insert".to_owned(), set::insert());
+    m.insert("remove".to_owned(), set::remove());
+    m.insert("grapheme_len".to_owned(), string::grapheme_len());
+    m.insert("split".to_owned(), string::split());
+    m.insert("join".to_owned(), string::join());
+    m.insert("find".to_owned(), string::find());
+    m.insert("replace".to_owned(), string::replace());
+    m.insert("trim".to_owned(), string::trim());
+    m.insert("upper".to_owned(), string::upper());
+    m.insert("lower".to_owned(), string::lower());
+    m.insert("starts_with".to_owned(), string::starts_with());
+    m.insert("ends_with".to_owned(), string::ends_with());
+    m.insert("chars".to_owned(), string::chars());
+    m.insert("c_load_header".to_owned(), ffi::c_load_header());
+    m.insert("ptr_cast".to_owned(), ffi::ptr_cast());
+    m.insert("fn".to_owned(), type_ops::fn_type());
+    m.insert("ptr".to_owned(), type_ops::ptr_type());
+    m.insert("list".to_owned(), type_ops::list_type());
+    m.insert("set".to_owned(), type_ops::set_type());
+    m.insert("require".to_owned(), type_ops::require_type());
+    m.insert("exclude".to_owned(), type_ops::exclude_type());
+    m.insert("typed_args".to_owned(), type_ops::typed_args());
+    m.insert("typed_rets".to_owned(), type_ops::typed_rets());
+    m.insert("untyped_args".to_owned(), type_ops::untyped_args());
+    m.insert("untyped_rets".to_owned(), type_ops::untyped_rets());
 }
 
-pub fn get_interpret_builtins() -> HashMap<&'static str, BuiltinEntry> {
+pub fn get_interpret_builtins() -> HashMap<String, BuiltinEntry> {
     let mut m = HashMap::new();
     register_shared(&mut m);
-    m.insert("c_link_lib", ffi::c_link_lib());
-    m.insert("ptr_read_cstr", ffi::ptr_read_cstr());
-    m.insert("while_do", control_flow::while_do());
-    m.insert("do_while", control_flow::do_while());
-    m.insert("match", control_flow::match_builtin());
+    m.insert("c_link_lib".to_owned(), ffi::c_link_lib());
+    m.insert("ptr_read_cstr".to_owned(), ffi::ptr_read_cstr());
+    m.insert("while_do".to_owned(), control_flow::while_do());
+    m.insert("do_while".to_owned(), control_flow::do_while());
+    m.insert("match".to_owned(), control_flow::match_builtin());
+    m.insert("and_then".to_owned(), logic::and_then());
+    m.insert("or_else".to_owned(), logic::or_else());
+    m.insert("map".to_owned(), list::map());
+    m.insert("filter".to_owned(), list::filter());
+    m.insert("fold".to_owned(), list::fold());
+    m.insert("each".to_owned(), list::each());
+    m.insert("any".to_owned(), list::any());
+    m.insert("all".to_owned(), list::all());
+    m.insert("sort_by".to_owned(), list::sort_by());
+    m.insert("print".to_owned(), io::print());
+    m.insert("println".to_owned(), io::println());
+    m.insert("eprint".to_owned(), io::eprint());
+    m.insert("read_line".to_owned(), io::read_line());
+    m.insert("read_all_stdin".to_owned(), io::read_all_stdin());
+    m.insert("read_file".to_owned(), io::read_file());
+    m.insert("write_file".to_owned(), io::write_file());
+    m.insert("append_file".to_owned(), io::append_file());
+    m.insert("exists".to_owned(), io::exists());
+    m.insert("list_dir".to_owned(), io::list_dir());
+    m.insert("now".to_owned(), system::now());
+    m.insert("random".to_owned(), system::random());
+    m.insert("env_var".to_owned(), system::env_var());
     m
 }
 
-pub fn get_comptime_builtins() -> HashMap<&'static str, BuiltinEntry> {
+pub fn get_comptime_builtins() -> HashMap<String, BuiltinEntry> {
     let mut m = HashMap::new();
     register_shared(&mut m);
+    // At runtime `map` maps over a list, so the type constructor is `@!` only.
+    m.insert("map".to_owned(), type_ops::map_type());
     m
 }
@@ -1,132 +1,153 @@
-use crate::built_in::BuiltinEntry;
+use crate::built_in::{pop_arg, BuiltinEntry};
 use crate::type_system::SidType;
-use crate::{get_from_scope, DataValue};
+use crate::{get_from_scope, DataValue, SidError};
 
-fn pop_arg(args: &mut Vec<DataValue>, name: &str) -> DataValue {
-    args.pop()
-        .unwrap_or_else(|| panic!("{}: expected an argument", name))
+fn pop_label(args: &mut Vec<DataValue>, name: &str) -> Result<String, SidError> {
+    match pop_arg(args, name)? {
+        DataValue::Label(l) => Ok(l),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected a label, got {:?}",
+            name, other
+        ))),
+    }
 }
 
-fn pop_label(args: &mut Vec<DataValue>, name: &str) -> String {
-    match pop_arg(args, name) {
-        DataValue::Label(l) => l,
-        other => panic!("{}: expected a label, got {:?}", name, other),
+/// Load every `label: value` entry of a Map into `target`.
+fn load_entries(
+    target: &mut std::collections::HashMap<String, DataValue>,
+    map: DataValue,
+    name: &str,
+) -> Result<(), SidError> {
+    let entries = match map {
+        DataValue::Map(e) => e,
+        other => {
+            return Err(SidError::TypeMismatch(format!(
+                "{} expects a label-keyed Map, got {:?}",
+                name, other
+            )))
+        }
+    };
+    for (key, value) in entries {
+        match key {
+            DataValue::Label(label) => {
+                target.insert(label, value);
+            }
+            other => {
+                return Err(SidError::TypeMismatch(format!(
+                    "{}: key must be a Label, got {:?}",
+                    name, other
+                )))
+            }
+        }
     }
+    Ok(())
 }
 
 pub fn get() -> BuiltinEntry {
     BuiltinEntry {
-        name: "get",
+        name: "get".to_owned(),
         args: vec![SidType::Label],
         ret: vec![SidType::Any],
-        exec: |state, mut args| {
-            let label = pop_label(&mut args, "get");
-            let builtin_names: std::collections::HashSet<&'static str> =
-                state.builtins.keys().copied().collect();
-            vec![get_from_scope(
+        exec: Box::new(|state, mut args| {
+            let label = pop_label(&mut args, "get")?;
+            let builtin_names: std::collections::HashSet<&str> =
+                state.builtins.keys().map(String::as_str).collect();
+            let value = get_from_scope(
                 &label,
                 Some(&state.local_scope),
                 Some(state.global_state.scope),
                 Some(&builtin_names),
             )
-            .unwrap_or_else(|_| panic!("get: '{}' not found", label))]
-        },
+            .map_err(|_| SidError::UndefinedLabel(format!("get: '{}' not found", label)))?;
+            Ok(vec![value])
+        }),
     }
 }
 
 pub fn get_local() -> BuiltinEntry {
     BuiltinEntry {
-        name: "get_local",
+        name: "get_local".to_owned(),
         args: vec![SidType::Label],
         ret: vec![SidType::Any],
-        exec: |state, mut args| {
-            let label = pop_label(&mut args, "get_local");
-            let builtin_names: std::collections::HashSet<&'static str> =
-                state.builtins.keys().copied().collect();
-            vec![
+        exec: Box::new(|state, mut args| {
+            let label = pop_label(&mut args, "get_local")?;
+            let builtin_names: std::collections::HashSet<&str> =
+                state.builtins.keys().map(String::as_str).collect();
+            let value =
                 get_from_scope(&label, Some(&state.local_scope), None, Some(&builtin_names))
-                    .unwrap_or_else(|_| panic!("get_local: '{}' not found in local scope", label)),
-            ]
-        },
+                    .map_err(|_| {
+                        SidError::UndefinedLabel(format!(
+                            "get_local: '{}' not found in local scope",
+                            label
+                        ))
+                    })?;
+            Ok(vec![value])
+        }),
     }
 }
 
 pub fn get_global() -> BuiltinEntry {
     BuiltinEntry {
-        name: "get_global",
+        name: "get_global".to_owned(),
         args: vec![SidType::Label],
         ret: vec![SidType::Any],
-        exec: |state, mut args| {
-            let label = pop_label(&mut args, "get_global");
-            let builtin_names: std::collections::HashSet<&'static str> =
-                state.builtins.keys().copied().collect();
-            vec![get_from_scope(
+        exec: Box::new(|state, mut args| {
+            let label = pop_label(&mut args, "get_global")?;
+            let builtin_names: std::collections::HashSet<&str> =
+                state.builtins.keys().map(String::as_str).collect();
+            let value = get_from_scope(
                 &label,
                 None,
                 Some(state.global_state.scope),
                 Some(&builtin_names),
             )
-            .unwrap_or_else(|_| panic!("get_global: '{}' not found in global scope", label))]
-        },
+            .map_err(|_| {
+                SidError::UndefinedLabel(format!(
+                    "get_global: '{}' not found in global scope",
+                    label
+                ))
+            })?;
+            Ok(vec![value])
+        }),
     }
 }
 
 pub fn local() -> BuiltinEntry {
     BuiltinEntry {
-        name: "local",
+        name: "local".to_owned(),
         args: vec![SidType::Any, SidType::Label],
         ret: vec![],
-        exec: |state, mut args| {
-            let value = pop_arg(&mut args, "local");
-            let name = pop_label(&mut args, "local");
+        exec: Box::new(|state, mut args| {
+            let value = pop_arg(&mut args, "local")?;
+            let name = pop_label(&mut args, "local")?;
             state.local_scope.insert(name, value);
-            vec![]
-        },
+            Ok(vec![])
+        }),
     }
 }
 
 pub fn load_local() -> BuiltinEntry {
     BuiltinEntry {
-        name: "load_local",
+        name: "load_local".to_owned(),
         args: vec![SidType::Any],
         ret: vec![],
-        exec: |state, mut args| {
-            let entries = match pop_arg(&mut args, "load_local") {
-                DataValue::Map(e) => e,
-                other => panic!("load_local expects a label-keyed Map, got {:?}", other),
-            };
-            for (key, value) in entries {
-                match key {
-                    DataValue::Label(name) => {
-                        state.local_scope.insert(name, value);
-                    }
-                    other => panic!("load_local: key must be a Label, got {:?}", other),
-                }
-            }
-            vec![]
-        },
+        exec: Box::new(|state, mut args| {
+            let map = pop_arg(&mut args, "load_local")?;
+            load_entries(&mut state.local_scope, map, "load_local")?;
+            Ok(vec![])
+        }),
     }
 }
 
 pub fn load_scope() -> BuiltinEntry {
     BuiltinEntry {
-        name: "load_scope",
+        name: "load_scope".to_owned(),
         args: vec![SidType::Any],
         ret: vec![],
-        exec: |state, mut args| {
-            let entries = match pop_arg(&mut args, "load_scope") {
-                DataValue::Map(e) => e,
-                other => panic!("load_scope expects a label-keyed Map, got {:?}", other),
-            };
-            for (key, value) in entries {
-                match key {
-                    DataValue::Label(name) => {
-                        state.global_state.scope.insert(name, value);
-                    }
-                    other => panic!("load_scope: key must be a Label, got {:?}", other),
-                }
-            }
-            vec![]
-        },
+        exec: Box::new(|state, mut args| {
+            let map = pop_arg(&mut args, "load_scope")?;
+            load_entries(state.global_state.scope, map, "load_scope")?;
+            Ok(vec![])
+        }),
     }
 }
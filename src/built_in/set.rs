@@ -0,0 +1,105 @@
+use crate::built_in::{label_or_any, pop_arg, BuiltinEntry};
+use crate::type_system::SidType;
+use crate::{DataValue, SidError};
+
+fn pop_set(args: &mut Vec<DataValue>, name: &str) -> Result<Vec<DataValue>, SidError> {
+    match pop_arg(args, name)? {
+        DataValue::Set(items) => Ok(items),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected Set, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+fn any_set() -> SidType {
+    SidType::Set(Box::new(SidType::Any))
+}
+
+/// Shared body of the binary set operations: `keep` decides, for each item of
+/// the lower set `a`, whether it stays given the upper set `b`.
+fn binary_op(
+    mut args: Vec<DataValue>,
+    name: &str,
+    keep: fn(&DataValue, &[DataValue]) -> bool,
+) -> Result<Vec<DataValue>, SidError> {
+    let b = pop_set(&mut args, name)?;
+    let a = pop_set(&mut args, name)?;
+    let kept = a.into_iter().filter(|item| keep(item, &b)).collect();
+    Ok(vec![DataValue::set(kept)])
+}
+
+pub fn union() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "union".to_owned(),
+        args: vec![any_set(), any_set()],
+        ret: vec![any_set()],
+        exec: Box::new(|_state, mut args| {
+            let b = pop_set(&mut args, "union")?;
+            let mut a = pop_set(&mut args, "union")?;
+            a.extend(b);
+            Ok(vec![DataValue::set(a)])
+        }),
+    }
+}
+
+pub fn intersection() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "intersection".to_owned(),
+        args: vec![any_set(), any_set()],
+        ret: vec![any_set()],
+        exec: Box::new(|_state, args| binary_op(args, "intersection", |item, b| b.contains(item))),
+    }
+}
+
+pub fn difference() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "difference".to_owned(),
+        args: vec![any_set(), any_set()],
+        ret: vec![any_set()],
+        exec: Box::new(|_state, args| binary_op(args, "difference", |item, b| !b.contains(item))),
+    }
+}
+
+pub fn contains() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "contains".to_owned(),
+        args: vec![label_or_any(), any_set()],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, mut args| {
+            let item = pop_arg(&mut args, "contains")?;
+            let items = pop_set(&mut args, "contains")?;
+            Ok(vec![DataValue::Bool(items.contains(&item))])
+        }),
+    }
+}
+
+pub fn insert() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "insert".to_owned(),
+        args: vec![label_or_any(), any_set()],
+        ret: vec![any_set()],
+        exec: Box::new(|_state, mut args| {
+            let item = pop_arg(&mut args, "insert")?;
+            let mut items = pop_set(&mut args, "insert")?;
+            if !items.contains(&item) {
+                items.push(item);
+            }
+            Ok(vec![DataValue::Set(items)])
+        }),
+    }
+}
+
+pub fn remove() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "remove".to_owned(),
+        args: vec![label_or_any(), any_set()],
+        ret: vec![any_set()],
+        exec: Box::new(|_state, mut args| {
+            let item = pop_arg(&mut args, "remove")?;
+            let mut items = pop_set(&mut args, "remove")?;
+            items.retain(|i| *i != item);
+            Ok(vec![DataValue::Set(items)])
+        }),
+    }
+}
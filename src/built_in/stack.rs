@@ -1,87 +1,102 @@
-use crate::built_in::BuiltinEntry;
+use crate::built_in::{pop_arg, BuiltinEntry};
 use crate::type_system::SidType;
-use crate::DataValue;
-
-fn pop_arg(args: &mut Vec<DataValue>, name: &str) -> DataValue {
-    args.pop()
-        .unwrap_or_else(|| panic!("{}: expected an argument", name))
-}
+use crate::{DataValue, SidError};
 
 pub fn clone() -> BuiltinEntry {
     BuiltinEntry {
-        name: "clone",
+        name: "clone".to_owned(),
         args: vec![SidType::Any],
         ret: vec![SidType::Any, SidType::Any],
-        exec: |_state, mut args| {
-            let v = pop_arg(&mut args, "clone");
-            vec![v.clone(), v]
-        },
+        exec: Box::new(|_state, mut args| {
+            let v = pop_arg(&mut args, "clone")?;
+            Ok(vec![v.clone(), v])
+        }),
     }
 }
 
 pub fn drop() -> BuiltinEntry {
     BuiltinEntry {
-        name: "drop",
+        name: "drop".to_owned(),
         args: vec![SidType::Any],
         ret: vec![],
-        exec: |_state, mut args| {
-            pop_arg(&mut args, "drop");
-            vec![]
-        },
+        exec: Box::new(|_state, mut args| {
+            pop_arg(&mut args, "drop")?;
+            Ok(vec![])
+        }),
     }
 }
 
 pub fn eq() -> BuiltinEntry {
     BuiltinEntry {
-        name: "eq",
+        name: "eq".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![SidType::Bool],
-        exec: |_state, mut args| {
-            let b = pop_arg(&mut args, "eq");
-            let a = pop_arg(&mut args, "eq");
-            vec![DataValue::Bool(a == b)]
-        },
+        exec: Box::new(|_state, mut args| {
+            let b = pop_arg(&mut args, "eq")?;
+            let a = pop_arg(&mut args, "eq")?;
+            Ok(vec![DataValue::Bool(a == b)])
+        }),
     }
 }
 
 pub fn assert_builtin() -> BuiltinEntry {
     BuiltinEntry {
-        name: "assert",
+        name: "assert".to_owned(),
         args: vec![SidType::Bool],
         ret: vec![],
-        exec: |_state, mut args| match pop_arg(&mut args, "assert") {
-            DataValue::Bool(true) => vec![],
-            DataValue::Bool(false) => panic!("assertion failed"),
-            other => panic!("assert expects Bool, got {:?}", other),
-        },
+        exec: Box::new(|_state, mut args| match pop_arg(&mut args, "assert")? {
+            DataValue::Bool(true) => Ok(vec![]),
+            DataValue::Bool(false) => Err(SidError::AssertionFailed("assertion failed".to_owned())),
+            other => Err(SidError::TypeMismatch(format!(
+                "assert expects Bool, got {:?}",
+                other
+            ))),
+        }),
     }
 }
 
 pub fn not() -> BuiltinEntry {
     BuiltinEntry {
-        name: "not",
+        name: "not".to_owned(),
         args: vec![SidType::Bool],
         ret: vec![SidType::Bool],
-        exec: |_state, mut args| match pop_arg(&mut args, "not") {
-            DataValue::Bool(b) => vec![DataValue::Bool(!b)],
-            other => panic!("not expects Bool, got {:?}", other),
-        },
+        exec: Box::new(|_state, mut args| match pop_arg(&mut args, "not")? {
+            DataValue::Bool(b) => Ok(vec![DataValue::Bool(!b)]),
+            other => Err(SidError::TypeMismatch(format!(
+                "not expects Bool, got {:?}",
+                other
+            ))),
+        }),
     }
 }
 
 pub fn debug_stack() -> BuiltinEntry {
     BuiltinEntry {
-        name: "debug_stack",
+        name: "debug_stack".to_owned(),
         args: vec![SidType::Int],
         ret: vec![],
-        exec: |_state, mut args| {
-            let n = match pop_arg(&mut args, "debug_stack") {
+        exec: Box::new(|state, mut args| {
+            let n = match pop_arg(&mut args, "debug_stack")? {
                 DataValue::Int(n) if n >= 0 => n as usize,
-                DataValue::Int(n) => panic!("debug_stack: count must be non-negative, got {}", n),
-                other => panic!("debug_stack expects Int, got {:?}", other),
+                DataValue::Int(n) => {
+                    return Err(SidError::InvalidArgument(format!(
+                        "debug_stack: count must be non-negative, got {}",
+                        n
+                    )))
+                }
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "debug_stack expects Int, got {:?}",
+                        other
+                    )))
+                }
             };
-            eprintln!("=== debug_stack (top {}) ===", n);
-            vec![]
-        },
+            state
+                .global_state
+                .side_effects
+                .write_stderr(format!("=== debug_stack (top {}) ===\n", n).as_bytes())
+                .map_err(|e| SidError::Io(format!("debug_stack: {}", e)))?;
+            Ok(vec![])
+        }),
     }
 }
@@ -0,0 +1,264 @@
+use std::ffi::CString;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::built_in::{pop_arg, pop_str, BuiltinEntry};
+use crate::type_system::SidType;
+use crate::{DataValue, SidError};
+
+/// The text of `s`.  A `Str` is only guaranteed to be free of NUL bytes, so
+/// one read from C may not be valid UTF-8.
+fn text<'a>(s: &'a CString, name: &str) -> Result<&'a str, SidError> {
+    s.to_str().map_err(|_| {
+        SidError::InvalidArgument(format!("{}: string is not valid UTF-8: {:?}", name, s))
+    })
+}
+
+/// Wrap a result as a `Str`, keeping the invariant that a `Str` never
+/// contains a NUL byte.
+fn str_value(s: impl Into<Vec<u8>>, name: &str) -> Result<DataValue, SidError> {
+    CString::new(s).map(DataValue::Str).map_err(|_| {
+        SidError::InvalidArgument(format!("{}: result would contain a NUL byte", name))
+    })
+}
+
+/// Check that `index` is a byte offset into `s` that falls on a character
+/// boundary.
+fn byte_offset(s: &str, index: i64, name: &str) -> Result<usize, SidError> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&i| s.is_char_boundary(i))
+        .ok_or_else(|| {
+            SidError::InvalidArgument(format!(
+                "{}: {} is not a character boundary in a string of {} bytes",
+                name,
+                index,
+                s.len()
+            ))
+        })
+}
+
+fn str_list() -> SidType {
+    SidType::List(Box::new(SidType::Str))
+}
+
+/// `concat` on two strings.  The entry itself is in `list`, since it also
+/// concatenates lists; likewise `len` and `slice`.
+pub(super) fn concat_str(a: CString, b: CString) -> Result<DataValue, SidError> {
+    let mut bytes = a.into_bytes();
+    bytes.extend_from_slice(b.as_bytes());
+    str_value(bytes, "concat")
+}
+
+/// `len` on a string: its length in bytes.
+pub(super) fn len_str(s: &CString) -> DataValue {
+    DataValue::Int(s.as_bytes().len() as i64)
+}
+
+/// `slice` on a string, with `start` and `end` as byte offsets.
+pub(super) fn slice_str(s: &CString, start: i64, end: i64) -> Result<DataValue, SidError> {
+    let s = text(s, "slice")?;
+    let start = byte_offset(s, start, "slice")?;
+    let end = byte_offset(s, end, "slice")?;
+    if start > end {
+        return Err(SidError::InvalidArgument(format!(
+            "slice: start {} is after end {}",
+            start, end
+        )));
+    }
+    str_value(&s[start..end], "slice")
+}
+
+pub fn grapheme_len() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "grapheme_len".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::Int],
+        exec: Box::new(|_state, mut args| {
+            let s = pop_str(&mut args, "grapheme_len")?;
+            let n = text(&s, "grapheme_len")?.graphemes(true).count();
+            Ok(vec![DataValue::Int(n as i64)])
+        }),
+    }
+}
+
+pub fn split() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "split".to_owned(),
+        args: vec![SidType::Str, SidType::Str],
+        ret: vec![str_list()],
+        exec: Box::new(|_state, mut args| {
+            let sep = pop_str(&mut args, "split")?;
+            let s = pop_str(&mut args, "split")?;
+            let sep = text(&sep, "split")?;
+            if sep.is_empty() {
+                return Err(SidError::InvalidArgument(
+                    "split: separator is empty (use chars to split into graphemes)".to_owned(),
+                ));
+            }
+            let parts = text(&s, "split")?
+                .split(sep)
+                .map(|part| str_value(part, "split"))
+                .collect::<Result<_, _>>()?;
+            Ok(vec![DataValue::List(parts)])
+        }),
+    }
+}
+
+pub fn join() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "join".to_owned(),
+        args: vec![SidType::Str, str_list()],
+        ret: vec![SidType::Str],
+        exec: Box::new(|_state, mut args| {
+            let sep = pop_str(&mut args, "join")?;
+            let parts = match pop_arg(&mut args, "join")? {
+                DataValue::List(items) => items,
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "join: expected a List of Str, got {:?}",
+                        other
+                    )))
+                }
+            };
+            let mut bytes = Vec::new();
+            for (i, part) in parts.into_iter().enumerate() {
+                if i > 0 {
+                    bytes.extend_from_slice(sep.as_bytes());
+                }
+                match part {
+                    DataValue::Str(s) => bytes.extend_from_slice(s.as_bytes()),
+                    other => {
+                        return Err(SidError::TypeMismatch(format!(
+                            "join: expected a List of Str, got element {:?}",
+                            other
+                        )))
+                    }
+                }
+            }
+            Ok(vec![str_value(bytes, "join")?])
+        }),
+    }
+}
+
+pub fn find() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "find".to_owned(),
+        args: vec![SidType::Str, SidType::Str],
+        ret: vec![SidType::Int],
+        exec: Box::new(|_state, mut args| {
+            let needle = pop_str(&mut args, "find")?;
+            let s = pop_str(&mut args, "find")?;
+            let index = text(&s, "find")?
+                .find(text(&needle, "find")?)
+                .map_or(-1, |i| i as i64);
+            Ok(vec![DataValue::Int(index)])
+        }),
+    }
+}
+
+pub fn replace() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "replace".to_owned(),
+        args: vec![SidType::Str, SidType::Str, SidType::Str],
+        ret: vec![SidType::Str],
+        exec: Box::new(|_state, mut args| {
+            let to = pop_str(&mut args, "replace")?;
+            let from = pop_str(&mut args, "replace")?;
+            let s = pop_str(&mut args, "replace")?;
+            let from = text(&from, "replace")?;
+            if from.is_empty() {
+                return Err(SidError::InvalidArgument(
+                    "replace: pattern is empty".to_owned(),
+                ));
+            }
+            let replaced = text(&s, "replace")?.replace(from, text(&to, "replace")?);
+            Ok(vec![str_value(replaced, "replace")?])
+        }),
+    }
+}
+
+/// Shared implementation of the built-ins that turn one `Str` into another.
+fn map_text(
+    mut args: Vec<DataValue>,
+    name: &str,
+    f: fn(&str) -> String,
+) -> Result<Vec<DataValue>, SidError> {
+    let s = pop_str(&mut args, name)?;
+    Ok(vec![str_value(f(text(&s, name)?), name)?])
+}
+
+pub fn trim() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "trim".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::Str],
+        exec: Box::new(|_state, args| map_text(args, "trim", |s| s.trim().to_owned())),
+    }
+}
+
+pub fn upper() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "upper".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::Str],
+        exec: Box::new(|_state, args| map_text(args, "upper", str::to_uppercase)),
+    }
+}
+
+pub fn lower() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "lower".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::Str],
+        exec: Box::new(|_state, args| map_text(args, "lower", str::to_lowercase)),
+    }
+}
+
+/// Shared implementation of `starts_with` and `ends_with`: pops `s affix` and
+/// pushes `test(s, affix)`.  Works on bytes, so it never fails on non-UTF-8.
+fn affix_test(
+    mut args: Vec<DataValue>,
+    name: &str,
+    test: fn(&[u8], &[u8]) -> bool,
+) -> Result<Vec<DataValue>, SidError> {
+    let affix = pop_str(&mut args, name)?;
+    let s = pop_str(&mut args, name)?;
+    Ok(vec![DataValue::Bool(test(s.as_bytes(), affix.as_bytes()))])
+}
+
+pub fn starts_with() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "starts_with".to_owned(),
+        args: vec![SidType::Str, SidType::Str],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| {
+            affix_test(args, "starts_with", |s, affix| s.starts_with(affix))
+        }),
+    }
+}
+
+pub fn ends_with() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "ends_with".to_owned(),
+        args: vec![SidType::Str, SidType::Str],
+        ret: vec![SidType::Bool],
+        exec: Box::new(|_state, args| affix_test(args, "ends_with", |s, affix| s.ends_with(affix))),
+    }
+}
+
+pub fn chars() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "chars".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::List(Box::new(SidType::Char))],
+        exec: Box::new(|_state, mut args| {
+            let s = pop_str(&mut args, "chars")?;
+            let chars = text(&s, "chars")?
+                .graphemes(true)
+                .map(|g| DataValue::Char(g.to_owned()))
+                .collect();
+            Ok(vec![DataValue::List(chars)])
+        }),
+    }
+}
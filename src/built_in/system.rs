@@ -0,0 +1,73 @@
+use std::ffi::CString;
+use std::time::UNIX_EPOCH;
+
+use crate::built_in::{pop_arg, BuiltinEntry};
+use crate::type_system::SidType;
+use crate::{Capability, DataValue, SidError};
+
+pub fn now() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "now".to_owned(),
+        args: vec![],
+        ret: vec![SidType::Int],
+        exec: Box::new(|state, _args| {
+            let since_epoch = state
+                .global_state
+                .side_effects
+                .now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| SidError::InvalidArgument(format!("now: clock is {}", e)))?;
+            let millis = i64::try_from(since_epoch.as_millis())
+                .map_err(|_| SidError::Arithmetic("now: time overflows Int".to_owned()))?;
+            Ok(vec![DataValue::Int(millis)])
+        }),
+    }
+}
+
+pub fn random() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "random".to_owned(),
+        args: vec![],
+        ret: vec![SidType::Float],
+        exec: Box::new(|state, _args| {
+            // The top 53 bits fill an f64 mantissa exactly.
+            let bits = state.global_state.side_effects.random_u64() >> 11;
+            Ok(vec![DataValue::Float(bits as f64 / (1u64 << 53) as f64)])
+        }),
+    }
+}
+
+pub fn env_var() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "env_var".to_owned(),
+        args: vec![SidType::Str],
+        ret: vec![SidType::Str],
+        exec: Box::new(|state, mut args| {
+            state
+                .global_state
+                .sandbox
+                .require(Capability::Env, "env_var")?;
+            let name = match pop_arg(&mut args, "env_var")? {
+                DataValue::Str(s) => s.to_string_lossy().into_owned(),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "env_var: expected Str, got {:?}",
+                        other
+                    )))
+                }
+            };
+            let value = state
+                .global_state
+                .side_effects
+                .env_var(&name)
+                .ok_or_else(|| {
+                    SidError::InvalidArgument(format!("env_var: {} is not set", name))
+                })?;
+            CString::new(value)
+                .map(|v| vec![DataValue::Str(v)])
+                .map_err(|_| {
+                    SidError::InvalidArgument(format!("env_var: {} contains a NUL byte", name))
+                })
+        }),
+    }
+}
@@ -1,132 +1,181 @@
-use crate::built_in::BuiltinEntry;
+use crate::built_in::{pop_arg, BuiltinEntry};
 use crate::type_system::SidType;
-use crate::DataValue;
-
-fn pop_arg(args: &mut Vec<DataValue>, name: &str) -> DataValue {
-    args.pop()
-        .unwrap_or_else(|| panic!("{}: expected an argument", name))
-}
+use crate::{DataValue, SidError};
 
 pub fn fn_type() -> BuiltinEntry {
     BuiltinEntry {
-        name: "fn",
+        name: "fn".to_owned(),
         args: vec![],
         ret: vec![SidType::Any],
-        exec: |_state, _args| {
-            vec![DataValue::Type(SidType::Fn {
+        exec: Box::new(|_state, _args| {
+            Ok(vec![DataValue::Type(SidType::Fn {
                 args: None,
                 ret: None,
-            })]
-        },
+            })])
+        }),
     }
 }
 
 pub fn ptr_type() -> BuiltinEntry {
     BuiltinEntry {
-        name: "ptr",
+        name: "ptr".to_owned(),
         args: vec![SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let raw = pop_arg(&mut args, "ptr");
+        exec: Box::new(|_state, mut args| {
+            let raw = pop_arg(&mut args, "ptr")?;
             let inner = match raw {
                 DataValue::Type(t) => t,
-                other => panic!("ptr: expected a type, got {:?}", other),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "ptr: expected a type, got {:?}",
+                        other
+                    )))
+                }
             };
-            vec![DataValue::Type(SidType::Pointer(Box::new(inner)))]
-        },
+            Ok(vec![DataValue::Type(SidType::Pointer(Box::new(inner)))])
+        }),
     }
 }
 
 pub fn list_type() -> BuiltinEntry {
     BuiltinEntry {
-        name: "list",
+        name: "list".to_owned(),
         args: vec![SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let raw = pop_arg(&mut args, "list");
+        exec: Box::new(|_state, mut args| {
+            let raw = pop_arg(&mut args, "list")?;
             let inner = match raw {
                 DataValue::Type(t) => t,
-                other => panic!("list: expected a type, got {:?}", other),
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "list: expected a type, got {:?}",
+                        other
+                    )))
+                }
             };
-            vec![DataValue::Type(SidType::List(Box::new(inner)))]
-        },
+            Ok(vec![DataValue::Type(SidType::List(Box::new(inner)))])
+        }),
+    }
+}
+
+pub fn set_type() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "set".to_owned(),
+        args: vec![SidType::Any],
+        ret: vec![SidType::Any],
+        exec: Box::new(|_state, mut args| {
+            let inner = type_arg(pop_arg(&mut args, "set")?, "set")?;
+            Ok(vec![DataValue::Type(SidType::Set(Box::new(inner)))])
+        }),
+    }
+}
+
+pub fn map_type() -> BuiltinEntry {
+    BuiltinEntry {
+        name: "map".to_owned(),
+        args: vec![SidType::Any, SidType::Any],
+        ret: vec![SidType::Any],
+        exec: Box::new(|_state, mut args| {
+            let value = pop_arg(&mut args, "map")?;
+            let key = pop_arg(&mut args, "map")?;
+            Ok(vec![DataValue::Type(SidType::Map {
+                key: Box::new(type_arg(key, "map")?),
+                value: Box::new(type_arg(value, "map")?),
+            })])
+        }),
+    }
+}
+
+fn type_arg(raw: DataValue, name: &str) -> Result<SidType, SidError> {
+    match raw {
+        DataValue::Type(t) => Ok(t),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected a type, got {:?}",
+            name, other
+        ))),
     }
 }
 
 pub fn require_type() -> BuiltinEntry {
     BuiltinEntry {
-        name: "require",
+        name: "require".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let constraint = pop_arg(&mut args, "require");
-            let base = pop_arg(&mut args, "require");
+        exec: Box::new(|_state, mut args| {
+            let constraint = pop_arg(&mut args, "require")?;
+            let base = pop_arg(&mut args, "require")?;
             let resolve_type = |raw: DataValue| -> SidType {
                 match raw {
                     DataValue::Type(t) => t,
                     other => SidType::Literal(Box::new(other)),
                 }
             };
-            vec![DataValue::Type(SidType::Require {
+            Ok(vec![DataValue::Type(SidType::Require {
                 base: Box::new(resolve_type(base)),
                 constraint: Box::new(resolve_type(constraint)),
-            })]
-        },
+            })])
+        }),
     }
 }
 
 pub fn exclude_type() -> BuiltinEntry {
     BuiltinEntry {
-        name: "exclude",
+        name: "exclude".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let forbidden = pop_arg(&mut args, "exclude");
-            let base = pop_arg(&mut args, "exclude");
+        exec: Box::new(|_state, mut args| {
+            let forbidden = pop_arg(&mut args, "exclude")?;
+            let base = pop_arg(&mut args, "exclude")?;
             let resolve_type = |raw: DataValue| -> SidType {
                 match raw {
                     DataValue::Type(t) => t,
                     other => SidType::Literal(Box::new(other)),
                 }
             };
-            vec![DataValue::Type(SidType::Exclude {
+            Ok(vec![DataValue::Type(SidType::Exclude {
                 base: Box::new(resolve_type(base)),
                 forbidden: Box::new(resolve_type(forbidden)),
-            })]
-        },
+            })])
+        }),
     }
 }
 
-fn list_to_type_vec(list: DataValue, ctx: &str) -> Vec<SidType> {
+fn list_to_type_vec(list: DataValue, ctx: &str) -> Result<Vec<SidType>, SidError> {
     match list {
         DataValue::List(items) => items
             .into_iter()
             .map(|v| match v {
-                DataValue::Type(t) => t,
-                other => panic!("{}: expected a list of types, got {:?}", ctx, other),
+                DataValue::Type(t) => Ok(t),
+                other => Err(SidError::TypeMismatch(format!(
+                    "{}: expected a list of types, got {:?}",
+                    ctx, other
+                ))),
             })
             .collect(),
-        other => panic!("{}: expected a List of types, got {:?}", ctx, other),
+        other => Err(SidError::TypeMismatch(format!(
+            "{}: expected a List of types, got {:?}",
+            ctx, other
+        ))),
     }
 }
 
 pub fn typed_args() -> BuiltinEntry {
     BuiltinEntry {
-        name: "typed_args",
+        name: "typed_args".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let target_val = pop_arg(&mut args, "typed_args");
-            let types_val = pop_arg(&mut args, "typed_args");
+        exec: Box::new(|_state, mut args| {
+            let target_val = pop_arg(&mut args, "typed_args")?;
+            let types_val = pop_arg(&mut args, "typed_args")?;
             let label_map_ty = SidType::Map {
                 key: Box::new(SidType::Label),
                 value: Box::new(SidType::Any),
             };
             if !label_map_ty.matches(&types_val) {
-                panic!(
+                return Err(SidError::TypeMismatch(format!(
                     "typed_args: expected a label-keyed Map, got {:?}",
                     types_val
-                );
+                )));
             }
             let entries = match types_val {
                 DataValue::Map(e) => e,
@@ -140,133 +189,164 @@ pub fn typed_args() -> BuiltinEntry {
                         DataValue::Label(s) => s,
                         _ => unreachable!(),
                     };
-                    let ty = match v {
-                        DataValue::Type(t) => t,
-                        other => panic!(
+                    match v {
+                        DataValue::Type(t) => Ok((name, t)),
+                        other => Err(SidError::TypeMismatch(format!(
                             "typed_args: field '{}' value must be a type, got {:?}",
                             name, other
-                        ),
-                    };
-                    (name, ty)
+                        ))),
+                    }
                 })
-                .collect();
+                .collect::<Result<_, _>>()?;
             let type_only: Vec<SidType> = named_args.iter().map(|(_, t)| t.clone()).collect();
-            vec![match target_val {
-                DataValue::Substack { body, ret, .. } => DataValue::Substack {
+            Ok(vec![match target_val {
+                DataValue::Substack {
+                    body, ret, spans, ..
+                } => DataValue::Substack {
                     body,
                     args: Some(named_args),
                     ret,
+                    spans,
                 },
-                DataValue::Script { body, ret, .. } => DataValue::Script {
+                DataValue::Script {
+                    body, ret, spans, ..
+                } => DataValue::Script {
                     body,
                     args: Some(named_args),
                     ret,
+                    spans,
                 },
                 DataValue::Type(SidType::Fn { ret, .. }) => DataValue::Type(SidType::Fn {
                     args: Some(type_only),
                     ret,
                 }),
-                other => panic!(
-                    "typed_args: expected Substack, Script, or Fn type, got {:?}",
-                    other
-                ),
-            }]
-        },
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "typed_args: expected Substack, Script, or Fn type, got {:?}",
+                        other
+                    )))
+                }
+            }])
+        }),
     }
 }
 
 pub fn typed_rets() -> BuiltinEntry {
     BuiltinEntry {
-        name: "typed_rets",
+        name: "typed_rets".to_owned(),
         args: vec![SidType::Any, SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let target_val = pop_arg(&mut args, "typed_rets");
-            let types_val = pop_arg(&mut args, "typed_rets");
-            let types: Vec<SidType> = list_to_type_vec(types_val, "typed_rets")
+        exec: Box::new(|_state, mut args| {
+            let target_val = pop_arg(&mut args, "typed_rets")?;
+            let types_val = pop_arg(&mut args, "typed_rets")?;
+            let types: Vec<SidType> = list_to_type_vec(types_val, "typed_rets")?
                 .into_iter()
                 .rev()
                 .collect();
-            vec![match target_val {
-                DataValue::Substack { body, args, .. } => DataValue::Substack {
+            Ok(vec![match target_val {
+                DataValue::Substack {
+                    body, args, spans, ..
+                } => DataValue::Substack {
                     body,
                     args,
                     ret: Some(types),
+                    spans,
                 },
-                DataValue::Script { body, args, .. } => DataValue::Script {
+                DataValue::Script {
+                    body, args, spans, ..
+                } => DataValue::Script {
                     body,
                     args,
                     ret: Some(types),
+                    spans,
                 },
                 DataValue::Type(SidType::Fn { args, .. }) => DataValue::Type(SidType::Fn {
                     args,
                     ret: Some(types),
                 }),
-                other => panic!(
-                    "typed_rets: expected Substack, Script, or Fn type, got {:?}",
-                    other
-                ),
-            }]
-        },
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "typed_rets: expected Substack, Script, or Fn type, got {:?}",
+                        other
+                    )))
+                }
+            }])
+        }),
     }
 }
 
 pub fn untyped_args() -> BuiltinEntry {
     BuiltinEntry {
-        name: "untyped_args",
+        name: "untyped_args".to_owned(),
         args: vec![SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let target_val = pop_arg(&mut args, "untyped_args");
-            vec![match target_val {
-                DataValue::Substack { body, ret, .. } => DataValue::Substack {
+        exec: Box::new(|_state, mut args| {
+            let target_val = pop_arg(&mut args, "untyped_args")?;
+            Ok(vec![match target_val {
+                DataValue::Substack {
+                    body, ret, spans, ..
+                } => DataValue::Substack {
                     body,
                     args: None,
                     ret,
+                    spans,
                 },
-                DataValue::Script { body, ret, .. } => DataValue::Script {
+                DataValue::Script {
+                    body, ret, spans, ..
+                } => DataValue::Script {
                     body,
                     args: None,
                     ret,
+                    spans,
                 },
                 DataValue::Type(SidType::Fn { ret, .. }) => {
                     DataValue::Type(SidType::Fn { args: None, ret })
                 }
-                other => panic!(
-                    "untyped_args: expected Substack, Script, or Fn type, got {:?}",
-                    other
-                ),
-            }]
-        },
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "untyped_args: expected Substack, Script, or Fn type, got {:?}",
+                        other
+                    )))
+                }
+            }])
+        }),
     }
 }
 
 pub fn untyped_rets() -> BuiltinEntry {
     BuiltinEntry {
-        name: "untyped_rets",
+        name: "untyped_rets".to_owned(),
         args: vec![SidType::Any],
         ret: vec![SidType::Any],
-        exec: |_state, mut args| {
-            let target_val = pop_arg(&mut args, "untyped_rets");
-            vec![match target_val {
-                DataValue::Substack { body, args, .. } => DataValue::Substack {
+        exec: Box::new(|_state, mut args| {
+            let target_val = pop_arg(&mut args, "untyped_rets")?;
+            Ok(vec![match target_val {
+                DataValue::Substack {
+                    body, args, spans, ..
+                } => DataValue::Substack {
                     body,
                     args,
                     ret: None,
+                    spans,
                 },
-                DataValue::Script { body, args, .. } => DataValue::Script {
+                DataValue::Script {
+                    body, args, spans, ..
+                } => DataValue::Script {
                     body,
                     args,
                     ret: None,
+                    spans,
                 },
                 DataValue::Type(SidType::Fn { args, .. }) => {
                     DataValue::Type(SidType::Fn { args, ret: None })
                 }
-                other => panic!(
-                    "untyped_rets: expected Substack, Script, or Fn type, got {:?}",
-                    other
-                ),
-            }]
-        },
+                other => {
+                    return Err(SidError::TypeMismatch(format!(
+                        "untyped_rets: expected Substack, Script, or Fn type, got {:?}",
+                        other
+                    )))
+                }
+            }])
+        }),
     }
 }
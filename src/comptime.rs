@@ -3,9 +3,13 @@ use std::collections::HashMap;
 use anyhow::{bail, Result};
 
 use crate::built_in::BuiltinEntry;
-use crate::invoke::ExeState;
+use crate::invoke::{schedule_body, ExeState};
+use crate::parse::parse_str_spanned;
 use crate::render::render_template;
-use crate::{DataValue, GlobalState, ProgramValue, Template, TemplateData, TemplateValue};
+use crate::{
+    DataValue, GlobalState, ProgramValue, SandboxPolicy, Span, SpanTable, Template, TemplateData,
+    TemplateValue,
+};
 
 fn is_template_concrete(data: &TemplateData) -> bool {
     match data {
@@ -29,19 +33,48 @@ fn template_value_is_concrete(tv: &TemplateValue) -> bool {
 
 pub fn comptime_pass(
     values: Vec<TemplateValue>,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
+    builtins: &HashMap<String, BuiltinEntry>,
     scope: &mut HashMap<String, DataValue>,
 ) -> Result<Vec<TemplateValue>> {
+    comptime_pass_spanned(
+        values,
+        SpanTable::default(),
+        builtins,
+        scope,
+        SandboxPolicy::default(),
+    )
+    .map(|(out, _)| out)
+}
+
+/// [`comptime_pass`] for a sequence with source spans, with the `@!`
+/// built-ins restricted by `sandbox` so that e.g. `c_load_header @!` can be
+/// denied before anything runs.
+///
+/// Returns the spans of the output sequence: entries passed through keep
+/// their span, and values produced by an `@!` take the span of the `@!`.
+pub fn comptime_pass_spanned(
+    values: Vec<TemplateValue>,
+    spans: SpanTable,
+    builtins: &HashMap<String, BuiltinEntry>,
+    scope: &mut HashMap<String, DataValue>,
+    sandbox: SandboxPolicy,
+) -> Result<(Vec<TemplateValue>, SpanTable)> {
     let mut stack: Vec<TemplateValue> = Vec::new();
+    // Index-aligned with `stack`.
+    let mut stack_spans: Vec<Option<Span>> = Vec::new();
 
-    for tv in values {
+    for (i, tv) in values.into_iter().enumerate() {
+        let span = spans.get(i);
         match tv {
             TemplateValue::Literal(ProgramValue::Template(t)) => {
-                let new_data = comptime_pass_template_data(t.data, builtins, scope)?;
+                let (new_data, new_spans) =
+                    comptime_pass_template_data(t.data, t.spans, builtins, scope, sandbox)?;
                 stack.push(TemplateValue::Literal(ProgramValue::Template(Template {
                     data: new_data,
                     consumes_stack_entries: t.consumes_stack_entries,
+                    spans: new_spans,
                 })));
+                stack_spans.push(span);
             }
 
             TemplateValue::Literal(ProgramValue::ComptimeInvoke) => {
@@ -57,6 +90,14 @@ pub fn comptime_pass(
                     .get(fn_name.as_str())
                     .ok_or_else(|| anyhow::anyhow!("Unknown comptime function: '{}'", fn_name))?;
 
+                if stack.len() < entry.args.len() {
+                    bail!(
+                        "builtin '{}': expected {} argument(s), only {} on stack",
+                        fn_name,
+                        entry.args.len(),
+                        stack.len()
+                    );
+                }
                 // Pop arguments from stack before building exe_state to avoid borrow conflicts.
                 let args_start = stack.len() - entry.args.len();
                 let arg_tvs: Vec<TemplateValue> = stack.drain(args_start..).collect();
@@ -71,10 +112,8 @@ pub fn comptime_pass(
                                 let mut parent_stack: Vec<TemplateValue> = Vec::new();
                                 let mut empty_scope = HashMap::new();
                                 let mut gs = GlobalState::new(&mut empty_scope);
-                                let template = Template {
-                                    data: t.data.clone(),
-                                    consumes_stack_entries: t.consumes_stack_entries,
-                                };
+                                gs.sandbox = sandbox;
+                                let template = t.clone();
                                 let dummy_scope = HashMap::new();
                                 Ok(render_template(
                                     template,
@@ -82,7 +121,7 @@ pub fn comptime_pass(
                                     &dummy_scope,
                                     &mut gs,
                                     builtins,
-                                ))
+                                )?)
                             } else {
                                 bail!(
                                     "builtin '{}': argument template is not concrete: {:?}",
@@ -101,57 +140,115 @@ pub fn comptime_pass(
 
                 let arg_values = arg_values?;
 
+                let mut global_state = GlobalState::new(scope);
+                global_state.sandbox = sandbox;
                 let mut exe_state = ExeState {
                     program_stack: Vec::new(),
                     data_stack: stack,
                     local_scope: HashMap::new(),
                     scope_stack: Vec::new(),
-                    global_state: GlobalState::new(scope),
+                    global_state,
                     builtins,
                 };
 
-                let results = (entry.exec)(&mut exe_state, arg_values);
+                let results =
+                    (entry.exec)(&mut exe_state, arg_values).map_err(|e| e.located(span))?;
 
                 for result in results {
                     exe_state.data_stack.push(TemplateValue::from(result));
                 }
 
                 stack = exe_state.data_stack;
+                stack_spans.truncate(args_start.min(stack.len()));
+                stack_spans.resize(stack.len(), span);
             }
 
             TemplateValue::ComptimeLabel(label) => {
                 let value = crate::get_from_scope(&label, None, Some(scope), None)
                     .map_err(|e| anyhow::anyhow!("@{}: {}", label, e))?;
                 stack.push(TemplateValue::Literal(ProgramValue::Data(value)));
+                stack_spans.push(span);
             }
 
-            other => stack.push(other),
+            other => {
+                stack.push(other);
+                stack_spans.push(span);
+            }
         }
     }
 
-    Ok(stack)
+    Ok((stack, SpanTable(stack_spans)))
+}
+
+/// Parse `source`, run its comptime pass and render it, returning a program
+/// stack that runs it as a body (see [`schedule_body`]).
+///
+/// The comptime pass binds into `global_state.scope` and is restricted by
+/// `global_state.sandbox`.  Running the body in place, rather than invoking
+/// it as a substack, keeps what it binds with `local` in the caller's scope.
+pub fn compile_str(
+    source: &str,
+    comptime_builtins: &HashMap<String, BuiltinEntry>,
+    global_state: &mut GlobalState,
+) -> Result<Vec<ProgramValue>> {
+    let (parsed, _, spans) = parse_str_spanned(source)?;
+    let (after_comptime, spans) = comptime_pass_spanned(
+        parsed,
+        spans,
+        comptime_builtins,
+        global_state.scope,
+        global_state.sandbox,
+    )?;
+    let rendered = render_template(
+        Template::substack((after_comptime, 0)).with_spans(spans),
+        &mut Vec::new(),
+        &HashMap::new(),
+        global_state,
+        comptime_builtins,
+    )?;
+    let DataValue::Substack { body, spans, .. } = rendered else {
+        bail!("top-level template did not render to a substack");
+    };
+    let mut program_stack = Vec::new();
+    schedule_body(&mut program_stack, body, &spans);
+    Ok(program_stack)
 }
 
 fn comptime_pass_template_data(
     data: TemplateData,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
+    spans: SpanTable,
+    builtins: &HashMap<String, BuiltinEntry>,
     scope: &mut HashMap<String, DataValue>,
-) -> Result<TemplateData> {
+    sandbox: SandboxPolicy,
+) -> Result<(TemplateData, SpanTable)> {
+    let mut pass = |tvs| comptime_pass_spanned(tvs, spans.clone(), builtins, scope, sandbox);
     match data {
         TemplateData::Substack(tvs) => {
-            Ok(TemplateData::Substack(comptime_pass(tvs, builtins, scope)?))
+            let (out, spans) = pass(tvs)?;
+            Ok((TemplateData::Substack(out), spans))
+        }
+        TemplateData::Script(tvs) => {
+            let (out, spans) = pass(tvs)?;
+            Ok((TemplateData::Script(out), spans))
+        }
+        TemplateData::List(tvs) => {
+            let (out, spans) = pass(tvs)?;
+            Ok((TemplateData::List(out), spans))
+        }
+        TemplateData::Set(tvs) => {
+            let (out, spans) = pass(tvs)?;
+            Ok((TemplateData::Set(out), spans))
         }
-        TemplateData::Script(tvs) => Ok(TemplateData::Script(comptime_pass(tvs, builtins, scope)?)),
-        TemplateData::List(tvs) => Ok(TemplateData::List(comptime_pass(tvs, builtins, scope)?)),
-        TemplateData::Set(tvs) => Ok(TemplateData::Set(comptime_pass(tvs, builtins, scope)?)),
         TemplateData::Map(pairs) => {
             let mut new_pairs: Vec<(Vec<TemplateValue>, Vec<TemplateValue>)> = Vec::new();
             for (k_tvs, v_tvs) in pairs {
-                let k_out = comptime_pass(k_tvs, builtins, scope)?;
-                let v_out = comptime_pass(v_tvs, builtins, scope)?;
+                let (k_out, _) =
+                    comptime_pass_spanned(k_tvs, SpanTable::default(), builtins, scope, sandbox)?;
+                let (v_out, _) =
+                    comptime_pass_spanned(v_tvs, SpanTable::default(), builtins, scope, sandbox)?;
                 new_pairs.push((k_out, v_out));
             }
-            Ok(TemplateData::Map(new_pairs))
+            Ok((TemplateData::Map(new_pairs), SpanTable::default()))
         }
     }
 }
@@ -0,0 +1,150 @@
+//! Conversions between `DataValue` and plain Rust types, for embedders.
+//!
+//! Going into a `DataValue` can only fail for strings, which must not contain
+//! NUL bytes.  Coming out fails with `SidError::TypeMismatch` if the value is
+//! of the wrong kind.
+
+use std::ffi::CString;
+
+use crate::{DataValue, Opaque, SidError};
+
+fn mismatch(expected: &str, got: &DataValue) -> SidError {
+    SidError::TypeMismatch(format!("expected {}, got {:?}", expected, got))
+}
+
+impl From<bool> for DataValue {
+    fn from(v: bool) -> Self {
+        DataValue::Bool(v)
+    }
+}
+
+impl From<i64> for DataValue {
+    fn from(v: i64) -> Self {
+        DataValue::Int(v)
+    }
+}
+
+impl From<f64> for DataValue {
+    fn from(v: f64) -> Self {
+        DataValue::Float(v)
+    }
+}
+
+impl From<char> for DataValue {
+    fn from(v: char) -> Self {
+        DataValue::Char(v.to_string())
+    }
+}
+
+impl From<CString> for DataValue {
+    fn from(v: CString) -> Self {
+        DataValue::Str(v)
+    }
+}
+
+impl From<Opaque> for DataValue {
+    fn from(v: Opaque) -> Self {
+        DataValue::Opaque(v)
+    }
+}
+
+impl<T: Into<DataValue>> From<Vec<T>> for DataValue {
+    fn from(v: Vec<T>) -> Self {
+        DataValue::List(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl TryFrom<&str> for DataValue {
+    type Error = SidError;
+    fn try_from(v: &str) -> Result<Self, SidError> {
+        CString::new(v)
+            .map(DataValue::Str)
+            .map_err(|_| SidError::InvalidArgument(format!("{:?} contains a NUL byte", v)))
+    }
+}
+
+impl TryFrom<String> for DataValue {
+    type Error = SidError;
+    fn try_from(v: String) -> Result<Self, SidError> {
+        DataValue::try_from(v.as_str())
+    }
+}
+
+impl TryFrom<DataValue> for bool {
+    type Error = SidError;
+    fn try_from(v: DataValue) -> Result<Self, SidError> {
+        match v {
+            DataValue::Bool(b) => Ok(b),
+            other => Err(mismatch("Bool", &other)),
+        }
+    }
+}
+
+impl TryFrom<DataValue> for i64 {
+    type Error = SidError;
+    fn try_from(v: DataValue) -> Result<Self, SidError> {
+        match v {
+            DataValue::Int(i) => Ok(i),
+            other => Err(mismatch("Int", &other)),
+        }
+    }
+}
+
+/// Accepts `Int` as well, since every `Int` a program is likely to produce
+/// has a close `f64`.
+impl TryFrom<DataValue> for f64 {
+    type Error = SidError;
+    fn try_from(v: DataValue) -> Result<Self, SidError> {
+        match v {
+            DataValue::Float(f) => Ok(f),
+            DataValue::Int(i) => Ok(i as f64),
+            other => Err(mismatch("Float", &other)),
+        }
+    }
+}
+
+/// Accepts `Str` (decoded lossily if it isn't UTF-8) and `Char`.
+impl TryFrom<DataValue> for String {
+    type Error = SidError;
+    fn try_from(v: DataValue) -> Result<Self, SidError> {
+        match v {
+            DataValue::Str(s) => Ok(s.to_string_lossy().into_owned()),
+            DataValue::Char(c) => Ok(c),
+            other => Err(mismatch("Str", &other)),
+        }
+    }
+}
+
+impl TryFrom<DataValue> for CString {
+    type Error = SidError;
+    fn try_from(v: DataValue) -> Result<Self, SidError> {
+        match v {
+            DataValue::Str(s) => Ok(s),
+            other => Err(mismatch("Str", &other)),
+        }
+    }
+}
+
+impl TryFrom<DataValue> for Opaque {
+    type Error = SidError;
+    fn try_from(v: DataValue) -> Result<Self, SidError> {
+        match v {
+            DataValue::Opaque(o) => Ok(o),
+            other => Err(mismatch("Opaque", &other)),
+        }
+    }
+}
+
+/// Converts every element of a `List` or `Set`, failing on the first that
+/// doesn't convert.
+impl<T: TryFrom<DataValue, Error = SidError>> TryFrom<DataValue> for Vec<T> {
+    type Error = SidError;
+    fn try_from(v: DataValue) -> Result<Self, SidError> {
+        match v {
+            DataValue::List(items) | DataValue::Set(items) => {
+                items.into_iter().map(T::try_from).collect()
+            }
+            other => Err(mismatch("List", &other)),
+        }
+    }
+}
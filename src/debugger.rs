@@ -0,0 +1,479 @@
+//! `Debugger`: a program paused between steps, with breakpoints, stepping by
+//! call depth and watch expressions.  Front ends (the egui window, `sid
+//! debug`) only decide when to resume and what to show.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::bail;
+use libloading::Library;
+
+use crate::engine::into_data;
+use crate::*;
+
+/// Fuel for evaluating one watch expression, so a runaway watch can't hang
+/// the debugger.
+const WATCH_FUEL: u64 = 100_000;
+
+/// Where execution should pause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before the instruction at `line:col`, or, without a column, before the
+    /// first instruction reached on `line` when coming from another line.
+    Location { line: usize, col: Option<usize> },
+    /// Before a value is invoked through this label, e.g. `name!`.
+    Label(String),
+    /// Before this built-in runs, however it was reached.
+    Builtin(String),
+}
+
+/// Parses `LINE`, `LINE:COL`, `builtin NAME` or a label name.
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(name) = s.strip_prefix("builtin ") {
+            return Ok(Breakpoint::Builtin(name.trim().to_owned()));
+        }
+        if s.starts_with(|c: char| c.is_ascii_digit()) {
+            let (line, col) = match s.split_once(':') {
+                Some((line, col)) => (line, Some(col)),
+                None => (s, None),
+            };
+            let line = line
+                .parse()
+                .map_err(|e| format!("bad line '{}': {}", line, e))?;
+            let col = col
+                .map(|col| {
+                    col.parse()
+                        .map_err(|e| format!("bad column '{}': {}", col, e))
+                })
+                .transpose()?;
+            return Ok(Breakpoint::Location { line, col });
+        }
+        if s.is_empty() || s.contains(char::is_whitespace) {
+            return Err(format!(
+                "expected LINE, LINE:COL, builtin NAME or a label, got '{}'",
+                s
+            ));
+        }
+        Ok(Breakpoint::Label(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Location { line, col: None } => write!(f, "{}", line),
+            Breakpoint::Location {
+                line,
+                col: Some(col),
+            } => write!(f, "{}:{}", line, col),
+            Breakpoint::Label(name) => f.write_str(name),
+            Breakpoint::Builtin(name) => write!(f, "builtin {}", name),
+        }
+    }
+}
+
+/// How far to run before pausing again.  Depths are counted in active
+/// substack calls (`PushScope` / `PopScope` pairs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// To the next instruction, inside a call if the current one makes one.
+    StepInto,
+    /// To the next instruction at the current call depth or shallower.
+    StepOver,
+    /// To the next instruction after the current call returns.
+    StepOut,
+    /// Until a breakpoint is hit or the program ends.
+    Continue,
+}
+
+/// Why [`Debugger::advance`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The resume command is done, or there was none.
+    Paused,
+    /// The breakpoint at this index in [`Debugger::breakpoints`] was hit.
+    Breakpoint(usize),
+    /// The program stack is empty.
+    Finished,
+    /// The step budget ran out first; call `advance` again to go on.
+    Running,
+}
+
+/// A resume command in progress.
+#[derive(Clone, Copy)]
+struct Pending {
+    resume: Resume,
+    depth: usize,
+    /// Whether anything has run yet, so resuming from a breakpoint doesn't
+    /// hit it again straight away.
+    moved: bool,
+}
+
+/// A loaded program, run one step at a time.
+///
+/// Once a command is done the debugger rests before an instruction (or at
+/// the end); the `SourceSpan`, `PushScope`, `PopScope` and `TypeCheck`
+/// bookkeeping in between runs as part of the step that reaches it.  Only a
+/// command cut short by [`Debugger::advance`]'s step budget or by
+/// [`Debugger::pause`] can leave it elsewhere.
+///
+/// ```
+/// use sid::{Debugger, Resume, Stop};
+///
+/// let mut debugger = Debugger::new();
+/// debugger.load("1 2 add!\n3 mul!").unwrap();
+/// debugger.breakpoints.push("2".parse().unwrap());
+/// assert_eq!(debugger.resume(Resume::Continue).unwrap(), Stop::Breakpoint(0));
+/// assert_eq!(debugger.span().unwrap().line, 2);
+/// assert_eq!(debugger.resume(Resume::Continue).unwrap(), Stop::Finished);
+/// ```
+pub struct Debugger {
+    program_stack: Vec<ProgramValue>,
+    data_stack: Vec<TemplateValue>,
+    local_scope: HashMap<String, DataValue>,
+    scope_stack: Vec<Frame>,
+    global_scope: HashMap<String, DataValue>,
+    libraries: HashMap<String, Arc<Library>>,
+    span: Option<Span>,
+    side_effects: Box<dyn SideEffects>,
+    /// What the program may do beyond pure computation; applies to `@!`
+    /// calls from the next [`Debugger::load`] on.
+    pub sandbox: SandboxPolicy,
+    pub breakpoints: Vec<Breakpoint>,
+    /// Sid expressions evaluated by [`Debugger::watch_values`].
+    pub watches: Vec<String>,
+    comptime_builtins: HashMap<String, BuiltinEntry>,
+    builtins: HashMap<String, BuiltinEntry>,
+    pending: Option<Pending>,
+    /// Line of the last instruction executed, for line breakpoints.
+    last_line: Option<usize>,
+    error: Option<SidError>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    /// A debugger with nothing loaded, performing real I/O.
+    pub fn new() -> Self {
+        Self::with_side_effects(Box::new(RealSideEffects))
+    }
+
+    /// Like [`Debugger::new`], but performing I/O through `side_effects`.
+    pub fn with_side_effects(side_effects: Box<dyn SideEffects>) -> Self {
+        Self {
+            program_stack: Vec::new(),
+            data_stack: Vec::new(),
+            local_scope: HashMap::new(),
+            scope_stack: Vec::new(),
+            global_scope: default_scope(),
+            libraries: HashMap::new(),
+            span: None,
+            side_effects,
+            sandbox: SandboxPolicy::default(),
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            comptime_builtins: get_comptime_builtins(),
+            builtins: get_interpret_builtins(),
+            pending: None,
+            last_line: None,
+            error: None,
+        }
+    }
+
+    /// Parse, comptime-evaluate and render `source`, replacing whatever was
+    /// loaded before, and pause before its first instruction.  Breakpoints and
+    /// watches are kept.
+    pub fn load(&mut self, source: &str) -> anyhow::Result<()> {
+        let mut global_scope = default_scope();
+        let mut global_state = GlobalState::new(&mut global_scope);
+        global_state.sandbox = self.sandbox;
+        self.program_stack = compile_str(source, &self.comptime_builtins, &mut global_state)?;
+        self.data_stack.clear();
+        self.local_scope.clear();
+        self.scope_stack.clear();
+        self.global_scope = global_scope;
+        self.libraries.clear();
+        self.span = None;
+        self.pending = None;
+        self.last_line = None;
+        self.error = None;
+        while !self.program_stack.is_empty() && !self.at_instruction() {
+            if let Err(e) = self.step_value() {
+                self.error = Some(e.clone());
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Run `resume` to completion: [`Debugger::start`] then
+    /// [`Debugger::advance`] without a step limit.
+    pub fn resume(&mut self, resume: Resume) -> Result<Stop, SidError> {
+        self.start(resume);
+        self.advance(usize::MAX)
+    }
+
+    /// Begin `resume`, replacing any command still in progress.  Nothing runs
+    /// until [`Debugger::advance`].
+    pub fn start(&mut self, resume: Resume) {
+        self.pending = Some(Pending {
+            resume,
+            depth: self.depth(),
+            moved: false,
+        });
+    }
+
+    /// Carry on with the command given to [`Debugger::start`] for at most
+    /// `max_steps` program values.
+    ///
+    /// An error stops the program for good: it is returned from this and
+    /// every later call.
+    pub fn advance(&mut self, max_steps: usize) -> Result<Stop, SidError> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        let Some(mut pending) = self.pending else {
+            return Ok(Stop::Paused);
+        };
+        let mut steps = 0;
+        loop {
+            if self.program_stack.is_empty() {
+                self.pending = None;
+                return Ok(Stop::Finished);
+            }
+            if pending.moved && self.at_instruction() {
+                if let Some(i) = self.hit_breakpoint() {
+                    self.pending = None;
+                    return Ok(Stop::Breakpoint(i));
+                }
+                let done = match pending.resume {
+                    Resume::StepInto => true,
+                    Resume::StepOver => self.depth() <= pending.depth,
+                    Resume::StepOut => self.depth() < pending.depth,
+                    Resume::Continue => false,
+                };
+                if done {
+                    self.pending = None;
+                    return Ok(Stop::Paused);
+                }
+            }
+            if steps == max_steps {
+                self.pending = Some(pending);
+                return Ok(Stop::Running);
+            }
+            if self.at_instruction() {
+                self.last_line = self.span.map(|span| span.line);
+            }
+            if let Err(e) = self.step_value() {
+                self.pending = None;
+                self.error = Some(e.clone());
+                return Err(e);
+            }
+            pending.moved = true;
+            steps += 1;
+        }
+    }
+
+    /// Drop the command in progress, if any, leaving the program where it is.
+    pub fn pause(&mut self) {
+        self.pending = None;
+    }
+
+    /// Whether the program has ended, by finishing or failing.
+    pub fn is_finished(&self) -> bool {
+        self.error.is_some() || self.program_stack.is_empty()
+    }
+
+    /// Whether a command given to [`Debugger::start`] is still in progress.
+    pub fn is_running(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// The error that stopped the program, if it failed.
+    pub fn error(&self) -> Option<&SidError> {
+        self.error.as_ref()
+    }
+
+    /// The instruction that runs next.
+    pub fn current(&self) -> Option<&ProgramValue> {
+        self.program_stack.last()
+    }
+
+    /// Source location of the instruction that runs next, if known.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Number of active substack calls.
+    pub fn depth(&self) -> usize {
+        self.scope_stack.len()
+    }
+
+    /// The active calls, innermost first.
+    pub fn backtrace(&self) -> Backtrace {
+        Backtrace::of(&self.scope_stack)
+    }
+
+    pub fn program_stack(&self) -> &[ProgramValue] {
+        &self.program_stack
+    }
+
+    pub fn data_stack(&self) -> &[TemplateValue] {
+        &self.data_stack
+    }
+
+    pub fn local_scope(&self) -> &HashMap<String, DataValue> {
+        &self.local_scope
+    }
+
+    /// One `Frame` per active call, outermost first, each holding its
+    /// caller's local scope.
+    pub fn scope_stack(&self) -> &[Frame] {
+        &self.scope_stack
+    }
+
+    /// The local scope of the `frame`th call from the inside: 0 is the
+    /// current scope, 1 the caller's, and so on up to [`Debugger::depth`].
+    pub fn frame_scope(&self, frame: usize) -> Option<&HashMap<String, DataValue>> {
+        if frame == 0 {
+            return Some(&self.local_scope);
+        }
+        let i = self.scope_stack.len().checked_sub(frame)?;
+        Some(&self.scope_stack[i].saved_scope)
+    }
+
+    pub fn global_scope(&self) -> &HashMap<String, DataValue> {
+        &self.global_scope
+    }
+
+    /// Run `expression` against a copy of the current local and global scope
+    /// and return the values it leaves on an empty stack.  It can't change the
+    /// program: I/O goes nowhere, sandboxed capabilities are denied and it
+    /// gets a small fuel budget.
+    pub fn evaluate(&self, expression: &str) -> anyhow::Result<Vec<DataValue>> {
+        self.evaluate_in(expression, 0)
+    }
+
+    /// Like [`Debugger::evaluate`], but in the local scope of `frame` (see
+    /// [`Debugger::frame_scope`]).
+    pub fn evaluate_in(&self, expression: &str, frame: usize) -> anyhow::Result<Vec<DataValue>> {
+        let Some(local_scope) = self.frame_scope(frame) else {
+            bail!("no frame {}, the call depth is {}", frame, self.depth());
+        };
+        let mut global_scope = self.global_scope.clone();
+        let mut global_state =
+            GlobalState::with_side_effects(&mut global_scope, Box::new(MockSideEffects::new()));
+        global_state.sandbox = SandboxPolicy::deny_all();
+        global_state.limits.fuel = Some(WATCH_FUEL);
+        let program_stack = compile_str(expression, &self.comptime_builtins, &mut global_state)?;
+        let mut exe_state = ExeState {
+            program_stack,
+            data_stack: Vec::new(),
+            local_scope: local_scope.clone(),
+            scope_stack: Vec::new(),
+            global_state,
+            builtins: &self.builtins,
+        };
+        exe_state.run()?;
+        Ok(exe_state
+            .data_stack
+            .into_iter()
+            .map(into_data)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Each watch expression with what [`Debugger::evaluate`] made of it.
+    pub fn watch_values(&self) -> Vec<(&str, anyhow::Result<Vec<DataValue>>)> {
+        self.watches
+            .iter()
+            .map(|watch| (watch.as_str(), self.evaluate(watch)))
+            .collect()
+    }
+
+    /// Execute the program value on top of the program stack.
+    fn step_value(&mut self) -> Result<(), SidError> {
+        let side_effects = std::mem::replace(&mut self.side_effects, Box::new(RealSideEffects));
+        let mut global_state = GlobalState::with_side_effects(&mut self.global_scope, side_effects);
+        global_state.libraries = std::mem::take(&mut self.libraries);
+        global_state.span = self.span;
+        global_state.sandbox = self.sandbox;
+        let result = interpret_one(
+            &mut self.data_stack,
+            &mut self.program_stack,
+            &mut self.local_scope,
+            &mut self.scope_stack,
+            &mut global_state,
+            &self.builtins,
+        );
+        self.side_effects = global_state.side_effects;
+        self.libraries = global_state.libraries;
+        self.span = global_state.span;
+        result
+    }
+
+    /// Whether the next program value is a real instruction rather than
+    /// interpreter bookkeeping.
+    fn at_instruction(&self) -> bool {
+        !matches!(
+            self.program_stack.last(),
+            None | Some(
+                ProgramValue::SourceSpan(_)
+                    | ProgramValue::PushScope { .. }
+                    | ProgramValue::PopScope
+                    | ProgramValue::TypeCheck { .. }
+            )
+        )
+    }
+
+    /// Index of the first breakpoint matching the next instruction.
+    fn hit_breakpoint(&self) -> Option<usize> {
+        let invoked = match self.program_stack.last() {
+            Some(ProgramValue::Invoke) => match self.data_stack.last() {
+                Some(TemplateValue::Literal(ProgramValue::Data(value))) => Some(value),
+                _ => None,
+            },
+            _ => None,
+        };
+        self.breakpoints
+            .iter()
+            .position(|breakpoint| match breakpoint {
+                Breakpoint::Location { line, col } => self.span.is_some_and(|span| {
+                    span.line == *line
+                        && match col {
+                            Some(col) => span.col == *col,
+                            None => self.last_line != Some(*line),
+                        }
+                }),
+                Breakpoint::Label(name) => {
+                    matches!(invoked, Some(DataValue::Label(label)) if label == name)
+                }
+                Breakpoint::Builtin(name) => match invoked {
+                    Some(DataValue::BuiltIn(builtin)) => builtin == name,
+                    Some(DataValue::Label(label)) => {
+                        let builtin_names: HashSet<&str> =
+                            self.builtins.keys().map(String::as_str).collect();
+                        matches!(
+                            get_from_scope(
+                                label,
+                                Some(&self.local_scope),
+                                Some(&self.global_scope),
+                                Some(&builtin_names),
+                            ),
+                            Ok(DataValue::BuiltIn(builtin)) if builtin == *name
+                        )
+                    }
+                    _ => false,
+                },
+            })
+    }
+}
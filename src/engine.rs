@@ -0,0 +1,286 @@
+//! `Engine`: everything needed to run sid code from a Rust program, behind
+//! one owned value.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use libloading::Library;
+
+use crate::*;
+
+type Builtins = HashMap<String, BuiltinEntry>;
+
+/// An interpreter session that keeps its data stack, local scope and global
+/// scope between calls.
+///
+/// Source passed to [`Engine::eval_str`] runs in place on top of the engine's
+/// data stack, the way the repl runs a line: values it leaves behind can be
+/// [`pop`](Engine::pop)ped, and labels it binds with `local` stay visible to
+/// later evals and to [`Engine::call`].  If an eval or call fails, the data
+/// stack and local scope are put back as they were before it started.
+///
+/// ```
+/// let mut engine = sid::Engine::new();
+/// engine.eval_str("add_one (1 add!) local!").unwrap();
+/// let out = engine.call("add_one", vec![41i64.into()]).unwrap();
+/// assert_eq!(out, vec![sid::DataValue::Int(42)]);
+/// ```
+pub struct Engine {
+    global_scope: HashMap<String, DataValue>,
+    local_scope: HashMap<String, DataValue>,
+    data_stack: Vec<TemplateValue>,
+    libraries: HashMap<String, Arc<Library>>,
+    side_effects: Box<dyn SideEffects>,
+    /// What programs may do beyond pure computation; allows everything
+    /// unless set.
+    pub sandbox: SandboxPolicy,
+    /// Bounds on running programs.  Fuel used by one eval is gone for the
+    /// next, so it acts as a budget for the whole engine.
+    pub limits: Limits,
+    comptime_builtins: Builtins,
+    builtins: Builtins,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    /// An engine with the default global scope, performing real I/O.
+    pub fn new() -> Self {
+        Self::with_side_effects(Box::new(RealSideEffects))
+    }
+
+    /// Like [`Engine::new`], but performing I/O through `side_effects`.
+    pub fn with_side_effects(side_effects: Box<dyn SideEffects>) -> Self {
+        Self {
+            global_scope: default_scope(),
+            local_scope: HashMap::new(),
+            data_stack: Vec::new(),
+            libraries: HashMap::new(),
+            side_effects,
+            sandbox: SandboxPolicy::default(),
+            limits: Limits::default(),
+            comptime_builtins: get_comptime_builtins(),
+            builtins: get_interpret_builtins(),
+        }
+    }
+
+    /// Make `exec` callable from sid code as `name`, replacing any built-in of
+    /// the same name.  It is available at runtime only, not to `@!`.
+    ///
+    /// Arguments are checked against `args` (`args[0]` is the top of the
+    /// stack) before `exec` runs, exactly as for the interpreter's own
+    /// built-ins; `exec` then gets them in stack order, the top last.  What it
+    /// returns is checked against `ret` the same way, so a host function can't
+    /// hand sid code a value its signature doesn't allow.
+    pub fn register_builtin(
+        &mut self,
+        name: impl Into<String>,
+        args: Vec<SidType>,
+        ret: Vec<SidType>,
+        exec: impl Fn(&mut ExeState, Vec<DataValue>) -> Result<Vec<DataValue>, SidError> + 'static,
+    ) {
+        let name = name.into();
+        let checked_ret = ret.clone();
+        let checked_name = name.clone();
+        let exec = move |state: &mut ExeState, args: Vec<DataValue>| {
+            let results = exec(state, args)?;
+            let matches = results.len() == checked_ret.len()
+                && checked_ret
+                    .iter()
+                    .zip(results.iter().rev())
+                    .all(|(t, v)| t.matches(v));
+            if !matches {
+                return Err(SidError::TypeMismatch(format!(
+                    "builtin '{}': returned {:?}, expected {:?}",
+                    checked_name, results, checked_ret
+                )));
+            }
+            Ok(results)
+        };
+        self.builtins.insert(
+            name.clone(),
+            BuiltinEntry {
+                name,
+                args,
+                ret,
+                exec: Box::new(exec),
+            },
+        );
+    }
+
+    /// Make `types.<name>` the type of [`Opaque`] values created with
+    /// `type_name` `name`, so sid code can check for them in `typed_args`,
+    /// `match` and the like.
+    pub fn register_opaque_type(&mut self, name: &str) {
+        let key = DataValue::Label(name.to_owned());
+        let ty = DataValue::Type(SidType::Opaque(name.to_owned()));
+        match self.global_scope.get_mut("types") {
+            Some(DataValue::Map(types)) => match types.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, slot)) => *slot = ty,
+                None => types.push((key, ty)),
+            },
+            _ => {
+                self.global_scope
+                    .insert("types".to_owned(), DataValue::Map(vec![(key, ty)]));
+            }
+        }
+    }
+
+    /// Set `limits.deadline` to `timeout` from now, by the side effects' clock.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.limits.deadline = Some(self.side_effects.now() + timeout);
+    }
+
+    /// Parse, comptime-evaluate and run `source`.
+    pub fn eval_str(&mut self, source: &str) -> anyhow::Result<()> {
+        let mut global_state = GlobalState::new(&mut self.global_scope);
+        global_state.sandbox = self.sandbox;
+        let program = compile_str(source, &self.comptime_builtins, &mut global_state)?;
+        self.execute(|exe_state| {
+            exe_state.program_stack = program;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Read the file at `path` through the engine's side effects and run it
+    /// with [`Engine::eval_str`].
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let bytes = self
+            .side_effects
+            .read_file(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let Ok(source) = String::from_utf8(bytes) else {
+            bail!("{} is not valid UTF-8", path.display());
+        };
+        self.eval_str(&source)
+    }
+
+    /// Push `args` (so the last one ends up on top), invoke the value bound to
+    /// `name`, and return what it left on the stack above where it started.
+    ///
+    /// `name` is looked up like a label in code: local scope, then global
+    /// scope, then built-ins.  Dotted names reach into structs.
+    ///
+    /// Fails with `StackUnderflow` if the callee popped values that were on
+    /// the stack before the call; the stack is left as the callee left it.
+    pub fn call(&mut self, name: &str, args: Vec<DataValue>) -> Result<Vec<DataValue>, SidError> {
+        let base = self.data_stack.len();
+        self.execute(|exe_state| {
+            exe_state
+                .data_stack
+                .extend(args.into_iter().map(TemplateValue::from));
+            exe_state
+                .data_stack
+                .push(DataValue::Label(name.to_owned()).into());
+            exe_state.program_stack.push(ProgramValue::Invoke);
+            Ok(())
+        })?;
+        if self.data_stack.len() < base {
+            return Err(SidError::StackUnderflow(format!(
+                "{}: consumed {} value(s) pushed before the call",
+                name,
+                base - self.data_stack.len()
+            )));
+        }
+        self.data_stack
+            .split_off(base)
+            .into_iter()
+            .map(into_data)
+            .collect()
+    }
+
+    /// Push `value` onto the data stack.
+    pub fn push(&mut self, value: impl Into<DataValue>) {
+        self.data_stack.push(value.into().into());
+    }
+
+    /// Pop the top of the data stack, if there is one.
+    pub fn pop(&mut self) -> Result<Option<DataValue>, SidError> {
+        self.data_stack.pop().map(into_data).transpose()
+    }
+
+    /// Number of values on the data stack.
+    pub fn stack_len(&self) -> usize {
+        self.data_stack.len()
+    }
+
+    /// The data stack, top last.
+    pub fn data_stack(&self) -> &[TemplateValue] {
+        &self.data_stack
+    }
+
+    /// Empty the data stack.
+    pub fn clear_stack(&mut self) {
+        self.data_stack.clear();
+    }
+
+    /// The labels bound with `local` by earlier evals.
+    pub fn local_scope(&self) -> &HashMap<String, DataValue> {
+        &self.local_scope
+    }
+
+    pub fn global_scope(&self) -> &HashMap<String, DataValue> {
+        &self.global_scope
+    }
+
+    /// The value bound to `name` in the global scope.
+    pub fn get_global(&self, name: &str) -> Option<&DataValue> {
+        self.global_scope.get(name)
+    }
+
+    /// Bind `name` in the global scope, replacing any previous value.
+    pub fn set_global(&mut self, name: &str, value: impl Into<DataValue>) {
+        self.global_scope.insert(name.to_owned(), value.into());
+    }
+
+    /// Set up an `ExeState` from the engine, let `prepare` load it, run it,
+    /// and take back what should outlive the run.
+    fn execute(
+        &mut self,
+        prepare: impl FnOnce(&mut ExeState) -> Result<(), SidError>,
+    ) -> Result<(), SidError> {
+        let side_effects = std::mem::replace(&mut self.side_effects, Box::new(RealSideEffects));
+        let mut global_state = GlobalState::with_side_effects(&mut self.global_scope, side_effects);
+        global_state.libraries = std::mem::take(&mut self.libraries);
+        global_state.sandbox = self.sandbox;
+        global_state.limits = self.limits;
+        let mut exe_state = ExeState {
+            program_stack: Vec::new(),
+            data_stack: self.data_stack.clone(),
+            local_scope: self.local_scope.clone(),
+            scope_stack: Vec::new(),
+            global_state,
+            builtins: &self.builtins,
+        };
+        let result = prepare(&mut exe_state).and_then(|_| exe_state.run());
+        self.side_effects = exe_state.global_state.side_effects;
+        self.libraries = exe_state.global_state.libraries;
+        self.limits = exe_state.global_state.limits;
+        if result.is_ok() {
+            self.data_stack = exe_state.data_stack;
+            self.local_scope = exe_state.local_scope;
+        }
+        result
+    }
+}
+
+/// Unwrap a data stack entry.  Only `Data` should be left there between
+/// steps.
+pub(crate) fn into_data(tv: TemplateValue) -> Result<DataValue, SidError> {
+    match tv {
+        TemplateValue::Literal(ProgramValue::Data(v)) => Ok(v),
+        other => Err(SidError::Internal(format!(
+            "non-data value left on the data stack: {:?}",
+            other
+        ))),
+    }
+}
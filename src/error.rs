@@ -0,0 +1,151 @@
+use std::fmt;
+
+use crate::{Backtrace, Frame, Span};
+
+/// An error raised while rendering or executing a SID program.
+///
+/// Every variant carries a human-readable message that already names the
+/// operation that failed (e.g. `"builtin 'add': arg 0 expected …"`); the
+/// variant itself classifies the failure so embedders can react to it
+/// without parsing the message.  `Display` prints the message only.
+///
+/// Errors raised by an instruction whose source location is known come back
+/// wrapped in [`SidError::Located`]; use [`SidError::unlocated`] to match on
+/// the underlying variant.  Errors raised inside a substack call also carry a
+/// [`Backtrace`], in [`SidError::Traced`] around the located error.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SidError {
+    /// A value did not have the type an operation or contract required.
+    TypeMismatch(String),
+    /// An operation needed more values than the data stack (or argument list)
+    /// held.
+    StackUnderflow(String),
+    /// A label could not be resolved in local scope, global scope or the
+    /// built-ins, or a dot-access named a missing field.
+    UndefinedLabel(String),
+    /// Loading a header or library, or calling into C, failed.
+    FfiError(String),
+    /// A `while_do` / `do_while` body or condition broke the stack-size
+    /// contract, or a `StackSizeAssert` sentinel fired.
+    LoopInvariant(String),
+    /// `assert` was given `false`.
+    AssertionFailed(String),
+    /// Integer or float overflow, or division by zero.
+    Arithmetic(String),
+    /// Reading or writing the console or a file failed.
+    Io(String),
+    /// The `SandboxPolicy` does not allow the operation.
+    PermissionDenied(String),
+    /// A `Limits` bound (fuel, stack depth or deadline) was hit before the
+    /// next step.  The stacks are left as they were, so execution can resume
+    /// once the limit is raised.
+    LimitExceeded(String),
+    /// A value had the right type but an unusable shape or content, e.g. a
+    /// negative count or a `match` with no matching case.
+    InvalidArgument(String),
+    /// An interpreter invariant was violated (a sentinel without its pair, a
+    /// comptime-only value reaching runtime, …).  Indicates a bug rather than
+    /// a mistake in the SID program.
+    Internal(String),
+    /// Any of the above, raised by the instruction at `span`.
+    Located { span: Span, error: Box<SidError> },
+    /// Any of the above, raised while the calls in `backtrace` were active.
+    Traced {
+        backtrace: Backtrace,
+        error: Box<SidError>,
+    },
+}
+
+impl SidError {
+    /// The message carried by the error, without the variant name.
+    pub fn message(&self) -> &str {
+        match self {
+            SidError::TypeMismatch(m)
+            | SidError::StackUnderflow(m)
+            | SidError::UndefinedLabel(m)
+            | SidError::FfiError(m)
+            | SidError::LoopInvariant(m)
+            | SidError::AssertionFailed(m)
+            | SidError::Arithmetic(m)
+            | SidError::Io(m)
+            | SidError::PermissionDenied(m)
+            | SidError::LimitExceeded(m)
+            | SidError::InvalidArgument(m)
+            | SidError::Internal(m) => m,
+            SidError::Located { error, .. } | SidError::Traced { error, .. } => error.message(),
+        }
+    }
+
+    /// The source location the error was raised at, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            SidError::Located { span, .. } => Some(*span),
+            SidError::Traced { error, .. } => error.span(),
+            _ => None,
+        }
+    }
+
+    /// The substack calls active when the error was raised, if it was raised
+    /// inside one.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            SidError::Traced { backtrace, .. } => Some(backtrace),
+            _ => None,
+        }
+    }
+
+    /// The error without its location or backtrace.
+    pub fn unlocated(&self) -> &SidError {
+        match self {
+            SidError::Located { error, .. } | SidError::Traced { error, .. } => error.unlocated(),
+            other => other,
+        }
+    }
+
+    /// Attach `span` unless it is `None` or the error already has a location;
+    /// the innermost location is the most precise one.
+    pub fn located(self, span: Option<Span>) -> SidError {
+        match (self, span) {
+            (SidError::Traced { backtrace, error }, span) => SidError::Traced {
+                backtrace,
+                error: Box::new(error.located(span)),
+            },
+            (located @ SidError::Located { .. }, _) | (located, None) => located,
+            (error, Some(span)) => SidError::Located {
+                span,
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Record that the error passed through the calls in `frames` (a scope
+    /// stack, outermost first).  Calls recorded further in come first, so
+    /// nested interpreter loops build up one backtrace, innermost first.
+    pub fn traced(self, frames: &[Frame]) -> SidError {
+        let outer = Backtrace::of(frames);
+        if outer.is_empty() {
+            return self;
+        }
+        match self {
+            SidError::Traced {
+                mut backtrace,
+                error,
+            } => {
+                backtrace.calls.extend(outer.calls);
+                SidError::Traced { backtrace, error }
+            }
+            error => SidError::Traced {
+                backtrace: outer,
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for SidError {}
@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 
 use super::{
-    call_c_function, call_cfuncsig, get_from_scope, render_template, resolve_if_label, DataValue,
-    GlobalState, ProgramValue, SidType, TemplateValue,
+    call_c_function, call_cfuncsig, get_from_scope, render_template, resolve_if_label, Call,
+    Capability, DataValue, Frame, GlobalState, ProgramValue, SidError, SidType, SpanTable,
+    TemplateValue,
 };
 use crate::built_in::BuiltinEntry;
 
@@ -27,14 +28,20 @@ fn collect_args(
     param_names: &[String],
     n: usize,
     variadic: bool,
-    resolve: &dyn Fn(DataValue) -> DataValue,
+    resolve: &dyn Fn(DataValue) -> Result<DataValue, SidError>,
     context: &str,
-) -> Option<DataValue> {
-    let pop_one = |stack: &mut Vec<TemplateValue>, ctx: &str| -> DataValue {
+) -> Result<Option<DataValue>, SidError> {
+    let pop_one = |stack: &mut Vec<TemplateValue>, ctx: &str| -> Result<DataValue, SidError> {
         match stack.pop() {
-            Some(TemplateValue::Literal(ProgramValue::Data(v))) => v,
-            Some(other) => panic!("{}: argument is not a concrete value: {:?}", ctx, other),
-            None => panic!("{}: expected argument but stack was empty", ctx),
+            Some(TemplateValue::Literal(ProgramValue::Data(v))) => Ok(v),
+            Some(other) => Err(SidError::TypeMismatch(format!(
+                "{}: argument is not a concrete value: {:?}",
+                ctx, other
+            ))),
+            None => Err(SidError::StackUnderflow(format!(
+                "{}: expected argument but stack was empty",
+                ctx
+            ))),
         }
     };
 
@@ -73,44 +80,51 @@ fn collect_args(
                                     .unwrap_or_else(|| unreachable!());
                                 resolve(v)
                             })
-                            .collect();
+                            .collect::<Result<_, _>>()?;
                         let variadic_list = entries
                             .iter()
                             .find(|(k, _)| matches!(k, DataValue::Label(l) if l == "..."))
                             .map(|(_, v)| v.clone())
                             .unwrap_or(DataValue::List(vec![]));
                         let variadic_items = match variadic_list {
-                            DataValue::List(vs) => vs.into_iter().map(resolve).collect::<Vec<_>>(),
+                            DataValue::List(vs) => {
+                                vs.into_iter().map(resolve).collect::<Result<Vec<_>, _>>()?
+                            }
                             other => {
-                                panic!("{}: '...' key must be a List, got {:?}", context, other)
+                                return Err(SidError::TypeMismatch(format!(
+                                    "{}: '...' key must be a List, got {:?}",
+                                    context, other
+                                )))
                             }
                         };
                         items.extend(variadic_items);
-                        return Some(DataValue::List(items));
+                        return Ok(Some(DataValue::List(items)));
                     }
                 }
             }
         }
         // Stack form for variadic: top = List of variadic args, below = N fixed params.
-        let variadic_val = pop_one(data_stack, context);
-        let variadic_items = match resolve(variadic_val) {
-            DataValue::List(vs) => vs.into_iter().map(resolve).collect::<Vec<_>>(),
-            other => panic!(
-                "{}: expected List of variadic args on top of stack, got {:?}",
-                context, other
-            ),
+        let variadic_val = pop_one(data_stack, context)?;
+        let variadic_items = match resolve(variadic_val)? {
+            DataValue::List(vs) => vs.into_iter().map(resolve).collect::<Result<Vec<_>, _>>()?,
+            other => {
+                return Err(SidError::TypeMismatch(format!(
+                    "{}: expected List of variadic args on top of stack, got {:?}",
+                    context, other
+                )))
+            }
         };
         let mut fixed: Vec<DataValue> = (0..n)
-            .map(|_| resolve(pop_one(data_stack, context)))
-            .collect();
+            .map(|_| resolve(pop_one(data_stack, context)?))
+            .collect::<Result<_, _>>()?;
         fixed.reverse();
         fixed.extend(variadic_items);
-        return Some(DataValue::List(fixed));
+        return Ok(Some(DataValue::List(fixed)));
     }
 
     match n {
-        0 => None,
-        1 => Some(resolve(pop_one(data_stack, context))),
+        0 => Ok(None),
+        1 => Ok(Some(resolve(pop_one(data_stack, context)?)?)),
         _ => {
             // Struct form: single Map with keys matching all param names.
             if !param_names.is_empty() {
@@ -135,28 +149,30 @@ fn collect_args(
                             && param_names.iter().all(|p| map_keys.contains(p.as_str()))
                         {
                             data_stack.pop();
-                            let items: Vec<DataValue> =
-                                param_names
-                                    .iter()
-                                    .map(|name| {
-                                        let v = entries.iter()
-                  .find(|(k, _)| matches!(k, DataValue::Label(l) if l == name))
-                  .map(|(_, v)| v.clone())
-                  .unwrap_or_else(|| unreachable!());
-                                        resolve(v)
-                                    })
-                                    .collect();
-                            return Some(DataValue::List(items));
+                            let items: Vec<DataValue> = param_names
+                                .iter()
+                                .map(|name| {
+                                    let v = entries
+                                        .iter()
+                                        .find(
+                                            |(k, _)| matches!(k, DataValue::Label(l) if l == name),
+                                        )
+                                        .map(|(_, v)| v.clone())
+                                        .unwrap_or_else(|| unreachable!());
+                                    resolve(v)
+                                })
+                                .collect::<Result<_, _>>()?;
+                            return Ok(Some(DataValue::List(items)));
                         }
                     }
                 }
             }
             // Stack form: pop N items, reverse to get declaration order.
             let mut items: Vec<DataValue> = (0..n)
-                .map(|_| resolve(pop_one(data_stack, context)))
-                .collect();
+                .map(|_| resolve(pop_one(data_stack, context)?))
+                .collect::<Result<_, _>>()?;
             items.reverse();
-            Some(DataValue::List(items))
+            Ok(Some(DataValue::List(items)))
         }
     }
 }
@@ -165,9 +181,9 @@ fn collect_args(
 /// via scope before matching.
 ///
 /// `types[0]` = top of stack, `types[N-1]` = deepest checked item.
-/// Panics with a detailed message on the first mismatch, or if the stack is
-/// too shallow. `label` ("args"/"ret") and `context` (callable description)
-/// are included in any panic message.
+/// Returns an error with a detailed message on the first mismatch, or if the
+/// stack is too shallow. `label` ("args"/"ret") and `context` (callable
+/// description) are included in any error message.
 ///
 /// When a label is found that doesn't match the expected type, it is resolved
 /// from scope and the resolved value replaces it in `data_stack` in-place
@@ -179,16 +195,16 @@ pub(crate) fn check_type_contract(
     context: &str,
     local_scope: &HashMap<String, DataValue>,
     global_scope: &HashMap<String, DataValue>,
-    builtin_names: &std::collections::HashSet<&'static str>,
-) {
+    builtin_names: &std::collections::HashSet<&str>,
+) -> Result<(), SidError> {
     if data_stack.len() < types.len() {
-        panic!(
+        return Err(SidError::StackUnderflow(format!(
             "{} {} check failed: expected {} items on stack, only {} available",
             context,
             label,
             types.len(),
             data_stack.len()
-        );
+        )));
     }
     let stack_len = data_stack.len();
     for (i, expected) in types.iter().enumerate() {
@@ -196,10 +212,10 @@ pub(crate) fn check_type_contract(
         let tv = &data_stack[stack_idx];
         let actual = match tv {
             TemplateValue::Literal(ProgramValue::Data(v)) => v,
-            other => panic!(
-        "{} {} check failed: position {} (0=top): expected {:?}, got non-concrete value {:?}",
-        context, label, i, expected, other
-      ),
+            other => return Err(SidError::TypeMismatch(format!(
+                "{} {} check failed: position {} (0=top): expected {:?}, got non-concrete value {:?}",
+                context, label, i, expected, other
+            ))),
         };
         if !expected.matches(actual) {
             if let DataValue::Label(_) = actual {
@@ -213,17 +229,18 @@ pub(crate) fn check_type_contract(
                     data_stack[stack_idx] = TemplateValue::from(resolved);
                     continue;
                 }
-                panic!(
-          "{} {} check failed: position {} (0=top): expected {:?}, label resolved to {:?}",
-          context, label, i, expected, &data_stack[stack_idx]
-        );
+                return Err(SidError::TypeMismatch(format!(
+                    "{} {} check failed: position {} (0=top): expected {:?}, label resolved to {:?}",
+                    context, label, i, expected, &data_stack[stack_idx]
+                )));
             }
-            panic!(
+            return Err(SidError::TypeMismatch(format!(
                 "{} {} check failed: position {} (0=top): expected {:?}, got {:?}",
                 context, label, i, expected, actual
-            );
+            )));
         }
     }
+    Ok(())
 }
 
 pub struct ExeState<'a> {
@@ -231,11 +248,34 @@ pub struct ExeState<'a> {
     pub data_stack: Vec<TemplateValue>,
     pub local_scope: HashMap<String, DataValue>,
     /// Scope stack used by `PushScope`/`PopScope` sentinels to isolate substack
-    /// local bindings.  Each `PushScope` pushes the current `local_scope` here
-    /// and installs a fresh empty one; `PopScope` restores it.
-    pub scope_stack: Vec<HashMap<String, DataValue>>,
+    /// local bindings.  Each `PushScope` pushes a `Frame` holding the current
+    /// `local_scope` and the call being made, and installs a fresh empty scope;
+    /// `PopScope` restores it.  Read innermost-last, it is the call stack.
+    pub scope_stack: Vec<Frame>,
     pub global_state: GlobalState<'a>,
-    pub builtins: &'a HashMap<&'static str, BuiltinEntry>,
+    pub builtins: &'a HashMap<String, BuiltinEntry>,
+}
+
+impl<'a> ExeState<'a> {
+    /// Run until the program stack is empty.
+    ///
+    /// Like `interpret_one`, an error leaves the state where it stopped.  In
+    /// particular a `SidError::LimitExceeded` is raised before the step it
+    /// would have taken, so after raising `global_state.limits` calling `run`
+    /// again carries on as if the limit had never been hit.
+    pub fn run(&mut self) -> Result<(), SidError> {
+        while !self.program_stack.is_empty() {
+            interpret_one(
+                &mut self.data_stack,
+                &mut self.program_stack,
+                &mut self.local_scope,
+                &mut self.scope_stack,
+                &mut self.global_state,
+                self.builtins,
+            )?;
+        }
+        Ok(())
+    }
 }
 
 pub fn invoke<'a>(
@@ -243,28 +283,45 @@ pub fn invoke<'a>(
     program_stack: &mut Vec<ProgramValue>,
     local_scope: &mut HashMap<String, DataValue>,
     global_state: &mut GlobalState<'a>,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
-) {
-    let builtin_names: std::collections::HashSet<&'static str> = builtins.keys().copied().collect();
+    builtins: &HashMap<String, BuiltinEntry>,
+) -> Result<(), SidError> {
+    let builtin_names: std::collections::HashSet<&str> =
+        builtins.keys().map(String::as_str).collect();
+    let mut call = Call {
+        callee: None,
+        call_site: global_state.span,
+    };
     let value = match data_stack.pop() {
-        Some(TemplateValue::Literal(ProgramValue::Data(DataValue::Label(l)))) => get_from_scope(
-            &l,
-            Some(local_scope),
-            Some(global_state.scope),
-            Some(&builtin_names),
-        )
-        .expect("label resolution failed"),
+        Some(TemplateValue::Literal(ProgramValue::Data(DataValue::Label(l)))) => {
+            let value = get_from_scope(
+                &l,
+                Some(local_scope),
+                Some(global_state.scope),
+                Some(&builtin_names),
+            )?;
+            call.callee = Some(l);
+            value
+        }
         Some(TemplateValue::Literal(ProgramValue::Data(v))) => v,
-        Some(other) => panic!("Invoked on non-data stack entry: {:?}", other),
-        None => panic!("Invoked on empty data_stack!"),
+        Some(other) => {
+            return Err(SidError::TypeMismatch(format!(
+                "Invoked on non-data stack entry: {:?}",
+                other
+            )))
+        }
+        None => {
+            return Err(SidError::StackUnderflow(
+                "Invoked on empty data_stack!".to_owned(),
+            ))
+        }
     };
     match value {
         DataValue::Substack {
-            body: mut s,
+            body,
             args,
             ret,
+            spans,
         } => {
-            s.reverse();
             let block_placed = args.is_some();
             let names: Vec<String> = args
                 .as_ref()
@@ -309,6 +366,7 @@ pub fn invoke<'a>(
                     }
                 }
                 let arg_types: Vec<SidType> = arg_fields.iter().map(|(_, t)| t.clone()).collect();
+                // The callee was never entered, but it belongs in the backtrace.
                 check_type_contract(
                     data_stack,
                     &arg_types,
@@ -317,7 +375,13 @@ pub fn invoke<'a>(
                     local_scope,
                     global_state.scope,
                     &builtin_names,
-                );
+                )
+                .map_err(|e| {
+                    e.located(global_state.span).traced(&[Frame {
+                        call: call.clone(),
+                        saved_scope: HashMap::new(),
+                    }])
+                })?;
                 let n = arg_fields.len();
                 let insert_pos = data_stack.len() - n;
                 data_stack.insert(insert_pos, TemplateValue::from(DataValue::StackBlock));
@@ -330,13 +394,27 @@ pub fn invoke<'a>(
                     block_placed,
                 }),
             }
+            // Once the body has run, errors belong to the call site again.
+            if !spans.is_empty() {
+                program_stack.push(ProgramValue::SourceSpan(global_state.span));
+            }
             program_stack.push(ProgramValue::PopScope);
-            program_stack.append(&mut s);
-            program_stack.push(ProgramValue::PushScope { names });
+            schedule_body(program_stack, body, &spans);
+            program_stack.push(ProgramValue::PushScope { names, call });
         }
 
         DataValue::BuiltIn(name) => {
-            let entry = &builtins[name.as_str()];
+            let entry = builtins.get(name.as_str()).ok_or_else(|| {
+                SidError::UndefinedLabel(format!("builtin '{}' is not available here", name))
+            })?;
+            if data_stack.len() < entry.args.len() {
+                return Err(SidError::StackUnderflow(format!(
+                    "builtin '{}': expected {} argument(s), only {} on stack",
+                    name,
+                    entry.args.len(),
+                    data_stack.len()
+                )));
+            }
             let mut exe_state = ExeState {
                 program_stack: std::mem::take(program_stack),
                 data_stack: std::mem::take(data_stack),
@@ -346,8 +424,14 @@ pub fn invoke<'a>(
                 builtins,
             };
             exe_state.global_state.libraries = std::mem::take(&mut global_state.libraries);
+            exe_state.global_state.sandbox = global_state.sandbox;
+            exe_state.global_state.limits = global_state.limits;
+            std::mem::swap(
+                &mut exe_state.global_state.side_effects,
+                &mut global_state.side_effects,
+            );
 
-            let arg_values: Vec<DataValue> = entry
+            let arg_values: Result<Vec<DataValue>, SidError> = entry
                 .args
                 .iter()
                 .enumerate()
@@ -358,14 +442,17 @@ pub fn invoke<'a>(
                     let v = match tv {
                         TemplateValue::Literal(ProgramValue::Data(v)) => v,
                         other => {
-                            panic!("builtin '{}': argument is not concrete: {:?}", name, other)
+                            return Err(SidError::TypeMismatch(format!(
+                                "builtin '{}': argument is not concrete: {:?}",
+                                name, other
+                            )))
                         }
                     };
-                    let should_keep_label = matches!(expected_type, SidType::Label);
+                    let should_keep_label = expected_type.keeps_labels();
                     if expected_type.matches(&v)
                         && (should_keep_label || !matches!(v, DataValue::Label(_)))
                     {
-                        v
+                        Ok(v)
                     } else if let DataValue::Label(ref l) = v {
                         let resolved = get_from_scope(
                             l,
@@ -375,41 +462,52 @@ pub fn invoke<'a>(
                         )
                         .unwrap_or_else(|_| v.clone());
                         if expected_type.matches(&resolved) {
-                            resolved
+                            Ok(resolved)
                         } else {
-                            panic!(
+                            Err(SidError::TypeMismatch(format!(
                                 "builtin '{}': arg {} expected {:?}, label '{}' resolved to {:?}",
                                 name, i, expected_type, l, resolved
-                            );
+                            )))
                         }
                     } else if expected_type.matches(&v) {
-                        v
+                        Ok(v)
                     } else {
-                        panic!(
+                        Err(SidError::TypeMismatch(format!(
                             "builtin '{}': arg {} expected {:?}, got {:?}",
                             name, i, expected_type, v
-                        );
+                        )))
                     }
                 })
                 .collect();
 
-            for _ in 0..entry.args.len() {
-                exe_state.data_stack.pop();
-            }
-
-            let results = (entry.exec)(&mut exe_state, arg_values);
+            // Run the builtin only if its arguments were acceptable, but always
+            // hand the state back to the caller.
+            let results = arg_values.and_then(|arg_values| {
+                for _ in 0..entry.args.len() {
+                    exe_state.data_stack.pop();
+                }
+                (entry.exec)(&mut exe_state, arg_values)
+            });
 
-            for result in results {
-                exe_state.data_stack.push(TemplateValue::from(result));
+            if let Ok(ref results) = results {
+                for result in results {
+                    exe_state
+                        .data_stack
+                        .push(TemplateValue::from(result.clone()));
+                }
             }
 
             *program_stack = exe_state.program_stack;
             *data_stack = exe_state.data_stack;
             *local_scope = exe_state.local_scope;
             global_state.libraries = exe_state.global_state.libraries;
+            global_state.side_effects = exe_state.global_state.side_effects;
+            global_state.limits = exe_state.global_state.limits;
+            results?;
         }
         DataValue::CFuncSig(sig) => {
             let ctx = format!("CFuncSig '{}'", sig.name);
+            global_state.sandbox.require(Capability::Ffi, &ctx)?;
             let resolve = |v: DataValue| match v {
                 DataValue::Label(ref l) => get_from_scope(
                     l,
@@ -417,8 +515,8 @@ pub fn invoke<'a>(
                     Some(global_state.scope),
                     Some(&builtin_names),
                 )
-                .unwrap_or_else(|e| panic!("CFuncSig '{}': {}", sig.name, e)),
-                other => other,
+                .map_err(|e| SidError::UndefinedLabel(format!("CFuncSig '{}': {}", sig.name, e))),
+                other => Ok(other),
             };
             let arg = collect_args(
                 data_stack,
@@ -427,15 +525,18 @@ pub fn invoke<'a>(
                 sig.variadic,
                 &resolve,
                 &ctx,
-            );
-            if let Some(result) = call_cfuncsig(&sig, arg, &global_state.libraries)
-                .unwrap_or_else(|e| panic!("CFuncSig '{}' call error: {}", sig.name, e))
+            )?;
+            if let Some(result) =
+                call_cfuncsig(&sig, arg, &global_state.libraries).map_err(|e| {
+                    SidError::FfiError(format!("CFuncSig '{}' call error: {}", sig.name, e))
+                })?
             {
                 data_stack.push(TemplateValue::from(result));
             }
         }
         DataValue::CFunction(f) => {
             let ctx = format!("CFunction '{}'", f.name);
+            global_state.sandbox.require(Capability::Ffi, &ctx)?;
             let resolve = |v: DataValue| match v {
                 DataValue::Label(ref l) => get_from_scope(
                     l,
@@ -443,8 +544,8 @@ pub fn invoke<'a>(
                     Some(global_state.scope),
                     Some(&builtin_names),
                 )
-                .unwrap_or_else(|e| panic!("CFunction '{}': {}", f.name, e)),
-                other => other,
+                .map_err(|e| SidError::UndefinedLabel(format!("CFunction '{}': {}", f.name, e))),
+                other => Ok(other),
             };
             let arg = collect_args(
                 data_stack,
@@ -453,23 +554,50 @@ pub fn invoke<'a>(
                 f.sig.variadic,
                 &resolve,
                 &ctx,
-            );
-            if let Some(result) = call_c_function(&f, arg)
-                .unwrap_or_else(|e| panic!("CFunction '{}' returned error: {}", f.name, e))
-            {
+            )?;
+            if let Some(result) = call_c_function(&f, arg).map_err(|e| {
+                SidError::FfiError(format!("CFunction '{}' returned error: {}", f.name, e))
+            })? {
                 data_stack.push(TemplateValue::from(result));
             }
         }
-        _ => panic!("Invalid object invoked."),
+        other => {
+            return Err(SidError::TypeMismatch(format!(
+                "Invalid object invoked: {:?}",
+                other
+            )))
+        }
     }
+    Ok(())
 }
 
+/// Push `body` onto `program_stack` so that its first entry runs next.
+///
+/// Every entry with a known span is preceded by a `SourceSpan` sentinel so
+/// errors raised while executing it can be located.
+pub fn schedule_body(
+    program_stack: &mut Vec<ProgramValue>,
+    body: Vec<ProgramValue>,
+    spans: &SpanTable,
+) {
+    for (i, pv) in body.into_iter().enumerate().rev() {
+        program_stack.push(pv);
+        if let Some(span) = spans.get(i) {
+            program_stack.push(ProgramValue::SourceSpan(Some(span)));
+        }
+    }
+}
+
+/// Run `program` to completion.
+///
+/// Returns the first error raised; the interpreter state at that point is
+/// discarded.
 pub fn interpret<'a>(
     program: Vec<ProgramValue>,
     data_stack: Vec<TemplateValue>,
     global_state: GlobalState<'a>,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
-) {
+    builtins: &HashMap<String, BuiltinEntry>,
+) -> Result<(), SidError> {
     let local_scope = HashMap::new();
     let mut exe_state = ExeState {
         program_stack: program,
@@ -479,35 +607,60 @@ pub fn interpret<'a>(
         global_state,
         builtins,
     };
-    while !exe_state.program_stack.is_empty() {
-        interpret_one(
-            &mut exe_state.data_stack,
-            &mut exe_state.program_stack,
-            &mut exe_state.local_scope,
-            &mut exe_state.scope_stack,
-            &mut exe_state.global_state,
-            builtins,
-        )
-    }
+    exe_state.run()
 }
 
+/// Pop and execute a single `ProgramValue`.
+///
+/// On error the stacks are left as they were when the failing operation gave
+/// up, so a caller stepping through a program can inspect them.  If the
+/// failing instruction has a known source span the error is wrapped in
+/// `SidError::Located`.  `global_state.limits` are checked (and one unit of
+/// fuel used) before anything is popped.
 pub fn interpret_one<'a>(
     data_stack: &mut Vec<TemplateValue>,
     program_stack: &mut Vec<ProgramValue>,
     local_scope: &mut HashMap<String, DataValue>,
-    scope_stack: &mut Vec<HashMap<String, DataValue>>,
+    scope_stack: &mut Vec<Frame>,
     global_state: &mut GlobalState<'a>,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
-) {
+    builtins: &HashMap<String, BuiltinEntry>,
+) -> Result<(), SidError> {
+    let side_effects = &mut global_state.side_effects;
+    global_state
+        .limits
+        .check(data_stack.len(), program_stack.len(), || side_effects.now())
+        .map_err(|e| e.located(global_state.span).traced(scope_stack))?;
+    execute_one(
+        data_stack,
+        program_stack,
+        local_scope,
+        scope_stack,
+        global_state,
+        builtins,
+    )
+    .map_err(|e| e.located(global_state.span).traced(scope_stack))
+}
+
+fn execute_one<'a>(
+    data_stack: &mut Vec<TemplateValue>,
+    program_stack: &mut Vec<ProgramValue>,
+    local_scope: &mut HashMap<String, DataValue>,
+    scope_stack: &mut Vec<Frame>,
+    global_state: &mut GlobalState<'a>,
+    builtins: &HashMap<String, BuiltinEntry>,
+) -> Result<(), SidError> {
     use ProgramValue as PV;
-    let operation = program_stack.pop().unwrap();
-    let builtin_names: std::collections::HashSet<&'static str> = builtins.keys().copied().collect();
+    let operation = program_stack.pop().ok_or_else(|| {
+        SidError::StackUnderflow("interpret_one called with an empty program stack".to_owned())
+    })?;
+    let builtin_names: std::collections::HashSet<&str> =
+        builtins.keys().map(String::as_str).collect();
     match operation {
         PV::Data(v) => {
             data_stack.push(TemplateValue::Literal(PV::Data(v)));
         }
         PV::Template(t) => {
-            let rendered = render_template(t, data_stack, local_scope, global_state, builtins);
+            let rendered = render_template(t, data_stack, local_scope, global_state, builtins)?;
             data_stack.push(TemplateValue::from(rendered));
         }
         PV::Invoke | PV::ComptimeInvoke => {
@@ -517,19 +670,19 @@ pub fn interpret_one<'a>(
                 local_scope,
                 global_state,
                 builtins,
-            );
+            )?;
         }
         PV::StackSizeAssert {
             expected_len,
             message,
         } => {
             if data_stack.len() != expected_len {
-                panic!(
+                return Err(SidError::LoopInvariant(format!(
                     "{} (expected stack size {}, got {})",
                     message,
                     expected_len,
                     data_stack.len()
-                );
+                )));
             }
         }
         PV::CondLoop {
@@ -538,17 +691,20 @@ pub fn interpret_one<'a>(
             expected_len,
         } => {
             if data_stack.len() != expected_len + 1 {
-                panic!(
-          "loop condition must leave exactly one Bool on top (expected stack size {}, got {})",
-          expected_len + 1, data_stack.len()
-        );
+                return Err(SidError::LoopInvariant(format!(
+                    "loop condition must leave exactly one Bool on top (expected stack size {}, got {})",
+                    expected_len + 1,
+                    data_stack.len()
+                )));
             }
             let bool_val = match data_stack.pop() {
                 Some(TemplateValue::Literal(ProgramValue::Data(DataValue::Bool(b)))) => b,
-                Some(other) => panic!(
-                    "loop condition must leave a Bool on top of the stack, got {:?}",
-                    other
-                ),
+                Some(other) => {
+                    return Err(SidError::LoopInvariant(format!(
+                        "loop condition must leave a Bool on top of the stack, got {:?}",
+                        other
+                    )))
+                }
                 None => unreachable!(),
             };
             if bool_val {
@@ -564,16 +720,20 @@ pub fn interpret_one<'a>(
             }
         }
         PV::CondLoopStart { cond, body } => {
-            let bool_val = match data_stack.pop() {
-                Some(TemplateValue::Literal(ProgramValue::Data(DataValue::Bool(b)))) => b,
-                Some(other) => panic!(
-                    "while_do condition must leave a Bool on top of the stack, got {:?}",
-                    other
-                ),
-                None => {
-                    panic!("while_do condition must leave a Bool on top of the stack (stack empty)")
-                }
-            };
+            let bool_val =
+                match data_stack.pop() {
+                    Some(TemplateValue::Literal(ProgramValue::Data(DataValue::Bool(b)))) => b,
+                    Some(other) => {
+                        return Err(SidError::LoopInvariant(format!(
+                            "while_do condition must leave a Bool on top of the stack, got {:?}",
+                            other
+                        )))
+                    }
+                    None => return Err(SidError::LoopInvariant(
+                        "while_do condition must leave a Bool on top of the stack (stack empty)"
+                            .to_owned(),
+                    )),
+                };
             if bool_val {
                 let expected_len = data_stack.len();
                 program_stack.push(PV::CondLoop {
@@ -601,18 +761,21 @@ pub fn interpret_one<'a>(
                             TemplateValue::Literal(ProgramValue::Data(DataValue::StackBlock))
                         )
                     })
-                    .unwrap_or_else(|| {
-                        panic!("TypeCheck ({}): no StackBlock found on data stack", context)
-                    });
+                    .ok_or_else(|| {
+                        SidError::Internal(format!(
+                            "TypeCheck ({}): no StackBlock found on data stack",
+                            context
+                        ))
+                    })?;
                 if let Some(ret_types) = types {
                     let results = &data_stack[block_pos + 1..];
                     if results.len() != ret_types.len() {
-                        panic!(
+                        return Err(SidError::TypeMismatch(format!(
                             "TypeCheck ({}): expected {} return value(s) above StackBlock, got {}",
                             context,
                             ret_types.len(),
                             results.len()
-                        );
+                        )));
                     }
                     let results_mut = &mut data_stack[block_pos + 1..];
                     check_type_contract(
@@ -623,7 +786,7 @@ pub fn interpret_one<'a>(
                         local_scope,
                         global_state.scope,
                         &builtin_names,
-                    );
+                    )?;
                 }
                 data_stack.remove(block_pos);
             } else if let Some(ret_types) = types {
@@ -635,23 +798,29 @@ pub fn interpret_one<'a>(
                     local_scope,
                     global_state.scope,
                     &builtin_names,
-                );
+                )?;
             }
         }
-        PV::PushScope { names } => {
-            let old_scope = std::mem::replace(local_scope, HashMap::new());
-            scope_stack.push(old_scope);
+        PV::PushScope { names, call } => {
+            scope_stack.push(Frame {
+                call,
+                saved_scope: std::mem::take(local_scope),
+            });
             for name in names.into_iter() {
                 let value = match data_stack.pop() {
                     Some(TemplateValue::Literal(ProgramValue::Data(v))) => v,
-                    Some(other) => panic!(
-                        "PushScope: arg '{}' is not a concrete value: {:?}",
-                        name, other
-                    ),
-                    None => panic!(
-                        "PushScope: expected arg '{}' but data stack was empty",
-                        name
-                    ),
+                    Some(other) => {
+                        return Err(SidError::TypeMismatch(format!(
+                            "PushScope: arg '{}' is not a concrete value: {:?}",
+                            name, other
+                        )))
+                    }
+                    None => {
+                        return Err(SidError::StackUnderflow(format!(
+                            "PushScope: expected arg '{}' but data stack was empty",
+                            name
+                        )))
+                    }
                 };
                 local_scope.insert(name, value);
             }
@@ -659,7 +828,15 @@ pub fn interpret_one<'a>(
         PV::PopScope => {
             *local_scope = scope_stack
                 .pop()
-                .expect("PopScope with no matching PushScope");
+                .ok_or_else(|| {
+                    SidError::Internal("PopScope with no matching PushScope".to_owned())
+                })?
+                .saved_scope;
+        }
+        PV::SourceSpan(span) => {
+            global_state.span = span;
         }
+        PV::ListIter(iter) => iter.step(data_stack, program_stack)?,
     }
+    Ok(())
 }
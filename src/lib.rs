@@ -1,5 +1,15 @@
 // We create side-effects through a trait implementation
 // (This allows mocking all side effects in one for testing)
+mod side_effects;
+pub use side_effects::{MockSideEffects, RealSideEffects, SideEffects};
+mod sandbox;
+pub use sandbox::{Capability, SandboxPolicy};
+mod limits;
+pub use limits::Limits;
+mod opaque;
+pub use opaque::Opaque;
+mod backtrace;
+pub use backtrace::{Backtrace, Call, Frame};
 pub mod type_system;
 pub use type_system::SidType;
 pub mod c_ffi;
@@ -7,6 +17,10 @@ pub mod c_ffi;
 /// pre-populate the library registry (e.g. after `c_link_lib` changes).
 pub use c_ffi::open_library as c_ffi_open_library;
 pub use c_ffi::{call_c_function, call_cfuncsig, parse_c_header, CFunc, CFuncSig, CType};
+mod error;
+pub use error::SidError;
+mod span;
+pub use span::{render_snippet, Span, SpanTable};
 mod types;
 pub use types::GlobalState;
 pub use types::*;
@@ -24,6 +38,11 @@ pub use built_in::BuiltinEntry;
 pub use built_in::*;
 mod comptime;
 pub use comptime::*;
+mod convert;
+mod engine;
+pub use engine::Engine;
+mod debugger;
+pub use debugger::{Breakpoint, Debugger, Resume, Stop};
 #[cfg(feature = "llvm")]
 pub mod llvm_backend;
 //
@@ -0,0 +1,56 @@
+use std::time::SystemTime;
+
+use crate::SidError;
+
+/// Bounds on how much a program may do, checked by `interpret_one` before
+/// every step.
+///
+/// A check that fails returns [`SidError::LimitExceeded`] without touching
+/// the stacks, so a caller driving `interpret_one` itself can raise the limit
+/// (e.g. add fuel) and carry on where the program stopped.  The default sets
+/// no limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Steps left to run; each `interpret_one` uses one.
+    pub fuel: Option<u64>,
+    /// Most values the data stack may hold.
+    pub max_data_stack: Option<usize>,
+    /// Most entries the program stack may hold, which bounds recursion.
+    pub max_program_stack: Option<usize>,
+    /// Time, as told by `SideEffects::now`, after which no step may start.
+    pub deadline: Option<SystemTime>,
+}
+
+impl Limits {
+    /// Fail if the next step would break a limit, otherwise use one unit of
+    /// fuel.  `now` is only called when there is a deadline.
+    pub(crate) fn check(
+        &mut self,
+        data_len: usize,
+        program_len: usize,
+        now: impl FnOnce() -> SystemTime,
+    ) -> Result<(), SidError> {
+        if self.fuel == Some(0) {
+            return Err(SidError::LimitExceeded("out of fuel".to_owned()));
+        }
+        if let Some(max) = self.max_data_stack.filter(|&max| data_len > max) {
+            return Err(SidError::LimitExceeded(format!(
+                "data stack holds {} values, more than the limit of {}",
+                data_len, max
+            )));
+        }
+        if let Some(max) = self.max_program_stack.filter(|&max| program_len > max) {
+            return Err(SidError::LimitExceeded(format!(
+                "program stack holds {} entries, more than the limit of {}",
+                program_len, max
+            )));
+        }
+        if self.deadline.is_some_and(|deadline| now() >= deadline) {
+            return Err(SidError::LimitExceeded("deadline passed".to_owned()));
+        }
+        if let Some(fuel) = &mut self.fuel {
+            *fuel -= 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// A host object handed to sid code, such as a file handle or a parsed
+/// document.
+///
+/// Sid code can only move it around, compare it and check its type: an
+/// `Opaque` matches `SidType::Opaque` with the same type name.  Host built-ins
+/// get the object back with [`Opaque::downcast_ref`], which fails rather than
+/// misreading memory if the object is of another Rust type.
+///
+/// Clones share the object.  Two `Opaque`s are equal only if they share it.
+#[derive(Clone)]
+pub struct Opaque {
+    type_name: String,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+impl Opaque {
+    /// Wrap `value`, to be known to sid code as `type_name`.
+    pub fn new<T: Any + Send + Sync>(type_name: impl Into<String>, value: T) -> Self {
+        Self::from_arc(type_name, Arc::new(value))
+    }
+
+    /// Like [`Opaque::new`], for an object the host keeps a handle to.
+    pub fn from_arc<T: Any + Send + Sync>(type_name: impl Into<String>, value: Arc<T>) -> Self {
+        Self {
+            type_name: type_name.into(),
+            value,
+        }
+    }
+
+    /// The name the object was given, which its `SidType` matches on.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// The object, if it is a `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    /// A shared handle to the object, if it is a `T`.
+    pub fn downcast<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.value.clone().downcast().ok()
+    }
+}
+
+impl fmt::Debug for Opaque {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Opaque({})", self.type_name)
+    }
+}
+
+impl PartialEq for Opaque {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::{bail, Result};
+use std::fmt;
 use std::iter::Peekable;
 use unicode_segmentation::{Graphemes, UnicodeSegmentation};
 
@@ -13,122 +14,365 @@ mod parse_template;
 use parse_char::parse_char;
 pub use parse_label::parse_label;
 use parse_number::parse_number;
-use parse_string::parse_string;
+use parse_string::{parse_raw_string, parse_string};
 use parse_template::{parse_global_access, parse_parent_access, parse_template};
 
+/// A problem found while parsing, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+/// The error [`parse_str`] and [`parse_str_spanned`] fail with: every
+/// [`Diagnostic`] found in the source, in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A grapheme iterator over SID source that tracks the line and column of the
+/// next grapheme, so the parser can record a [`Span`] for every value.
+///
+/// It also holds the parser's error recovery state: the diagnostics reported
+/// so far, and the closing delimiters of the templates being parsed.
+pub struct SourceIter<'a> {
+    inner: Peekable<Graphemes<'a>>,
+    line: usize,
+    col: usize,
+    diagnostics: Vec<Diagnostic>,
+    /// The terminator of each template being parsed, innermost last, with the
+    /// span of its opening delimiter.
+    enclosing: Vec<(&'static str, Span)>,
+}
+
+impl<'a> SourceIter<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self::starting_at(source, 1, 1)
+    }
+
+    /// Iterate `source` as if it started at `line`:`col` of a larger text.
+    pub fn starting_at(source: &'a str, line: usize, col: usize) -> Self {
+        Self {
+            inner: source.graphemes(true).peekable(),
+            line,
+            col,
+            diagnostics: Vec::new(),
+            enclosing: Vec::new(),
+        }
+    }
+
+    /// The diagnostics reported so far, leaving none behind.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub fn peek(&mut self) -> Option<&&'a str> {
+        self.inner.peek()
+    }
+
+    /// The grapheme after the next one, without consuming either.
+    pub fn peek_second(&self) -> Option<&'a str> {
+        self.inner.clone().nth(1)
+    }
+
+    /// Line and column (both 1-based) of the next grapheme.
+    pub fn location(&self) -> (usize, usize) {
+        (self.line, self.col)
+    }
+
+    /// The span from `start` up to (not including) the next grapheme.  A value
+    /// that spans several lines is only underlined on its first.
+    fn span_from(&self, start: (usize, usize)) -> Span {
+        let len = if self.line == start.0 {
+            (self.col - start.1).max(1)
+        } else {
+            1
+        };
+        Span::new(start.0, start.1, len)
+    }
+
+    fn report(&mut self, span: Span, message: impl fmt::Display) {
+        self.diagnostics.push(Diagnostic {
+            span,
+            message: message.to_string(),
+        });
+    }
+
+    /// Consume everything up to and including the next `g` if it is on the
+    /// current line; otherwise consume nothing.
+    fn skip_through_on_line(&mut self, g: &str) {
+        let mut ahead = self
+            .inner
+            .clone()
+            .take_while(|&next| next != "\n" && next != "\r\n");
+        if let Some(n) = ahead.position(|next| next == g) {
+            for _ in 0..=n {
+                self.next();
+            }
+        }
+    }
+
+    /// After a value starting at `start` failed to parse, skip the rest of
+    /// it: up to the next separator, delimiter or literal, always moving on
+    /// by at least one grapheme.
+    fn skip_bad_value(&mut self, start: (usize, usize)) {
+        if self.location() == start {
+            self.next();
+        }
+        while let Some(&g) = self.peek() {
+            if is_key_char(g) || matches!(g, "(" | "[" | "{" | "<" | "\"" | "'") {
+                break;
+            }
+            self.next();
+        }
+    }
+}
+
+impl<'a> Iterator for SourceIter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let g = self.inner.next()?;
+        if g == "\n" || g == "\r\n" {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(g)
+    }
+}
+
 /// Parse a complete source string into a flat sequence of [`TemplateValue`]s
 /// plus the number of parent-stack entries the sequence consumes.
 ///
-/// This is the main entry point for the parser.
+/// This is the main entry point for the parser.  It fails with a
+/// [`ParseError`] listing every problem in the source.
 pub fn parse_str(source: &str) -> Result<(Vec<TemplateValue>, usize)> {
-    parse_program_sequence(&mut source.graphemes(true).peekable(), None)
+    parse_str_spanned(source).map(|(out, consumed, _)| (out, consumed))
+}
+
+/// [`parse_str`], also returning the source span of every top-level value.
+/// Nested templates carry the spans of their own bodies.
+pub fn parse_str_spanned(source: &str) -> Result<(Vec<TemplateValue>, usize, SpanTable)> {
+    let (out, consumed, spans, diagnostics) = parse_str_recovering(source);
+    if !diagnostics.is_empty() {
+        return Err(ParseError { diagnostics }.into());
+    }
+    Ok((out, consumed, spans))
+}
+
+/// [`parse_str_spanned`] for tooling: never fails, but returns every problem
+/// found as a [`Diagnostic`] next to a best-effort tree of what did parse.
+///
+/// A value that fails to parse is left out and the parser carries on after
+/// it.  A stray closing delimiter is skipped, one that closes an enclosing
+/// template ends the templates inside it, and a template still open at the
+/// end of input ends there.
+pub fn parse_str_recovering(
+    source: &str,
+) -> (Vec<TemplateValue>, usize, SpanTable, Vec<Diagnostic>) {
+    let mut iter = SourceIter::new(source);
+    let (out, consumed, spans) = parse_program_sequence(&mut iter, None);
+    let mut diagnostics = iter.take_diagnostics();
+    diagnostics.sort_by_key(|d| (d.span.line, d.span.col));
+    (out, consumed, spans, diagnostics)
 }
 
 /// Parse a sequence of [`TemplateValue`]s until `terminator` is consumed or
-/// input runs out (when `terminator` is `None`).
+/// input runs out (when `terminator` is `None`), reporting problems to
+/// `iter` as it recovers from them.
+///
+/// Returns the values, the number of parent-stack entries they consume and
+/// the span of each value.  The span of a `!` or `@!` is widened to cover the
+/// value it invokes when both are on the same line.
 pub fn parse_program_sequence(
-    iter: &mut Peekable<Graphemes>,
+    iter: &mut SourceIter,
     terminator: Option<&str>,
-) -> Result<(Vec<TemplateValue>, usize)> {
+) -> (Vec<TemplateValue>, usize, SpanTable) {
     let mut out: Vec<TemplateValue> = Vec::new();
+    let mut spans: Vec<Option<Span>> = Vec::new();
     let mut max_consumed = 0usize;
     loop {
-        // Skip insignificant whitespace and commas before checking the terminator.
-        // Without this, `( ... ) ` would fail because `parse_template_value` sees
-        // whitespace, skips it, then bails on the `)` before the parent loop can
-        // match it as the terminator.
-        while matches!(
-            iter.peek().map(|x| *x),
-            Some(" ") | Some("\n") | Some("\t") | Some(",")
-        ) {
-            iter.next();
+        // Skip insignificant whitespace, commas and comments before checking
+        // the terminator, which `parse_template_value` would reject.
+        loop {
+            match iter.peek().copied() {
+                Some(" ") | Some("\n") | Some("\t") | Some(",") => {
+                    iter.next();
+                }
+                Some("#") => while iter.next().unwrap_or("\n") != "\n" {},
+                _ => break,
+            }
         }
         // Check for the terminator; consume it and stop.
-        if terminator == iter.peek().map(|x| *x) {
+        if terminator.is_some() && terminator == iter.peek().copied() {
             iter.next();
-            return Ok((out, max_consumed));
+            return (out, max_consumed, SpanTable(spans));
+        }
+        if let Some(&closer @ (")" | "]" | "}" | ">")) = iter.peek() {
+            let start = iter.location();
+            let span = Span::new(start.0, start.1, 1);
+            match terminator {
+                // It closes an enclosing template: end this one here and
+                // leave it for that one.
+                Some(terminator) if iter.enclosing.iter().any(|(t, _)| *t == closer) => {
+                    iter.report(
+                        span,
+                        format!("unexpected '{}' while looking for '{}'", closer, terminator),
+                    );
+                    return (out, max_consumed, SpanTable(spans));
+                }
+                _ => {
+                    iter.next();
+                    iter.report(span, format!("unexpected closing delimiter '{}'", closer));
+                    continue;
+                }
+            }
         }
-        match parse_template_value(iter)? {
+        match parse_template_value(iter) {
             None => {
-                if terminator.is_none() {
-                    return Ok((out, max_consumed));
+                if let Some(terminator) = terminator {
+                    // Point at the opening delimiter; the end of input says
+                    // nothing about where the closing one should have been.
+                    let span = match iter.enclosing.last() {
+                        Some(&(_, opener)) => opener,
+                        None => Span::new(iter.line, iter.col, 1),
+                    };
+                    iter.report(
+                        span,
+                        format!("unexpected end of input while looking for '{}'", terminator),
+                    );
                 }
-                bail!(
-                    "unexpected end of input while looking for '{}'",
-                    terminator.unwrap()
-                );
+                return (out, max_consumed, SpanTable(spans));
             }
-            Some(TemplateValue::ParentStackMove(i)) => {
-                max_consumed = max_consumed.max(i);
-                out.push(TemplateValue::ParentStackMove(i));
+            Some((v, span)) => {
+                let span = match (&v, spans.last()) {
+                    (
+                        TemplateValue::Literal(ProgramValue::Invoke | ProgramValue::ComptimeInvoke),
+                        Some(Some(prev)),
+                    ) => prev.to(span),
+                    _ => span,
+                };
+                if let TemplateValue::ParentStackMove(i) = v {
+                    max_consumed = max_consumed.max(i);
+                }
+                out.push(v);
+                spans.push(Some(span));
             }
-            Some(v) => out.push(v),
         }
     }
 }
 
-/// Parse the next single [`TemplateValue`] from the iterator.
+/// Parse the next single [`TemplateValue`] from the iterator, along with the
+/// span it was parsed from.  A value that fails to parse is reported to
+/// `iter` and skipped.
 ///
 /// Returns `None` when the iterator is exhausted (signals end of input to
 /// the caller rather than an error, since the caller knows whether more input
 /// is required).
-pub(super) fn parse_template_value(
-    iter: &mut Peekable<Graphemes>,
-) -> Result<Option<TemplateValue>> {
+pub(super) fn parse_template_value(iter: &mut SourceIter) -> Option<(TemplateValue, Span)> {
     loop {
-        let ch = match iter.peek() {
-            None => return Ok(None),
-            Some(&c) => c,
-        };
-        match ch {
-            // These are only valid as terminators consumed by the parent call.
-            ")" | "]" | "}" | ">" => {
-                bail!("unexpected closing delimiter '{}'", ch)
-            }
+        match iter.peek().copied() {
+            None => return None,
             // Comment: skip to end of line.
-            "#" => while iter.next().unwrap_or("\n") != "\n" {},
+            Some("#") => while iter.next().unwrap_or("\n") != "\n" {},
             // Insignificant whitespace and comma separators.
-            " " | "\n" | "\t" | "," => {
-                iter.next();
-            }
-            // String literal.
-            "\"" => return Ok(Some(DataValue::Str(parse_string(iter)?).into())),
-            // Char literal.
-            "'" => return Ok(Some(DataValue::Char(parse_char(iter)?).into())),
-            // Template literals: substack, list, set/struct, script.
-            "(" | "[" | "{" | "<" => return Ok(Some(parse_template(iter)?.into())),
-            // Invoke / comptime-invoke.
-            "!" => {
+            Some(" ") | Some("\n") | Some("\t") | Some(",") => {
                 iter.next();
-                return Ok(Some(ProgramValue::Invoke.into()));
             }
-            "@" => {
-                iter.next(); // consume @
-                match iter.peek().map(|x| *x) {
-                    Some("!") => {
-                        iter.next(); // consume !
-                        return Ok(Some(ProgramValue::ComptimeInvoke.into()));
+            Some(_) => {
+                let start = iter.location();
+                match parse_value(iter) {
+                    Ok(value) => return Some((value, iter.span_from(start))),
+                    Err(e) => {
+                        let span = iter.span_from(start);
+                        iter.report(span, format!("{:#}", e));
+                        iter.skip_bad_value(start);
                     }
-                    _ => return Ok(Some(parse_global_access(iter)?)),
                 }
             }
-            // Stack / scope substitution inside a template.
-            "$" => return Ok(Some(parse_parent_access(iter)?)),
-            // Number (digit or leading minus).
-            x if x
-                .chars()
-                .next()
-                .map(|c| c.is_ascii_digit() || c == '-')
-                .unwrap_or(false) =>
-            {
-                return Ok(Some(parse_number(iter)?.into()));
-            }
-            // Anything else is a label or boolean.
-            _ => return Ok(Some(parse_label(iter)?.into())),
         }
     }
 }
 
+/// Parse the single value starting at the next grapheme, which must not be
+/// whitespace, a comment or the end of input.
+fn parse_value(iter: &mut SourceIter) -> Result<TemplateValue> {
+    let ch = *iter.peek().expect("parse_value called at end of input");
+    Ok(match ch {
+        // These are only valid as terminators consumed by the parent call.
+        ")" | "]" | "}" | ">" => {
+            bail!("unexpected closing delimiter '{}'", ch)
+        }
+        // String literal.
+        "\"" => DataValue::Str(parse_string(iter)?).into(),
+        // Raw string literal: `r"…"` or `r#"…"#`.
+        "r" if matches!(iter.peek_second(), Some("\"") | Some("#")) => {
+            DataValue::Str(parse_raw_string(iter)?).into()
+        }
+        // Char literal.
+        "'" => DataValue::Char(parse_char(iter)?).into(),
+        // Template literals: substack, list, set/struct, script.
+        "(" | "[" | "{" | "<" => parse_template(iter)?.into(),
+        // Invoke / comptime-invoke.
+        "!" => {
+            iter.next();
+            ProgramValue::Invoke.into()
+        }
+        "@" => {
+            iter.next(); // consume @
+            match iter.peek().copied() {
+                Some("!") => {
+                    iter.next(); // consume !
+                    ProgramValue::ComptimeInvoke.into()
+                }
+                _ => parse_global_access(iter)?,
+            }
+        }
+        // Stack / scope substitution inside a template.
+        "$" => parse_parent_access(iter)?,
+        // Number (digit, leading minus, or a leading dot before a digit).
+        x if x
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_digit() || c == '-')
+            .unwrap_or(false) =>
+        {
+            parse_number(iter)?.into()
+        }
+        "." if iter
+            .peek_second()
+            .is_some_and(|g| g.chars().all(|c| c.is_ascii_digit())) =>
+        {
+            parse_number(iter)?.into()
+        }
+        // Anything else is a label or boolean.
+        _ => parse_label(iter)?.into(),
+    })
+}
+
 /// Characters that delimit tokens (not valid inside a bare label or number).
 pub(super) fn is_key_char(ch: &str) -> bool {
     matches!(
@@ -1,21 +1,30 @@
-use super::Graphemes;
+use super::parse_string::parse_escape;
+use super::SourceIter;
 use anyhow::{bail, Result};
-use std::iter::Peekable;
 
 /// Parse a `'…'` character literal.  The iterator must be positioned at the
-/// opening `'`.  The contents must be exactly one unicode grapheme cluster.
-pub fn parse_char(input: &mut Peekable<Graphemes>) -> Result<String> {
+/// opening `'`.  The contents must be exactly one unicode grapheme cluster or
+/// one escape sequence (see [`parse_escape`]).
+pub fn parse_char(input: &mut SourceIter) -> Result<String> {
     match input.next() {
         Some("'") => (),
         other => bail!("expected '\\'\\'' to open char literal, got {:?}", other),
     }
     let ch = match input.next() {
+        Some("\\") => parse_escape(input)?.to_string(),
         Some(g) => g.to_owned(),
         None => bail!("unterminated char literal: no grapheme after opening quote"),
     };
     match input.next() {
         Some("'") => (),
-        other => bail!("expected '\\'\\'' to close char literal, got {:?}", other),
+        other => {
+            // Skip to the closing quote, so the parser can carry on after the
+            // literal.
+            if other.is_some_and(|g| g != "\n") {
+                input.skip_through_on_line("'");
+            }
+            bail!("expected '\\'\\'' to close char literal, got {:?}", other)
+        }
     }
     Ok(ch)
 }
@@ -1,13 +1,13 @@
-use super::{is_key_char, Graphemes};
+use super::{is_key_char, SourceIter};
 use crate::DataValue;
 use anyhow::{bail, Result};
-use std::iter::Peekable;
 
 /// Parse a label or boolean literal.
 ///
 /// A label is any sequence of graphemes that are not key characters.
-/// `true` and `false` are recognised as boolean literals.
-pub fn parse_label(input: &mut Peekable<Graphemes>) -> Result<DataValue> {
+/// `true` and `false` are recognised as boolean literals, and `inf` and `nan`
+/// as float literals.
+pub fn parse_label(input: &mut SourceIter) -> Result<DataValue> {
     let mut data = String::new();
     loop {
         match input.peek() {
@@ -23,6 +23,8 @@ pub fn parse_label(input: &mut Peekable<Graphemes>) -> Result<DataValue> {
     Ok(match data.as_str() {
         "true" => DataValue::Bool(true),
         "false" => DataValue::Bool(false),
+        "inf" => DataValue::Float(f64::INFINITY),
+        "nan" => DataValue::Float(f64::NAN),
         _ => DataValue::Label(data),
     })
 }
@@ -1,37 +1,112 @@
-use super::{is_key_char, Graphemes};
+use super::{is_key_char, SourceIter};
 use crate::DataValue;
-use anyhow::{bail, Result};
-use std::iter::Peekable;
+use anyhow::{anyhow, bail, Result};
 
 /// Parse an integer or float literal.
 ///
 /// The iterator must be positioned at the first character of the number
-/// (a digit or a leading `-`).
-pub fn parse_number(input: &mut Peekable<Graphemes>) -> Result<DataValue> {
-    let mut is_float = false;
-    let mut agg = String::new();
-    loop {
-        match input.peek() {
-            None => break,
-            Some(&ch) => match ch {
-                "." if is_float => bail!("two decimal points in float literal"),
-                "." => {
-                    is_float = true;
-                    agg.push('.');
-                }
-                "-" if !agg.is_empty() => {
-                    bail!("minus sign after first character in number literal")
-                }
-                "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "-" => agg.push_str(ch),
-                x if is_key_char(x) => break,
-                x => bail!("unexpected character {:?} in number literal", x),
-            },
+/// (a digit, a leading `-`, or a `.` followed by a digit).  Accepted forms:
+///
+/// - decimal ints: `42`, `-7`, `1_000_000`
+/// - hex, octal and binary ints: `0xFF`, `0o755`, `0b1010`, `-0x10`.  These
+///   may use all 64 bits, so `0xFFFFFFFFFFFFFFFF` is the int `-1`.
+/// - floats, which have a fraction, an exponent or both: `3.14`, `.78`,
+///   `1.`, `6.02e23`, `1e-9`
+/// - `-inf` (`inf` and `nan` are parsed by [`super::parse_label`])
+///
+/// An `_` may separate any two digits.
+pub fn parse_number(input: &mut SourceIter) -> Result<DataValue> {
+    let mut raw = String::new();
+    while let Some(&ch) = input.peek() {
+        if is_key_char(ch) {
+            break;
         }
+        raw.push_str(ch);
         input.next();
     }
-    Ok(if is_float {
-        DataValue::Float(agg.parse()?)
+    number_from_str(&raw)
+}
+
+fn number_from_str(raw: &str) -> Result<DataValue> {
+    let (negative, body) = match raw.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, raw),
+    };
+    if negative && body == "inf" {
+        return Ok(DataValue::Float(f64::NEG_INFINITY));
+    }
+    let radix = match body.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let magnitude = u64::from_str_radix(&digits(&body[2..], radix, raw)?, radix)
+            .map_err(|_| anyhow!("number literal {:?} does not fit in 64 bits", raw))?;
+        return Ok(DataValue::Int(if negative {
+            if magnitude > 1 << 63 {
+                bail!("number literal {:?} is too small for an int", raw);
+            }
+            (magnitude as i64).wrapping_neg()
+        } else {
+            magnitude as i64
+        }));
+    }
+
+    let (mantissa, exponent) = match body.find(['e', 'E']) {
+        Some(i) => (&body[..i], Some(&body[i + 1..])),
+        None => (body, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let mut text = String::from(if negative { "-" } else { "" });
+    // One of the whole and fractional parts may be empty (`.5`, `1.`), but
+    // not both.
+    match (whole, fraction) {
+        ("", Some(fraction)) => text.push_str(&format!("0.{}", digits(fraction, 10, raw)?)),
+        (whole, Some("")) => text.push_str(&digits(whole, 10, raw)?),
+        (whole, Some(fraction)) => text.push_str(&format!(
+            "{}.{}",
+            digits(whole, 10, raw)?,
+            digits(fraction, 10, raw)?
+        )),
+        (whole, None) => text.push_str(&digits(whole, 10, raw)?),
+    }
+    if let Some(exponent) = exponent {
+        let (sign, exponent) = match exponent.strip_prefix(['+', '-']) {
+            Some(rest) => (&exponent[..1], rest),
+            None => ("", exponent),
+        };
+        text.push_str(&format!("e{}{}", sign, digits(exponent, 10, raw)?));
+    }
+    Ok(if fraction.is_some() || exponent.is_some() {
+        DataValue::Float(text.parse()?)
     } else {
-        DataValue::Int(agg.parse()?)
+        DataValue::Int(
+            text.parse()
+                .map_err(|_| anyhow!("number literal {:?} does not fit in an int", raw))?,
+        )
     })
 }
+
+/// One group of digits of a number literal, with its `_` separators removed.
+/// Every `_` must sit between two digits.
+fn digits(group: &str, radix: u32, literal: &str) -> Result<String> {
+    if group.is_empty() {
+        bail!("missing digits in number literal {:?}", literal);
+    }
+    if let Some(c) = group.chars().find(|&c| c != '_' && !c.is_digit(radix)) {
+        bail!(
+            "unexpected character {:?} in number literal {:?}",
+            c,
+            literal
+        );
+    }
+    if group.starts_with('_') || group.ends_with('_') || group.contains("__") {
+        bail!("misplaced '_' in number literal {:?}", literal);
+    }
+    Ok(group.replace('_', ""))
+}
@@ -1,28 +1,147 @@
-use super::Graphemes;
+use super::SourceIter;
 use anyhow::{bail, Result};
 use std::ffi::CString;
-use std::iter::Peekable;
 
 /// Parse a `"…"` string literal.  The iterator must be positioned at the
 /// opening `"`.
 ///
 /// Returns a [`CString`] (null-terminated, no interior NUL bytes) so the value
 /// can be used directly in C FFI calls.  A NUL byte inside the literal is a
-/// parse error.
-pub fn parse_string(input: &mut Peekable<Graphemes>) -> Result<CString> {
+/// parse error.  The literal may span several lines; `\` starts an escape
+/// sequence (see [`parse_escape`]).
+///
+/// A bad escape sequence fails only once the closing `"` is consumed, so the
+/// parser can carry on after the literal.
+pub fn parse_string(input: &mut SourceIter) -> Result<CString> {
     match input.next() {
         Some("\"") => (),
         other => bail!("expected '\"' to open string literal, got {:?}", other),
     }
     let mut data = String::new();
-    for ch in input {
+    let mut bad_escape = None;
+    while let Some(ch) = input.next() {
         match ch {
-            "\"" => {
-                return CString::new(data)
-                    .map_err(|_| anyhow::anyhow!("string literal contains NUL byte"));
-            }
+            "\"" => match bad_escape {
+                Some(e) => return Err(e),
+                None => return to_cstring(data),
+            },
+            "\\" => match parse_escape(input) {
+                Ok(ch) => data.push(ch),
+                Err(e) => {
+                    bad_escape.get_or_insert(e);
+                }
+            },
             x => data.push_str(x),
         }
     }
     bail!("unterminated string literal")
 }
+
+/// Parse a raw string literal, `r"…"`, or `r#"…"#` with any number of `#`s so
+/// that the contents can contain `"`.  The iterator must be positioned at the
+/// `r`.  Nothing inside a raw string is escaped.
+pub fn parse_raw_string(input: &mut SourceIter) -> Result<CString> {
+    match input.next() {
+        Some("r") => (),
+        other => bail!("expected 'r' to open raw string literal, got {:?}", other),
+    }
+    let mut hashes = 0;
+    while input.peek() == Some(&"#") {
+        input.next();
+        hashes += 1;
+    }
+    match input.next() {
+        Some("\"") => (),
+        other => bail!("expected '\"' to open raw string literal, got {:?}", other),
+    }
+    let mut data = String::new();
+    while let Some(ch) = input.next() {
+        if ch != "\"" {
+            data.push_str(ch);
+            continue;
+        }
+        // Only a `"` followed by as many `#`s as opened the literal closes it.
+        let mut seen = 0;
+        while seen < hashes && input.peek() == Some(&"#") {
+            input.next();
+            seen += 1;
+        }
+        if seen == hashes {
+            return to_cstring(data);
+        }
+        data.push('"');
+        data.extend(std::iter::repeat_n('#', seen));
+    }
+    bail!("unterminated raw string literal")
+}
+
+fn to_cstring(data: String) -> Result<CString> {
+    CString::new(data).map_err(|_| anyhow::anyhow!("string literal contains NUL byte"))
+}
+
+/// Parse the escape sequence after a `\` (which must already be consumed) in
+/// a string or char literal:
+///
+/// - `\n` `\t` `\r` `\\` `\"` `\'`
+/// - `\xHH`: an ASCII character by its two-digit hex code
+/// - `\u{H…}`: any unicode scalar value by its hex code (1 to 6 digits)
+///
+/// NUL can't be written, since strings are passed to C as NUL-terminated.
+pub(super) fn parse_escape(input: &mut SourceIter) -> Result<char> {
+    let ch = match input.next() {
+        Some("n") => '\n',
+        Some("t") => '\t',
+        Some("r") => '\r',
+        Some("\\") => '\\',
+        Some("\"") => '"',
+        Some("'") => '\'',
+        Some("x") => {
+            let mut hex = String::new();
+            for _ in 0..2 {
+                match input.next() {
+                    Some(d) if is_hex_digit(d) => hex.push_str(d),
+                    other => bail!("expected two hex digits after '\\x', got {:?}", other),
+                }
+            }
+            let code = u8::from_str_radix(&hex, 16)?;
+            if code > 0x7f {
+                bail!(
+                    "'\\x{}' is not ASCII; use '\\u{{{}}}' for other characters",
+                    hex,
+                    hex
+                );
+            }
+            code as char
+        }
+        Some("u") => {
+            match input.next() {
+                Some("{") => (),
+                other => bail!("expected '{{' after '\\u', got {:?}", other),
+            }
+            let mut hex = String::new();
+            loop {
+                match input.next() {
+                    Some("}") => break,
+                    Some(d) if is_hex_digit(d) && hex.len() < 6 => hex.push_str(d),
+                    other => bail!("expected 1 to 6 hex digits in '\\u{{…}}', got {:?}", other),
+                }
+            }
+            if hex.is_empty() {
+                bail!("empty unicode escape '\\u{{}}'");
+            }
+            let code = u32::from_str_radix(&hex, 16)?;
+            char::from_u32(code)
+                .ok_or_else(|| anyhow::anyhow!("'\\u{{{}}}' is not a unicode scalar value", hex))?
+        }
+        Some(other) => bail!("unknown escape sequence '\\{}'", other),
+        None => bail!("unterminated escape sequence"),
+    };
+    if ch == '\0' {
+        bail!("escape sequence produces a NUL character");
+    }
+    Ok(ch)
+}
+
+fn is_hex_digit(g: &str) -> bool {
+    g.len() == 1 && g.as_bytes()[0].is_ascii_hexdigit()
+}
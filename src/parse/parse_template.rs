@@ -1,12 +1,11 @@
-use super::{parse_program_sequence, parse_template_value, Graphemes};
-use crate::{Template, TemplateValue};
+use super::{parse_program_sequence, parse_template_value, SourceIter};
+use crate::{Span, Template, TemplateValue};
 use anyhow::{bail, Result};
-use std::iter::Peekable;
 
 /// Parse a `$n` (parent stack move) or `$name` (parent label) access.
 ///
 /// The iterator must be positioned at the `$`.
-pub fn parse_parent_access(input: &mut Peekable<Graphemes>) -> Result<TemplateValue> {
+pub fn parse_parent_access(input: &mut SourceIter) -> Result<TemplateValue> {
     assert_eq!(
         input.next(),
         Some("$"),
@@ -38,7 +37,7 @@ pub fn parse_parent_access(input: &mut Peekable<Graphemes>) -> Result<TemplateVa
 ///
 /// The iterator must be positioned **after** the `@` (already consumed by the caller).
 /// `@<integer>` is rejected — comptime has no data stack.
-pub fn parse_global_access(input: &mut Peekable<Graphemes>) -> Result<TemplateValue> {
+pub fn parse_global_access(input: &mut SourceIter) -> Result<TemplateValue> {
     let mut agg = String::new();
     while let Some(&ch) = input.peek() {
         if super::is_key_char(ch) {
@@ -61,36 +60,67 @@ pub fn parse_global_access(input: &mut Peekable<Graphemes>) -> Result<TemplateVa
 /// whether it is a set or a struct (struct has at least one `:` at depth 0).
 ///
 /// The iterator must be positioned at the opening delimiter.
-pub fn parse_template(input: &mut Peekable<Graphemes>) -> Result<Template> {
-    match input.next().as_deref() {
-        Some("(") => Ok(Template::substack(parse_program_sequence(
+pub fn parse_template(input: &mut SourceIter) -> Result<Template> {
+    let (line, col) = input.location();
+    let opener = Span::new(line, col, 1);
+    match input.next() {
+        Some("(") => Ok(parse_sequence_template(
             input,
-            Some(")"),
-        )?)),
-        Some("[") => Ok(Template::list(parse_program_sequence(input, Some("]"))?)),
-        Some("<") => Ok(Template::script(parse_program_sequence(input, Some(">"))?)),
-        Some("{") => parse_brace_template(input),
+            opener,
+            ")",
+            Template::substack,
+        )),
+        Some("[") => Ok(parse_sequence_template(input, opener, "]", Template::list)),
+        Some("<") => Ok(parse_sequence_template(
+            input,
+            opener,
+            ">",
+            Template::script,
+        )),
+        Some("{") => Ok(parse_brace_template(input, opener)),
         other => bail!("expected template opening delimiter, got {:?}", other),
     }
 }
 
+/// Parse a template body with `parse_program_sequence`, keeping its spans.
+/// `opener` is the span of the opening delimiter, already consumed.
+fn parse_sequence_template(
+    input: &mut SourceIter,
+    opener: Span,
+    terminator: &'static str,
+    make: fn((Vec<TemplateValue>, usize)) -> Template,
+) -> Template {
+    input.enclosing.push((terminator, opener));
+    let (body, consumed, spans) = parse_program_sequence(input, Some(terminator));
+    input.enclosing.pop();
+    make((body, consumed)).with_spans(spans)
+}
+
 /// Disambiguate `{…}` as set vs struct by peeking for a top-level `:`.
-fn parse_brace_template(input: &mut Peekable<Graphemes>) -> Result<Template> {
+///
+/// A `{` left open is reported and the rest of the input parsed as its body.
+fn parse_brace_template(input: &mut SourceIter, opener: Span) -> Template {
     // Collect all raw graphemes up to the matching `}`, tracking depth so we
     // only look at the first parsing level for `:`.
+    let (line, col) = input.location();
     let mut buf: Vec<String> = Vec::new();
     let mut depth = 0usize;
     let mut has_colon = false;
     loop {
-        match input.next().as_deref() {
-            None => bail!("unterminated '{{' — reached end of input"),
+        match input.next() {
+            None => {
+                input.report(opener, "unterminated '{' — reached end of input");
+                break;
+            }
             Some("}") if depth == 0 => break,
             Some(ch @ ("(" | "[" | "{" | "<")) => {
                 depth += 1;
                 buf.push(ch.to_owned());
             }
+            // A closer with nothing open is left for the body's parser to
+            // report.
             Some(ch @ (")" | "]" | "}" | ">")) => {
-                depth -= 1;
+                depth = depth.saturating_sub(1);
                 buf.push(ch.to_owned());
             }
             Some(":") if depth == 0 => {
@@ -101,27 +131,29 @@ fn parse_brace_template(input: &mut Peekable<Graphemes>) -> Result<Template> {
         }
     }
     let raw: String = buf.join("");
-    if has_colon {
-        // {:} is the empty-map literal — a lone `:` with no entries.
-        if raw.trim() == ":" {
-            return Ok(Template::map(vec![], 0));
-        }
-        parse_map(&raw)
-    } else {
-        Ok(Template::set(parse_program_sequence(
-            &mut unicode_segmentation::UnicodeSegmentation::graphemes(raw.as_str(), true)
-                .peekable(),
-            None,
-        )?))
+    // {:} is the empty-map literal — a lone `:` with no entries.
+    if has_colon && raw.trim() == ":" {
+        return Template::map(vec![], 0);
     }
+    let mut body = SourceIter::starting_at(&raw, line, col);
+    let template = if has_colon {
+        parse_map(&mut body).unwrap_or_else(|e| {
+            // Keep what the entries reported; the map as a whole is lost.
+            body.report(opener, format!("{:#}", e));
+            Template::map(vec![], 0)
+        })
+    } else {
+        let (values, consumed, spans) = parse_program_sequence(&mut body, None);
+        Template::set((values, consumed)).with_spans(spans)
+    };
+    input.diagnostics.append(&mut body.diagnostics);
+    template
 }
 
 /// Parse `key: value, …` pairs from the collected brace body.
 /// Keys are multi-token sequences up to `:` at depth 0.
 /// Values are multi-token sequences up to `,` or end at depth 0.
-fn parse_map(raw: &str) -> Result<Template> {
-    use unicode_segmentation::UnicodeSegmentation;
-    let mut iter = raw.graphemes(true).peekable();
+fn parse_map(iter: &mut SourceIter) -> Result<Template> {
     let mut pairs: Vec<(Vec<TemplateValue>, Vec<TemplateValue>)> = Vec::new();
     let mut max_consumed = 0usize;
 
@@ -134,17 +166,14 @@ fn parse_map(raw: &str) -> Result<Template> {
     };
 
     // Skip leading whitespace
-    let skip_ws = |iter: &mut std::iter::Peekable<_>| loop {
-        match iter.peek() {
-            Some(&" ") | Some(&"\n") | Some(&"\t") => {
-                iter.next();
-            }
-            _ => break,
+    let skip_ws = |iter: &mut SourceIter| {
+        while let Some(&" ") | Some(&"\n") | Some(&"\t") = iter.peek() {
+            iter.next();
         }
     };
 
     loop {
-        skip_ws(&mut iter);
+        skip_ws(iter);
 
         // Check for end of input
         if iter.peek().is_none() {
@@ -154,15 +183,15 @@ fn parse_map(raw: &str) -> Result<Template> {
         // Parse key tokens until `:` at depth 0
         let mut key_tvs: Vec<TemplateValue> = Vec::new();
         loop {
-            skip_ws(&mut iter);
-            match iter.peek().map(|s| *s) {
+            skip_ws(iter);
+            match iter.peek().copied() {
                 None => bail!("unexpected end of input while parsing map key"),
                 Some(":") => {
                     iter.next();
                     break;
                 }
-                _ => match parse_template_value(&mut iter)? {
-                    Some(v) => key_tvs.push(v),
+                _ => match parse_template_value(iter) {
+                    Some((v, _)) => key_tvs.push(v),
                     None => bail!("unexpected end of input while parsing map key"),
                 },
             }
@@ -175,11 +204,11 @@ fn parse_map(raw: &str) -> Result<Template> {
         // Parse value tokens until `,` or end at depth 0
         let mut val_tvs: Vec<TemplateValue> = Vec::new();
         loop {
-            skip_ws(&mut iter);
-            match iter.peek().map(|s| *s) {
+            skip_ws(iter);
+            match iter.peek().copied() {
                 None | Some(",") => break,
-                _ => match parse_template_value(&mut iter)? {
-                    Some(v) => val_tvs.push(v),
+                _ => match parse_template_value(iter) {
+                    Some((v, _)) => val_tvs.push(v),
                     None => break,
                 },
             }
@@ -192,8 +221,8 @@ fn parse_map(raw: &str) -> Result<Template> {
         pairs.push((key_tvs, val_tvs));
 
         // Consume trailing `,` if present
-        skip_ws(&mut iter);
-        match iter.peek().map(|s| *s) {
+        skip_ws(iter);
+        match iter.peek().copied() {
             None => break,
             Some(",") => {
                 iter.next();
@@ -12,3 +12,19 @@ fn parse_char() {
     }
     .test();
 }
+
+#[test]
+fn parse_char_escapes() {
+    ParseTestFixture {
+        input: r"'\n' '\'' '\\' '\u{1F46E}' '\x7e'",
+        expected_output: vec![
+            DataValue::Char("\n".to_owned()).into(),
+            DataValue::Char("'".to_owned()).into(),
+            DataValue::Char("\\".to_owned()).into(),
+            DataValue::Char("👮".to_owned()).into(),
+            DataValue::Char("~".to_owned()).into(),
+        ],
+        expected_consumed: 0,
+    }
+    .test();
+}
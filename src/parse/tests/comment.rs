@@ -12,3 +12,13 @@ fn parse_comments() {
     }
     .test();
 }
+
+#[test]
+fn parse_shebang_line() {
+    ParseTestFixture {
+        input: "#!/usr/bin/env sid\n1",
+        expected_output: vec![DataValue::Int(1).into()],
+        expected_consumed: 0,
+    }
+    .test();
+}
@@ -5,6 +5,8 @@ mod comment;
 mod invoke;
 mod label;
 mod number;
+mod recovery;
+mod span;
 mod string;
 mod template;
 
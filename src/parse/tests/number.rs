@@ -22,3 +22,96 @@ fn parse_float() {
     }
     .test();
 }
+
+#[test]
+fn parse_radix_integers() {
+    ParseTestFixture {
+        input: "0xFF 0x7f 0o755 0b1010 -0x10 0xFFFF_FFFF_FFFF_FFFF -0x8000000000000000",
+        expected_output: vec![
+            DataValue::Int(255).into(),
+            DataValue::Int(127).into(),
+            DataValue::Int(0o755).into(),
+            DataValue::Int(10).into(),
+            DataValue::Int(-16).into(),
+            DataValue::Int(-1).into(),
+            DataValue::Int(i64::MIN).into(),
+        ],
+        expected_consumed: 0,
+    }
+    .test();
+}
+
+#[test]
+fn parse_underscores() {
+    ParseTestFixture {
+        input: "1_000_000 0b1111_0000 1_000.000_1",
+        expected_output: vec![
+            DataValue::Int(1_000_000).into(),
+            DataValue::Int(0xF0).into(),
+            DataValue::Float(1_000.000_1).into(),
+        ],
+        expected_consumed: 0,
+    }
+    .test();
+}
+
+#[test]
+fn parse_float_forms() {
+    ParseTestFixture {
+        input: ".78 -.5 1. 6.02e23 1e9 1E-9 2.5e+3 -1e0",
+        expected_output: vec![
+            DataValue::Float(0.78).into(),
+            DataValue::Float(-0.5).into(),
+            DataValue::Float(1.0).into(),
+            DataValue::Float(6.02e23).into(),
+            DataValue::Float(1e9).into(),
+            DataValue::Float(1e-9).into(),
+            DataValue::Float(2500.0).into(),
+            DataValue::Float(-1.0).into(),
+        ],
+        expected_consumed: 0,
+    }
+    .test();
+}
+
+#[test]
+fn parse_infinities() {
+    ParseTestFixture {
+        input: "inf -inf",
+        expected_output: vec![
+            DataValue::Float(f64::INFINITY).into(),
+            DataValue::Float(f64::NEG_INFINITY).into(),
+        ],
+        expected_consumed: 0,
+    }
+    .test();
+    // NaN never compares equal, so check it by hand.
+    let (out, _) = parse_str("nan").unwrap();
+    assert!(matches!(
+        out[..],
+        [TemplateValue::Literal(ProgramValue::Data(DataValue::Float(f)))] if f.is_nan()
+    ));
+}
+
+#[test]
+fn reject_malformed_numbers() {
+    for input in [
+        "1.2.3",
+        "1-2",
+        "-",
+        "1__0",
+        "1_",
+        "1_.5",
+        "0x",
+        "0xG",
+        "0b102",
+        "0x1_0000_0000_0000_0000",
+        "-0x8000000000000001",
+        "9223372036854775808",
+        "1e",
+        "1e+",
+        "12abc",
+    ] {
+        assert!(parse_str(input).is_err(), "{} should not parse", input);
+    }
+}
@@ -0,0 +1,96 @@
+use super::*;
+
+/// Where and what each diagnostic of `input` is.
+fn diagnostics_of(input: &str) -> Vec<(usize, usize, String)> {
+    parse_str_recovering(input)
+        .3
+        .into_iter()
+        .map(|d| (d.span.line, d.span.col, d.message))
+        .collect()
+}
+
+fn label(name: &str) -> TemplateValue {
+    DataValue::Label(name.to_owned()).into()
+}
+
+#[test]
+fn every_stray_closer_is_reported() {
+    assert_eq!(
+        diagnostics_of(") 1 ]\n}"),
+        vec![
+            (1, 1, "unexpected closing delimiter ')'".to_owned()),
+            (1, 5, "unexpected closing delimiter ']'".to_owned()),
+            (2, 1, "unexpected closing delimiter '}'".to_owned()),
+        ]
+    );
+    let (out, _, _, _) = parse_str_recovering(") 1 ]\n}");
+    assert_eq!(out, vec![DataValue::Int(1).into()]);
+}
+
+#[test]
+fn unclosed_template_is_reported_at_its_opener() {
+    assert_eq!(
+        diagnostics_of("a\n  (1 [2"),
+        vec![
+            (
+                2,
+                3,
+                "unexpected end of input while looking for ')'".to_owned()
+            ),
+            (
+                2,
+                6,
+                "unexpected end of input while looking for ']'".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn closer_of_an_enclosing_template_ends_the_inner_one() {
+    let (out, _, _, diagnostics) = parse_str_recovering("[1 (2 3] x");
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic {
+            span: Span::new(1, 8, 1),
+            message: "unexpected ']' while looking for ')'".to_owned(),
+        }]
+    );
+    let inner: TemplateValue =
+        Template::substack((vec![DataValue::Int(2).into(), DataValue::Int(3).into()], 0)).into();
+    let list: TemplateValue = Template::list((vec![DataValue::Int(1).into(), inner], 0)).into();
+    assert_eq!(out, vec![list, label("x")]);
+}
+
+#[test]
+fn bad_values_are_skipped() {
+    let (out, _, _, diagnostics) = parse_str_recovering("a 0x1g b \"\\q\" c 'xy' d");
+    assert_eq!(
+        diagnostics.iter().map(|d| d.span).collect::<Vec<_>>(),
+        vec![Span::new(1, 3, 4), Span::new(1, 10, 4), Span::new(1, 17, 4)]
+    );
+    assert_eq!(out, vec![label("a"), label("b"), label("c"), label("d")]);
+}
+
+#[test]
+fn comment_before_closer() {
+    let (out, _, _, diagnostics) = parse_str_recovering("(1 # one\n)");
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    assert_eq!(
+        out,
+        vec![Template::substack((vec![DataValue::Int(1).into()], 0)).into()]
+    );
+}
+
+#[test]
+fn parse_error_lists_every_diagnostic() {
+    let error = parse_str(") (")
+        .unwrap_err()
+        .downcast::<ParseError>()
+        .expect("not a ParseError");
+    assert_eq!(
+        error.to_string(),
+        "1:1: unexpected closing delimiter ')'\n\
+         1:3: unexpected end of input while looking for ')'"
+    );
+}
@@ -0,0 +1,75 @@
+use super::*;
+
+fn spans_of(input: &str) -> Vec<Option<Span>> {
+    parse_str_spanned(input).expect("parse failed").2 .0
+}
+
+fn template_spans(tv: &TemplateValue) -> Vec<Option<Span>> {
+    match tv {
+        TemplateValue::Literal(ProgramValue::Template(t)) => t.spans.0.clone(),
+        other => panic!("expected a template, got {:?}", other),
+    }
+}
+
+#[test]
+fn span_per_top_level_value() {
+    assert_eq!(
+        spans_of("1 \"two\"\n  three"),
+        vec![
+            Some(Span::new(1, 1, 1)),
+            Some(Span::new(1, 3, 5)),
+            Some(Span::new(2, 3, 5)),
+        ]
+    );
+}
+
+#[test]
+fn comments_are_not_part_of_a_span() {
+    assert_eq!(
+        spans_of("# leading comment\n x # trailing"),
+        vec![Some(Span::new(2, 2, 1))]
+    );
+}
+
+#[test]
+fn invoke_span_covers_invoked_value() {
+    assert_eq!(
+        spans_of("1 2 add !\nadd\n!"),
+        vec![
+            Some(Span::new(1, 1, 1)),
+            Some(Span::new(1, 3, 1)),
+            Some(Span::new(1, 5, 3)),
+            Some(Span::new(1, 5, 5)),
+            Some(Span::new(2, 1, 3)),
+            // On a different line than `add`, so only the `!` itself.
+            Some(Span::new(3, 1, 1)),
+        ]
+    );
+}
+
+#[test]
+fn comptime_invoke_span_covers_invoked_value() {
+    assert_eq!(
+        spans_of("x @!"),
+        vec![Some(Span::new(1, 1, 1)), Some(Span::new(1, 1, 4))]
+    );
+}
+
+#[test]
+fn template_body_has_its_own_spans() {
+    let (out, _, spans) = parse_str_spanned("(1\n  two)").expect("parse failed");
+    assert_eq!(spans.0, vec![Some(Span::new(1, 1, 1))]);
+    assert_eq!(
+        template_spans(&out[0]),
+        vec![Some(Span::new(1, 2, 1)), Some(Span::new(2, 3, 3))]
+    );
+}
+
+#[test]
+fn set_body_spans_are_relative_to_source() {
+    let (out, _, _) = parse_str_spanned("  {a b}").expect("parse failed");
+    assert_eq!(
+        template_spans(&out[0]),
+        vec![Some(Span::new(1, 4, 1)), Some(Span::new(1, 6, 1))]
+    );
+}
@@ -12,3 +12,68 @@ fn parse_string() {
     }
     .test();
 }
+
+fn s(v: &str) -> TemplateValue {
+    DataValue::Str(std::ffi::CString::new(v).unwrap()).into()
+}
+
+#[test]
+fn parse_string_escapes() {
+    ParseTestFixture {
+        input: r#""a\nb\tc\r\\ \"q\" \'" "\x41\u{1F46E}\u{e9}""#,
+        expected_output: vec![s("a\nb\tc\r\\ \"q\" '"), s("A👮é")],
+        expected_consumed: 0,
+    }
+    .test();
+}
+
+#[test]
+fn parse_multiline_string() {
+    ParseTestFixture {
+        input: "\"one\ntwo\"",
+        expected_output: vec![s("one\ntwo")],
+        expected_consumed: 0,
+    }
+    .test();
+}
+
+#[test]
+fn parse_raw_strings() {
+    ParseTestFixture {
+        input: r###"r"C:\dir\n" r#"say "hi""# r##"a"#b"## r"""###,
+        expected_output: vec![s(r"C:\dir\n"), s(r#"say "hi""#), s(r##"a"#b"##), s("")],
+        expected_consumed: 0,
+    }
+    .test();
+}
+
+#[test]
+fn label_starting_with_r_is_not_a_raw_string() {
+    ParseTestFixture {
+        input: "r rest",
+        expected_output: vec![
+            DataValue::Label("r".to_owned()).into(),
+            DataValue::Label("rest".to_owned()).into(),
+        ],
+        expected_consumed: 0,
+    }
+    .test();
+}
+
+#[test]
+fn bad_escapes_are_errors() {
+    for input in [
+        r#""\q""#,
+        r#""\x0""#,
+        r#""\x80""#,
+        r#""\x00""#,
+        r#""\u{0}""#,
+        r#""\u{}""#,
+        r#""\u{110000}""#,
+        r#""\u{d800}""#,
+        r#""\"#,
+        r##"r#"open"##,
+    ] {
+        assert!(parse_str(input).is_err(), "{} should not parse", input);
+    }
+}
@@ -2,17 +2,19 @@ use std::collections::HashMap;
 
 use crate::built_in::BuiltinEntry;
 use crate::{
-    get_from_scope, DataValue, GlobalState, ProgramValue, Template, TemplateData, TemplateValue,
+    get_from_scope, DataValue, Frame, GlobalState, ProgramValue, SidError, Template, TemplateData,
+    TemplateValue,
 };
 
 fn resolve_to_program_values(
     source: Vec<TemplateValue>,
-    consumed_stack: &mut Vec<Option<DataValue>>,
+    consumed_stack: &mut [Option<DataValue>],
     parent_scope: &HashMap<String, DataValue>,
     global_scope: &HashMap<String, DataValue>,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
-) -> Vec<ProgramValue> {
-    let builtin_names: std::collections::HashSet<&'static str> = builtins.keys().copied().collect();
+    builtins: &HashMap<String, BuiltinEntry>,
+) -> Result<Vec<ProgramValue>, SidError> {
+    let builtin_names: std::collections::HashSet<&str> =
+        builtins.keys().map(String::as_str).collect();
     let mut rendered: Vec<ProgramValue> = Vec::new();
     use TemplateValue as TV;
     for entry in source {
@@ -26,42 +28,45 @@ fn resolve_to_program_values(
                     Some(parent_scope),
                     Some(global_scope),
                     Some(&builtin_names),
-                )
-                .expect("label resolution failed");
+                )?;
                 rendered.push(v.into())
             }
             TV::ParentStackMove(i) => {
                 if i == 0 {
-                    panic!("Parent stack index 0 is the template!");
+                    return Err(SidError::Internal(
+                        "Parent stack index 0 is the template!".to_owned(),
+                    ));
                 }
                 let value = consumed_stack[i - 1]
                     .as_ref()
-                    .expect("Stack value missing in template")
+                    .ok_or_else(|| {
+                        SidError::Internal("Stack value missing in template".to_owned())
+                    })?
                     .clone();
                 rendered.push(value.into());
             }
             TV::ComptimeLabel(l) => {
-                panic!(
+                return Err(SidError::Internal(format!(
                     "@{} should have been resolved at comptime; render pass should never see it",
                     l
-                );
+                )));
             }
         }
     }
-    rendered
+    Ok(rendered)
 }
 
 fn eval_data_seq(
     pvs: Vec<ProgramValue>,
     parent_scope: &HashMap<String, DataValue>,
     global_state: &mut GlobalState,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
-) -> Vec<DataValue> {
+    builtins: &HashMap<String, BuiltinEntry>,
+) -> Result<Vec<DataValue>, SidError> {
     use crate::invoke::interpret_one;
     let mut data_stack: Vec<TemplateValue> = Vec::new();
     let mut program_stack: Vec<ProgramValue> = pvs.into_iter().rev().collect();
     let mut local_scope = parent_scope.clone();
-    let mut scope_stack: Vec<HashMap<String, DataValue>> = Vec::new();
+    let mut scope_stack: Vec<Frame> = Vec::new();
     while !program_stack.is_empty() {
         interpret_one(
             &mut data_stack,
@@ -70,16 +75,16 @@ fn eval_data_seq(
             &mut scope_stack,
             global_state,
             builtins,
-        );
+        )?;
     }
     data_stack
         .into_iter()
         .map(|tv| match tv {
-            TemplateValue::Literal(ProgramValue::Data(v)) => v,
-            other => panic!(
+            TemplateValue::Literal(ProgramValue::Data(v)) => Ok(v),
+            other => Err(SidError::Internal(format!(
                 "eval_data_seq: non-data value left on mini-stack: {:?}",
                 other
-            ),
+            ))),
         })
         .collect()
 }
@@ -89,23 +94,27 @@ pub fn render_template(
     parent_stack: &mut Vec<TemplateValue>,
     parent_scope: &HashMap<String, DataValue>,
     global_state: &mut GlobalState,
-    builtins: &HashMap<&'static str, BuiltinEntry>,
-) -> DataValue {
+    builtins: &HashMap<String, BuiltinEntry>,
+) -> Result<DataValue, SidError> {
     if template.consumes_stack_entries > parent_stack.len() {
-        panic!("Template consumes more stack entries than there are.");
+        return Err(SidError::StackUnderflow(
+            "Template consumes more stack entries than there are.".to_owned(),
+        ));
     }
     let mut consumed_stack: Vec<Option<DataValue>> = parent_stack
         .drain(parent_stack.len() - template.consumes_stack_entries..)
         .map(|tv| match tv {
-            TemplateValue::Literal(ProgramValue::Data(v)) => v,
-            other => panic!(
+            TemplateValue::Literal(ProgramValue::Data(v)) => Ok(Some(v)),
+            other => Err(SidError::TypeMismatch(format!(
                 "render_template: parent stack entry is not a concrete DataValue: {:?}",
                 other
-            ),
+            ))),
         })
-        .map(|v| Some(v))
-        .collect();
+        .collect::<Result<_, _>>()?;
 
+    // Rendering maps template entries 1:1 onto program values, so body spans
+    // carry over unchanged.
+    let spans = template.spans;
     use TemplateData as TD;
     let rendered_template: DataValue = match template.data {
         TD::Substack(source) => DataValue::Substack {
@@ -115,9 +124,10 @@ pub fn render_template(
                 parent_scope,
                 global_state.scope,
                 builtins,
-            ),
+            )?,
             args: None,
             ret: None,
+            spans: spans.clone(),
         },
         TD::Script(source) => DataValue::Script {
             body: resolve_to_program_values(
@@ -126,9 +136,10 @@ pub fn render_template(
                 parent_scope,
                 global_state.scope,
                 builtins,
-            ),
+            )?,
             args: None,
             ret: None,
+            spans: spans.clone(),
         },
         TD::List(source) => {
             let pvs = resolve_to_program_values(
@@ -137,8 +148,8 @@ pub fn render_template(
                 parent_scope,
                 global_state.scope,
                 builtins,
-            );
-            DataValue::List(eval_data_seq(pvs, parent_scope, global_state, builtins))
+            )?;
+            DataValue::List(eval_data_seq(pvs, parent_scope, global_state, builtins)?)
         }
         TD::Set(source) => {
             let pvs = resolve_to_program_values(
@@ -147,8 +158,8 @@ pub fn render_template(
                 parent_scope,
                 global_state.scope,
                 builtins,
-            );
-            DataValue::Set(eval_data_seq(pvs, parent_scope, global_state, builtins))
+            )?;
+            DataValue::set(eval_data_seq(pvs, parent_scope, global_state, builtins)?)
         }
         TD::Map(pairs) => {
             let mut entries: Vec<(DataValue, DataValue)> = Vec::new();
@@ -159,27 +170,27 @@ pub fn render_template(
                     parent_scope,
                     global_state.scope,
                     builtins,
-                );
+                )?;
                 let val_pvs = resolve_to_program_values(
                     val_tvs,
                     &mut consumed_stack,
                     parent_scope,
                     global_state.scope,
                     builtins,
-                );
-                let mut key_vals = eval_data_seq(key_pvs, parent_scope, global_state, builtins);
-                let mut val_vals = eval_data_seq(val_pvs, parent_scope, global_state, builtins);
+                )?;
+                let mut key_vals = eval_data_seq(key_pvs, parent_scope, global_state, builtins)?;
+                let mut val_vals = eval_data_seq(val_pvs, parent_scope, global_state, builtins)?;
                 if key_vals.len() != 1 {
-                    panic!(
+                    return Err(SidError::InvalidArgument(format!(
                         "map key expression must produce exactly 1 value, got {}",
                         key_vals.len()
-                    );
+                    )));
                 }
                 if val_vals.len() != 1 {
-                    panic!(
+                    return Err(SidError::InvalidArgument(format!(
                         "map value expression must produce exactly 1 value, got {}",
                         val_vals.len()
-                    );
+                    )));
                 }
                 entries.push((key_vals.remove(0), val_vals.remove(0)));
             }
@@ -187,5 +198,5 @@ pub fn render_template(
         }
     };
 
-    rendered_template
+    Ok(rendered_template)
 }
@@ -0,0 +1,92 @@
+use crate::SidError;
+
+/// A kind of access to the world outside the interpreter that a
+/// [`SandboxPolicy`] can deny.
+///
+/// Console I/O, the clock and randomness are always allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// `c_load_header`, `c_link_lib`, and invoking a `CFunction` or
+    /// `CFuncSig`.
+    Ffi,
+    /// `ptr_cast` and `ptr_read_cstr`, which reinterpret or read raw memory.
+    Pointers,
+    /// `read_file`, `write_file`, `append_file`, `exists` and `list_dir`.
+    Filesystem,
+    /// `env_var`.
+    Env,
+}
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Ffi => "FFI",
+            Capability::Pointers => "raw pointer access",
+            Capability::Filesystem => "filesystem access",
+            Capability::Env => "environment access",
+        }
+    }
+}
+
+/// Which [`Capability`]s a program may use.
+///
+/// The default allows everything; [`SandboxPolicy::deny_all`] is the starting
+/// point for running untrusted code.  Held in `GlobalState::sandbox` and
+/// checked by each built-in before it acts, so a denied call fails with
+/// [`SidError::PermissionDenied`] and has no effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    pub ffi: bool,
+    pub pointers: bool,
+    pub filesystem: bool,
+    pub env: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self::allow_all()
+    }
+}
+
+impl SandboxPolicy {
+    pub fn allow_all() -> Self {
+        Self {
+            ffi: true,
+            pointers: true,
+            filesystem: true,
+            env: true,
+        }
+    }
+
+    pub fn deny_all() -> Self {
+        Self {
+            ffi: false,
+            pointers: false,
+            filesystem: false,
+            env: false,
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Ffi => self.ffi,
+            Capability::Pointers => self.pointers,
+            Capability::Filesystem => self.filesystem,
+            Capability::Env => self.env,
+        }
+    }
+
+    /// Fail with a permission error naming `operation` unless `capability`
+    /// is allowed.
+    pub fn require(&self, capability: Capability, operation: &str) -> Result<(), SidError> {
+        if self.allows(capability) {
+            Ok(())
+        } else {
+            Err(SidError::PermissionDenied(format!(
+                "{}: {} is not allowed by the sandbox policy",
+                operation,
+                capability.name()
+            )))
+        }
+    }
+}
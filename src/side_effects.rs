@@ -0,0 +1,320 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use libloading::Library;
+
+/// Everything a SID program can do to the world outside the interpreter.
+///
+/// Built-ins reach the console, the filesystem, the clock, randomness, the
+/// environment and shared libraries only through the
+/// `GlobalState::side_effects` trait object, so an embedder can swap in a
+/// different implementation.  [`RealSideEffects`] is the normal one and
+/// [`MockSideEffects`] keeps everything in memory for tests.
+pub trait SideEffects {
+    /// Write `bytes` to standard output.
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()>;
+    /// Write `bytes` to standard error.
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()>;
+    /// Read one line from standard input, including its `\n` if any.
+    /// Returns an empty string at end of input.
+    fn read_line(&mut self) -> io::Result<String>;
+    /// Read standard input to its end.
+    fn read_all_stdin(&mut self) -> io::Result<Vec<u8>>;
+
+    /// Read the whole file at `path`.
+    fn read_file(&mut self, path: &Path) -> io::Result<Vec<u8>>;
+    /// Create or truncate the file at `path` and write `contents` to it.
+    fn write_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    /// Append `contents` to the file at `path`, creating it if needed.
+    fn append_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    /// Whether anything exists at `path`.
+    fn exists(&mut self, path: &Path) -> bool;
+    /// The names of the entries in the directory at `path`, sorted.
+    fn list_dir(&mut self, path: &Path) -> io::Result<Vec<String>>;
+
+    /// The current wall-clock time.
+    fn now(&mut self) -> SystemTime;
+    /// A uniformly distributed random number.
+    fn random_u64(&mut self) -> u64;
+    /// The value of the environment variable `name`, if it is set and valid
+    /// UTF-8.
+    fn env_var(&mut self, name: &str) -> Option<String>;
+    /// Load the shared library at `path` (a path or soname), as `c_link_lib`
+    /// does.
+    fn open_library(&mut self, path: &str) -> anyhow::Result<Arc<Library>>;
+}
+
+/// The side effects of a normal run: the process's own stdio and filesystem.
+#[derive(Debug, Default)]
+pub struct RealSideEffects;
+
+impl SideEffects for RealSideEffects {
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        out.write_all(bytes)?;
+        out.flush()
+    }
+
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()> {
+        io::stderr().lock().write_all(bytes)
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        Ok(line)
+    }
+
+    fn read_all_stdin(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        io::stdin().lock().read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_file(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn append_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?
+            .write_all(contents)
+    }
+
+    fn exists(&mut self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn list_dir(&mut self, path: &Path) -> io::Result<Vec<String>> {
+        let mut names = fs::read_dir(path)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<io::Result<Vec<String>>>()?;
+        names.sort();
+        Ok(names)
+    }
+
+    fn now(&mut self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn random_u64(&mut self) -> u64 {
+        // Every `RandomState` is seeded differently, which is all the
+        // randomness std offers without another dependency.
+        RandomState::new().build_hasher().finish()
+    }
+
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn open_library(&mut self, path: &str) -> anyhow::Result<Arc<Library>> {
+        crate::c_ffi::open_library(path)
+    }
+}
+
+/// In-memory side effects for tests.
+///
+/// Clones share the same state, so a test keeps one clone to read output and
+/// files back after handing another to
+/// [`GlobalState::with_side_effects`](crate::GlobalState::with_side_effects).
+/// Standard input, files, environment variables, the time and the random
+/// seed are all set up front; libraries can't be loaded.
+///
+/// Directories are implied by the files in them: `/a` exists and lists `b`
+/// as soon as `/a/b/c` is written.
+#[derive(Clone, Debug)]
+pub struct MockSideEffects {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    stdin: Vec<u8>,
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    env: HashMap<String, String>,
+    now: SystemTime,
+    random: u64,
+}
+
+impl Default for MockSideEffects {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MockState {
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+                stdin: Vec::new(),
+                files: BTreeMap::new(),
+                env: HashMap::new(),
+                now: SystemTime::UNIX_EPOCH,
+                random: 0x2545_f491_4f6c_dd1d,
+            })),
+        }
+    }
+}
+
+impl MockSideEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        // A poisoned lock only means another test thread panicked; the state
+        // itself is still consistent.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Everything written to standard output so far.
+    pub fn stdout(&self) -> Vec<u8> {
+        self.state().stdout.clone()
+    }
+
+    /// [`MockSideEffects::stdout`] as text, replacing invalid UTF-8.
+    pub fn stdout_string(&self) -> String {
+        String::from_utf8_lossy(&self.state().stdout).into_owned()
+    }
+
+    /// Everything written to standard error so far.
+    pub fn stderr(&self) -> Vec<u8> {
+        self.state().stderr.clone()
+    }
+
+    /// [`MockSideEffects::stderr`] as text, replacing invalid UTF-8.
+    pub fn stderr_string(&self) -> String {
+        String::from_utf8_lossy(&self.state().stderr).into_owned()
+    }
+
+    /// Replace what is left of standard input.
+    pub fn set_stdin(&self, input: impl Into<Vec<u8>>) {
+        self.state().stdin = input.into();
+    }
+
+    /// The contents of the file at `path`, if there is one.
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.state().files.get(path.as_ref()).cloned()
+    }
+
+    /// Create or replace the file at `path`.
+    pub fn set_file(&self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        self.state().files.insert(path.into(), contents.into());
+    }
+
+    pub fn set_env(&self, name: impl Into<String>, value: impl Into<String>) {
+        self.state().env.insert(name.into(), value.into());
+    }
+
+    /// Fix the time `now` reports; the clock never moves on its own.
+    pub fn set_now(&self, now: SystemTime) {
+        self.state().now = now;
+    }
+
+    /// Restart the random sequence from `seed`.
+    pub fn set_seed(&self, seed: u64) {
+        // xorshift never leaves the all-zero state.
+        self.state().random = seed.max(1);
+    }
+}
+
+impl SideEffects for MockSideEffects {
+    fn write_stdout(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.state().stdout.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_stderr(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.state().stderr.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut state = self.state();
+        let end = state
+            .stdin
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(state.stdin.len(), |i| i + 1);
+        let line: Vec<u8> = state.stdin.drain(..end).collect();
+        String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn read_all_stdin(&mut self) -> io::Result<Vec<u8>> {
+        Ok(std::mem::take(&mut self.state().stdin))
+    }
+
+    fn read_file(&mut self, path: &Path) -> io::Result<Vec<u8>> {
+        self.file(path)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.set_file(path, contents);
+        Ok(())
+    }
+
+    fn append_file(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.state()
+            .files
+            .entry(path.to_owned())
+            .or_default()
+            .extend_from_slice(contents);
+        Ok(())
+    }
+
+    fn exists(&mut self, path: &Path) -> bool {
+        self.state().files.keys().any(|file| file.starts_with(path))
+    }
+
+    fn list_dir(&mut self, path: &Path) -> io::Result<Vec<String>> {
+        let state = self.state();
+        let mut names: Vec<String> = state
+            .files
+            .keys()
+            .filter(|file| *file != path)
+            .filter_map(|file| file.strip_prefix(path).ok()?.iter().next())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        if names.is_empty() {
+            return Err(io::Error::from(io::ErrorKind::NotFound));
+        }
+        names.dedup();
+        Ok(names)
+    }
+
+    fn now(&mut self) -> SystemTime {
+        self.state().now
+    }
+
+    fn random_u64(&mut self) -> u64 {
+        let mut state = self.state();
+        let mut x = state.random;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.random = x;
+        x
+    }
+
+    fn env_var(&mut self, name: &str) -> Option<String> {
+        self.state().env.get(name).cloned()
+    }
+
+    fn open_library(&mut self, path: &str) -> anyhow::Result<Arc<Library>> {
+        anyhow::bail!(
+            "failed to load '{}': MockSideEffects can't load libraries",
+            path
+        )
+    }
+}
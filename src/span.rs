@@ -0,0 +1,104 @@
+use std::fmt;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A location in SID source text.
+///
+/// `line` and `col` are 1-based and count grapheme clusters, matching how the
+/// parser walks the source.  `len` is the number of graphemes underlined in a
+/// diagnostic; a span never extends past the end of its first line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, col: usize, len: usize) -> Self {
+        Self { line, col, len }
+    }
+
+    /// The span from the start of `self` to the end of `other`, if both are on
+    /// the same line.  Otherwise `other` on its own.
+    pub fn to(self, other: Span) -> Span {
+        if self.line == other.line && self.col <= other.col {
+            Span::new(self.line, self.col, other.col + other.len - self.col)
+        } else {
+            other
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Source spans for a sequence of instructions, index-aligned with it.
+///
+/// Entry `i` is the span of instruction `i`, or `None` where no location is
+/// known (e.g. values produced by a comptime invocation without one).  An
+/// empty table means the sequence carries no location information at all,
+/// which is what every constructor that doesn't come from the parser yields.
+///
+/// Spans are debug information: two tables always compare equal, so spans
+/// never change whether two templates or substacks are considered the same.
+#[derive(Clone, Default)]
+pub struct SpanTable(pub Vec<Option<Span>>);
+
+impl SpanTable {
+    pub fn get(&self, index: usize) -> Option<Span> {
+        self.0.get(index).copied().flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(Option::is_none)
+    }
+}
+
+impl PartialEq for SpanTable {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for SpanTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SpanTable(..)")
+    }
+}
+
+/// Render the location of `span` in `source` as a `file:line:col` header
+/// followed by the offending line with the span underlined:
+///
+/// ```text
+///   --> example.sid:1:8
+///   |
+/// 1 | 1 true add !
+///   |        ^^^^^
+/// ```
+pub fn render_snippet(file: &str, source: &str, span: Span) -> String {
+    let gutter = span.line.to_string().len();
+    let pad = " ".repeat(gutter);
+    let mut out = format!("{} --> {}:{}:{}\n", pad, file, span.line, span.col);
+    let Some(line) = source.lines().nth(span.line.saturating_sub(1)) else {
+        return out;
+    };
+    let graphemes: Vec<&str> = line.graphemes(true).collect();
+    // Keep tabs in the caret line so the carets stay aligned with the text.
+    let indent: String = graphemes
+        .iter()
+        .take(span.col.saturating_sub(1))
+        .map(|g| if *g == "\t" { "\t" } else { " " })
+        .collect();
+    let len = span
+        .len
+        .min(graphemes.len().saturating_sub(span.col.saturating_sub(1)))
+        .max(1);
+    out.push_str(&format!("{} |\n", pad));
+    out.push_str(&format!("{} | {}\n", span.line, line));
+    out.push_str(&format!("{} | {}{}\n", pad, indent, "^".repeat(len)));
+    out
+}
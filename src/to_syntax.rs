@@ -16,14 +16,41 @@ fn list_to_syntax<T: ToSyntax>(list: &[T], left_bracket: &str, right_bracket: &s
     format!("{}\n{} ", s, right_bracket)
 }
 
+/// Escape `text` for use between `quote`s in a string or char literal, so that
+/// the parser reads back exactly `text`.
+fn escape_literal(text: &str, quote: char) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
 impl ToSyntax for DataValue {
     fn to_syntax(&self) -> String {
         match self {
             DataValue::Bool(v) => v.to_string(),
-            DataValue::Str(v) => format!("\"{}\"", v.to_string_lossy()),
-            DataValue::Char(v) => format!("\'{}\'", v),
+            DataValue::Str(v) => format!("\"{}\"", escape_literal(&v.to_string_lossy(), '"')),
+            DataValue::Char(v) => format!("'{}'", escape_literal(v, '\'')),
             DataValue::Int(v) => v.to_string(),
-            DataValue::Float(v) => v.to_string(),
+            DataValue::Float(v) if v.is_nan() => "nan".to_owned(),
+            DataValue::Float(v) if v.is_infinite() => {
+                if *v > 0.0 { "inf" } else { "-inf" }.to_owned()
+            }
+            // Debug formatting is the shortest text that parses back to the
+            // same f64, and always includes a `.` or an exponent.
+            DataValue::Float(v) => format!("{:?}", v),
             DataValue::Substack { body: v, .. } => list_to_syntax(v, "(", ")"),
             DataValue::Script { body: v, .. } => list_to_syntax(v, "<", ">"),
             DataValue::List(v) => list_to_syntax(v, "[", "]"),
@@ -48,6 +75,7 @@ impl ToSyntax for DataValue {
                 format!("<Pointer 0x{:x} : {}>", addr, pointee_ty.to_syntax())
             }
             DataValue::CFuncSig(sig) => format!("<CFuncSig {}>", sig.name),
+            DataValue::Opaque(o) => format!("<Opaque {}>", o.type_name()),
             DataValue::StackBlock => "# <StackBlock>\n".to_owned(),
         }
     }
@@ -93,14 +121,21 @@ impl ToSyntax for ProgramValue {
                     context, types_syntax, block_placed
                 )
             }
-            ProgramValue::PushScope { names } => {
+            ProgramValue::PushScope { names, call } => {
                 if names.is_empty() {
-                    "# push scope\n".to_owned()
+                    format!("# push scope ({})\n", call)
                 } else {
-                    format!("# push scope (bind: {})\n", names.join(", "))
+                    format!("# push scope ({}; bind: {})\n", call, names.join(", "))
                 }
             }
             ProgramValue::PopScope => "# pop scope\n".to_owned(),
+            ProgramValue::SourceSpan(Some(span)) => format!("# at {}\n", span),
+            ProgramValue::SourceSpan(None) => "# at <unknown>\n".to_owned(),
+            ProgramValue::ListIter(iter) => format!(
+                "# {} ({} item(s) left)\n",
+                iter.op.name(),
+                iter.pending.len()
+            ),
         }
     }
 }
@@ -115,7 +150,7 @@ impl ToSyntax for TemplateData {
             TemplateData::Map(pairs) => {
                 let mut s = "{#Template".to_owned();
                 let n = pairs.len();
-                for (i, (k_tvs, v_tvs)) in pairs.into_iter().enumerate() {
+                for (i, (k_tvs, v_tvs)) in pairs.iter().enumerate() {
                     let k_str = k_tvs
                         .iter()
                         .map(|tv| tv.to_syntax())
@@ -156,6 +191,7 @@ impl ToSyntax for SidType {
             SidType::Str => "types.str".to_owned(),
             SidType::Label => "types.label".to_owned(),
             SidType::List(elem) => format!("{} list !", elem.to_syntax()),
+            SidType::Set(elem) => format!("{} set !", elem.to_syntax()),
             SidType::Map { key, value } => {
                 format!("{} {} map @!", key.to_syntax(), value.to_syntax())
             }
@@ -180,6 +216,7 @@ impl ToSyntax for SidType {
                 s
             }
             SidType::Pointer(pointee) => format!("{} ptr !", pointee.to_syntax()),
+            SidType::Opaque(name) => format!("types.{}", name),
             SidType::Require { base, constraint } => {
                 format!("{} {} require @!", base.to_syntax(), constraint.to_syntax())
             }
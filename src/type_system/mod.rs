@@ -31,9 +31,11 @@ use crate::DataValue;
 ///
 /// `Struct` and `Union` are not separate variants — they are expressed via
 /// `Literal`:
+///
 /// - A union `{int, str}` is `Literal(Set([Type(Int), Type(Str)]))`.
 /// - A struct type `{x: float, y: float}` is
 ///   `Literal(Struct([("x", Type(Float)), ("y", Type(Float))]))`.
+///
 /// This works because `Literal` dispatches on the inner `DataValue` kind, and
 /// a `DataValue::Type(t)` element delegates matching to `t`.
 #[derive(Debug, Clone, PartialEq)]
@@ -49,6 +51,8 @@ pub enum SidType {
     // Parametric container types (RPN: push type args then call constructor)
     /// `T list` — a homogeneous list whose elements are of type `T`
     List(Box<Self>),
+    /// `T set` — a set whose elements are of type `T`
+    Set(Box<Self>),
     /// `K V map` — a homogeneous map with key type `K` and value type `V`
     Map {
         key: Box<Self>,
@@ -67,6 +71,11 @@ pub enum SidType {
     /// `T ptr` — a pointer to a value of type `T`; `SidType::Any` for `void*`
     Pointer(Box<Self>),
 
+    // Host interop
+    /// A host object created with this type name; see `Opaque`.  Hosts make
+    /// the type available as `types.<name>`.
+    Opaque(String),
+
     // Combinators (RPN: push args then call constructor with @!)
     /// `base constraint require @!` — value must match both `base` AND `constraint`.
     Require {
@@ -98,9 +107,26 @@ pub enum SidType {
 }
 
 impl SidType {
+    /// Whether a built-in argument of this type takes a label as the label
+    /// itself rather than the value it is bound to: `label`, or a union
+    /// naming `label` such as `{label, any}`.
+    pub fn keeps_labels(&self) -> bool {
+        match self {
+            SidType::Label => true,
+            SidType::Literal(lit) => match lit.as_ref() {
+                DataValue::Set(members) => members
+                    .iter()
+                    .any(|m| matches!(m, DataValue::Type(SidType::Label))),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     /// Returns `true` if `value` is an instance of this type.
     ///
     /// - `List(T)` — every element must match `T`.
+    /// - `Set(T)` — every element must match `T`.
     /// - `Map { key: K, value: V }` — every (k, v) entry must match `K` and `V`.
     /// - `Fn { .. }` — value is a callable with matching type annotations.
     ///   `None` on a dimension is unconstrained; `Some` requires a matching
@@ -180,7 +206,7 @@ impl SidType {
                                 val_entries
                                     .iter()
                                     .find(|(vk, _)| vk == pk)
-                                    .map_or(false, |(_, vv)| {
+                                    .is_some_and(|(_, vv)| {
                                         SidType::Literal(Box::new(pv.clone())).matches(vv)
                                     })
                             }),
@@ -213,6 +239,11 @@ impl SidType {
                 _ => false,
             },
 
+            SidType::Set(elem_ty) => match value {
+                DataValue::Set(items) => items.iter().all(|v| elem_ty.matches(v)),
+                _ => false,
+            },
+
             SidType::Map {
                 key: key_ty,
                 value: val_ty,
@@ -262,6 +293,10 @@ impl SidType {
                 _ => false,
             },
 
+            SidType::Opaque(name) => {
+                matches!(value, DataValue::Opaque(o) if o.type_name() == name)
+            }
+
             SidType::Require { base, constraint } => {
                 base.matches(value) && constraint.matches(value)
             }
@@ -294,6 +329,7 @@ mod tests {
             body: vec![],
             args,
             ret,
+            spans: crate::SpanTable::default(),
         }
     }
 
@@ -422,6 +458,22 @@ mod tests {
         assert!(ty.matches(&DataValue::List(vec![]))); // empty list always matches
     }
 
+    #[test]
+    fn opaque_matches_by_type_name() {
+        let ty = SidType::Opaque("File".to_owned());
+        assert!(ty.matches(&DataValue::Opaque(crate::Opaque::new("File", 0u8))));
+        assert!(!ty.matches(&DataValue::Opaque(crate::Opaque::new("Socket", 0u8))));
+        assert!(!ty.matches(&DataValue::Int(0)));
+    }
+
+    #[test]
+    fn set_matches_homogeneous_elements() {
+        let ty = SidType::Set(Box::new(SidType::Int));
+        assert!(ty.matches(&DataValue::Set(vec![DataValue::Int(1), DataValue::Int(2)])));
+        assert!(!ty.matches(&DataValue::Set(vec![DataValue::Bool(true)])));
+        assert!(!ty.matches(&DataValue::List(vec![DataValue::Int(1)])));
+    }
+
     // ── struct (label-keyed Map with Type field values) ───────────────────────
 
     fn struct_type(fields: &[(&str, SidType)]) -> SidType {
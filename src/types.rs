@@ -1,5 +1,8 @@
 use crate::c_ffi::{CFunc, CFuncSig};
 use crate::type_system::SidType;
+use crate::{
+    Call, Limits, Opaque, RealSideEffects, SandboxPolicy, SidError, SideEffects, Span, SpanTable,
+};
 use libloading::Library;
 /// Defines the possible types at each stage of the execution process.
 ///
@@ -41,15 +44,43 @@ pub struct GlobalState<'a> {
     /// Shared libraries loaded by `c_link_lib`, keyed by the path/soname used
     /// to load them.  Libraries are added on first use and reused thereafter.
     pub libraries: HashMap<String, Arc<Library>>,
+    /// Source location of the instruction being executed, as recorded by the
+    /// most recent `SourceSpan` sentinel.  `None` while running code that
+    /// carries no spans.
+    pub span: Option<Span>,
+    /// Console and filesystem access for the I/O built-ins.
+    pub side_effects: Box<dyn SideEffects>,
+    /// What the program may do beyond pure computation; allows everything
+    /// unless set.
+    pub sandbox: SandboxPolicy,
+    /// Fuel, stack depth and deadline bounds; unlimited unless set.
+    pub limits: Limits,
 }
 
 impl<'a> GlobalState<'a> {
     pub fn new(scope: &'a mut HashMap<String, DataValue>) -> Self {
+        Self::with_side_effects(scope, Box::new(RealSideEffects))
+    }
+
+    /// Like [`GlobalState::new`], but performing I/O through `side_effects`.
+    pub fn with_side_effects(
+        scope: &'a mut HashMap<String, DataValue>,
+        side_effects: Box<dyn SideEffects>,
+    ) -> Self {
         Self {
             scope,
             libraries: HashMap::new(),
+            span: None,
+            side_effects,
+            sandbox: SandboxPolicy::default(),
+            limits: Limits::default(),
         }
     }
+
+    /// Set `limits.deadline` to `timeout` from now, by the side effects' clock.
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.limits.deadline = Some(self.side_effects.now() + timeout);
+    }
 }
 
 /// Look up a label in scope, with support for dot-separated field access on structs.
@@ -69,8 +100,8 @@ pub fn get_from_scope(
     label: &str,
     local: Option<&HashMap<String, DataValue>>,
     global: Option<&HashMap<String, DataValue>>,
-    builtin_names: Option<&std::collections::HashSet<&'static str>>,
-) -> anyhow::Result<DataValue> {
+    builtin_names: Option<&std::collections::HashSet<&str>>,
+) -> Result<DataValue, SidError> {
     let mut segments = label.split('.');
     let root = segments.next().unwrap();
 
@@ -84,7 +115,7 @@ pub fn get_from_scope(
                     .then(|| DataValue::BuiltIn(root.to_owned()))
             })
         })
-        .ok_or_else(|| anyhow::anyhow!("undefined label '{}'", label))?;
+        .ok_or_else(|| SidError::UndefinedLabel(format!("undefined label '{}'", label)))?;
 
     for segment in segments {
         current = match current {
@@ -93,14 +124,17 @@ pub fn get_from_scope(
                 .find(|(k, _)| matches!(k, DataValue::Label(n) if n == segment))
                 .map(|(_, v)| v)
                 .ok_or_else(|| {
-                    anyhow::anyhow!("label '{}': field '{}' not found", label, segment)
+                    SidError::UndefinedLabel(format!(
+                        "label '{}': field '{}' not found",
+                        label, segment
+                    ))
                 })?,
-            other => anyhow::bail!(
-                "label '{}': expected a label-keyed Map for field '{}', got {:?}",
-                label,
-                segment,
-                other
-            ),
+            other => {
+                return Err(SidError::TypeMismatch(format!(
+                    "label '{}': expected a label-keyed Map for field '{}', got {:?}",
+                    label, segment, other
+                )))
+            }
         };
     }
     Ok(current)
@@ -114,7 +148,7 @@ pub fn resolve_if_label(
     v: DataValue,
     local_scope: Option<&HashMap<String, DataValue>>,
     global_scope: Option<&HashMap<String, DataValue>>,
-    builtin_names: Option<&std::collections::HashSet<&'static str>>,
+    builtin_names: Option<&std::collections::HashSet<&str>>,
 ) -> DataValue {
     match v {
         DataValue::Label(ref l) => {
@@ -150,10 +184,13 @@ pub enum DataValue {
     ///
     /// `args` uses named fields: each entry is `(name, type)`.  At call time the
     /// types are checked and the names are bound into the callee's local scope.
+    ///
+    /// `spans` holds the source location of each `body` entry, if known.
     Substack {
         body: Vec<ProgramValue>,
         args: Option<Vec<(String, SidType)>>,
         ret: Option<Vec<SidType>>,
+        spans: SpanTable,
     },
     /// Like `Substack` but sequential execution is guaranteed (no concurrency).
     ///
@@ -165,6 +202,7 @@ pub enum DataValue {
         body: Vec<ProgramValue>,
         args: Option<Vec<(String, SidType)>>,
         ret: Option<Vec<SidType>>,
+        spans: SpanTable,
     },
     List(Vec<DataValue>),
     Set(Vec<DataValue>),
@@ -189,6 +227,8 @@ pub enum DataValue {
     /// Stored in scope under the function's name by `c_load_header`.
     /// Replaced with `CFunction` when `c_link_lib` resolves it against a library.
     CFuncSig(CFuncSig),
+    /// An object owned by the host program; see [`Opaque`].
+    Opaque(Opaque),
     /// Interpreter-internal sentinel pushed onto the **data** stack to mark the
     /// boundary below a typed substack's arguments.  Acts as a hard floor: if any
     /// operation tries to use `StackBlock` as a real value the program panics.
@@ -198,6 +238,19 @@ pub enum DataValue {
 }
 
 impl DataValue {
+    /// Build a `Set`, dropping every item equal to an earlier one.
+    ///
+    /// Order of first occurrence is kept, so the result is deterministic.
+    pub fn set(items: Vec<DataValue>) -> DataValue {
+        let mut unique: Vec<DataValue> = Vec::with_capacity(items.len());
+        for item in items {
+            if !unique.contains(&item) {
+                unique.push(item);
+            }
+        }
+        DataValue::Set(unique)
+    }
+
     /// Test whether `self`, used as a **pattern**, matches `value`.
     ///
     /// - If `self` is `DataValue::Type(t)`, delegates to `t.matches(value)`.
@@ -273,9 +326,10 @@ pub enum ProgramValue {
         context: String,
         block_placed: bool,
     },
-    /// Saves the current local scope onto the scope stack and installs a fresh
-    /// empty scope.  Paired with `PopScope`.  Every substack body is wrapped in
-    /// `PushScope` / `PopScope` to isolate its local bindings.
+    /// Saves the current local scope onto the scope stack, in a `Frame` that
+    /// also records `call`, and installs a fresh empty scope.  Paired with
+    /// `PopScope`.  Every substack body is wrapped in `PushScope` / `PopScope`
+    /// to isolate its local bindings.
     ///
     /// `names` holds the field names for the callee's declared `args` (top-first).
     /// When non-empty, `PushScope` pops that many items from the data stack and
@@ -283,9 +337,61 @@ pub enum ProgramValue {
     /// the body begins.  Empty when the callee has no named args.
     PushScope {
         names: Vec<String>,
+        call: Call,
     },
     /// Restores the local scope saved by the matching `PushScope`.
     PopScope,
+    /// Records the source location of the instruction below it.  When popped
+    /// it becomes `GlobalState::span`, which is attached to any error raised
+    /// before the next `SourceSpan`.  Scheduled by `invoke` for substacks that
+    /// carry spans, with a trailing one restoring the caller's location (which
+    /// may be `None`) once the body is done; never produced by the parser.
+    SourceSpan(Option<Span>),
+    /// Sentinel driving a higher-order list built-in (`map`, `filter`, `fold`,
+    /// `each`, `any`, `all`, `sort_by`).  It is placed below each call of the
+    /// function being applied; when popped it takes the function's result
+    /// off the data stack and schedules the next call, or pushes the final
+    /// result once the list is exhausted.
+    ListIter(ListIter),
+}
+
+/// State of a higher-order list built-in between two calls of its function.
+/// See `ProgramValue::ListIter`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ListIter {
+    pub op: ListOp,
+    /// The function applied to each element.
+    pub f: DataValue,
+    /// Elements not yet visited, last element first.
+    pub pending: Vec<DataValue>,
+    /// The element the function was last applied to; `None` before the first
+    /// call.
+    pub current: Option<DataValue>,
+    /// Results collected so far (`map`, `filter`) or the sorted prefix
+    /// (`sort_by`).
+    pub done: Vec<DataValue>,
+    /// Data stack size before the function's arguments were pushed.  The
+    /// function must leave exactly one value above it (none for `each`).
+    pub expected_len: usize,
+}
+
+/// Which higher-order list built-in a `ListIter` is running.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ListOp {
+    Map,
+    Filter,
+    /// The accumulator lives on the data stack rather than in `done`, and
+    /// `expected_len` does not count it.
+    Fold,
+    Each,
+    Any,
+    All,
+    /// Binary insertion sort: `current` belongs somewhere in `done[lo..hi]`,
+    /// and the comparator was called with `current` and `done[(lo + hi) / 2]`.
+    SortBy {
+        lo: usize,
+        hi: usize,
+    },
 }
 
 impl From<DataValue> for ProgramValue {
@@ -303,38 +409,52 @@ impl From<Template> for ProgramValue {
 pub struct Template {
     pub data: TemplateData,
     pub consumes_stack_entries: usize,
+    /// Source location of each entry of a `Substack`, `Script`, `List` or `Set`
+    /// body, index-aligned with it.  Empty for `Map` templates and for
+    /// templates not built by the parser.
+    pub spans: SpanTable,
 }
 impl Template {
     pub fn substack(parsed: (Vec<TemplateValue>, usize)) -> Self {
         Self {
             data: TemplateData::Substack(parsed.0),
             consumes_stack_entries: parsed.1,
+            spans: SpanTable::default(),
         }
     }
     pub fn list(parsed: (Vec<TemplateValue>, usize)) -> Self {
         Self {
             data: TemplateData::List(parsed.0),
             consumes_stack_entries: parsed.1,
+            spans: SpanTable::default(),
         }
     }
     pub fn set(parsed: (Vec<TemplateValue>, usize)) -> Self {
         Self {
             data: TemplateData::Set(parsed.0),
             consumes_stack_entries: parsed.1,
+            spans: SpanTable::default(),
         }
     }
     pub fn map(pairs: Vec<(Vec<TemplateValue>, Vec<TemplateValue>)>, consumes: usize) -> Self {
         Self {
             data: TemplateData::Map(pairs),
             consumes_stack_entries: consumes,
+            spans: SpanTable::default(),
         }
     }
     pub fn script(parsed: (Vec<TemplateValue>, usize)) -> Self {
         Self {
             data: TemplateData::Script(parsed.0),
             consumes_stack_entries: parsed.1,
+            spans: SpanTable::default(),
         }
     }
+    /// Attach the source spans of the template body.
+    pub fn with_spans(mut self, spans: SpanTable) -> Self {
+        self.spans = spans;
+        self
+    }
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -350,6 +470,7 @@ pub enum TemplateData {
 }
 
 #[derive(PartialEq, Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum TemplateValue {
     ParentLabel(String),
     ParentStackMove(usize),
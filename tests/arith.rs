@@ -0,0 +1,161 @@
+use sid::*;
+/// Tests for the arithmetic built-ins: `add`, `sub`, `mul`, `div`, `mod`, `neg`.
+///
+/// Mixed `Int`/`Float` operands are promoted to `Float`; overflow and division
+/// by zero are errors rather than wrapping.
+use std::collections::HashMap;
+
+fn run_snippet(source: &str) -> Vec<DataValue> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered: DataValue = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
+    let builtins = get_interpret_builtins();
+    let mut global_scope_for_run = global_scope;
+    let global_state = GlobalState::new(&mut global_scope_for_run);
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: instructions,
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// ── Integer arithmetic ────────────────────────────────────────────────────────
+
+#[test]
+fn int_operations() {
+    assert_eq!(run_snippet("7 3 add!"), vec![DataValue::Int(10)]);
+    assert_eq!(run_snippet("7 3 sub!"), vec![DataValue::Int(4)]);
+    assert_eq!(run_snippet("7 3 mul!"), vec![DataValue::Int(21)]);
+    assert_eq!(run_snippet("7 3 div!"), vec![DataValue::Int(2)]);
+    assert_eq!(run_snippet("7 3 mod!"), vec![DataValue::Int(1)]);
+    assert_eq!(run_snippet("7 neg!"), vec![DataValue::Int(-7)]);
+}
+
+/// Integer division truncates toward zero and `mod` takes the dividend's sign.
+#[test]
+fn int_division_truncates() {
+    assert_eq!(run_snippet("-7 2 div!"), vec![DataValue::Int(-3)]);
+    assert_eq!(run_snippet("-7 2 mod!"), vec![DataValue::Int(-1)]);
+}
+
+// ── Float arithmetic and promotion ────────────────────────────────────────────
+
+#[test]
+fn float_operations() {
+    assert_eq!(run_snippet("1.5 2.0 add!"), vec![DataValue::Float(3.5)]);
+    assert_eq!(run_snippet("1.5 2.0 mul!"), vec![DataValue::Float(3.0)]);
+    assert_eq!(run_snippet("1.0 4.0 div!"), vec![DataValue::Float(0.25)]);
+    assert_eq!(run_snippet("1.5 neg!"), vec![DataValue::Float(-1.5)]);
+}
+
+#[test]
+fn mixed_operands_promote_to_float() {
+    assert_eq!(run_snippet("1 0.5 add!"), vec![DataValue::Float(1.5)]);
+    assert_eq!(run_snippet("0.5 1 sub!"), vec![DataValue::Float(-0.5)]);
+    assert_eq!(run_snippet("1 4.0 div!"), vec![DataValue::Float(0.25)]);
+}
+
+/// Operands stored under labels are resolved before the operation.
+#[test]
+fn label_operands_are_resolved() {
+    assert_eq!(
+        run_snippet("x 40 local! x 2 add!"),
+        vec![DataValue::Int(42)]
+    );
+}
+
+/// Arithmetic is available at comptime.
+#[test]
+fn comptime_arithmetic() {
+    assert_eq!(run_snippet("6 7 mul @!"), vec![DataValue::Int(42)]);
+}
+
+// ── Error cases ───────────────────────────────────────────────────────────────
+
+#[test]
+#[should_panic(expected = "add: integer overflow")]
+fn add_overflow_errors() {
+    run_snippet("9223372036854775807 1 add!");
+}
+
+#[test]
+#[should_panic(expected = "neg: integer overflow")]
+fn neg_overflow_errors() {
+    run_snippet("-9223372036854775807 1 sub! neg!");
+}
+
+#[test]
+#[should_panic(expected = "div: integer overflow")]
+fn div_overflow_errors() {
+    run_snippet("-9223372036854775807 1 sub! -1 div!");
+}
+
+#[test]
+#[should_panic(expected = "div: division by zero")]
+fn int_division_by_zero_errors() {
+    run_snippet("1 0 div!");
+}
+
+#[test]
+#[should_panic(expected = "mod: division by zero")]
+fn mod_by_zero_errors() {
+    run_snippet("1 0 mod!");
+}
+
+#[test]
+#[should_panic(expected = "div: division by zero")]
+fn float_division_by_zero_errors() {
+    run_snippet("1.0 0 div!");
+}
+
+#[test]
+#[should_panic(expected = "mul: float overflow")]
+fn float_overflow_errors() {
+    let big = format!("1{}.0", "0".repeat(300));
+    run_snippet(&format!("{} {} mul!", big, big));
+}
+
+#[test]
+#[should_panic(expected = "expected")]
+fn non_numeric_operand_errors() {
+    run_snippet("1 true add!");
+}
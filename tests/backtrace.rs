@@ -0,0 +1,77 @@
+use sid::*;
+/// Tests for call backtraces: errors raised inside substack calls record the
+/// active calls, innermost first, with the label used and the call site.
+///
+/// `eval_err` runs `source` in a fresh engine and returns the error it raised.
+fn eval_err(source: &str) -> SidError {
+    let mut engine = Engine::new();
+    let err = engine.eval_str(source).unwrap_err();
+    err.downcast_ref::<SidError>()
+        .unwrap_or_else(|| panic!("not a SidError: {}", err))
+        .clone()
+}
+
+#[test]
+fn nested_calls_are_listed_innermost_first() {
+    let mut engine = Engine::new();
+    engine.eval_str("(1 0 div!)").unwrap();
+    let inner = engine.pop().unwrap().unwrap();
+    engine.set_global("inner", inner);
+    let err = engine
+        .eval_str("outer (\n  inner!\n) local!\nouter!")
+        .unwrap_err()
+        .downcast::<SidError>()
+        .unwrap();
+    let backtrace = err.backtrace().expect("no backtrace");
+    assert_eq!(
+        backtrace.calls,
+        vec![
+            Call {
+                callee: Some("inner".to_owned()),
+                call_site: Some(Span::new(2, 3, 6)),
+            },
+            Call {
+                callee: Some("outer".to_owned()),
+                call_site: Some(Span::new(4, 1, 6)),
+            },
+        ]
+    );
+    assert_eq!(
+        err.unlocated(),
+        &SidError::Arithmetic("div: division by zero".to_owned())
+    );
+    assert_eq!(err.span(), Some(Span::new(1, 6, 4)));
+}
+
+#[test]
+fn anonymous_substacks_have_no_callee() {
+    let err = eval_err("(1 0 div!)!");
+    let backtrace = err.backtrace().expect("no backtrace");
+    assert_eq!(backtrace.calls.len(), 1);
+    assert_eq!(backtrace.calls[0].callee, None);
+    assert_eq!(backtrace.to_string(), "  0: <substack>, called at 1:1\n");
+}
+
+#[test]
+fn argument_check_failures_name_the_callee() {
+    let err = eval_err("f {x: $types.int} (x) typed_args! local!\ntrue f!");
+    let backtrace = err.backtrace().expect("no backtrace");
+    assert_eq!(backtrace.calls[0].callee.as_deref(), Some("f"));
+    assert!(matches!(err.unlocated(), SidError::TypeMismatch(_)));
+}
+
+#[test]
+fn top_level_errors_have_no_backtrace() {
+    let err = eval_err("1 0 div!");
+    assert_eq!(err.backtrace(), None);
+    assert_eq!(err.span(), Some(Span::new(1, 5, 4)));
+}
+
+#[test]
+fn engine_state_is_usable_after_a_traced_error() {
+    let mut engine = Engine::new();
+    engine.eval_str("f (1 0 div!) local!").unwrap();
+    assert!(engine.eval_str("f!").is_err());
+    engine.eval_str("(1 2 add!)!").unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(3)));
+}
@@ -25,6 +25,7 @@ fn run_snippet(source: &str) -> Vec<DataValue> {
             &mut gs,
             &comptime_builtins,
         )
+        .unwrap_or_else(|e| panic!("{}", e))
     };
     let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
     let builtins = get_interpret_builtins();
@@ -46,7 +47,8 @@ fn run_snippet(source: &str) -> Vec<DataValue> {
             &mut exe_state.scope_stack,
             &mut exe_state.global_state,
             &builtins,
-        );
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
     }
     exe_state
         .data_stack
@@ -13,7 +13,7 @@ const TEST_LIB: &str = "libm.so.6";
 
 fn make_exe_state<'a>(
     scope: &'a mut HashMap<String, DataValue>,
-    builtins: &'a HashMap<&'static str, BuiltinEntry>,
+    builtins: &'a HashMap<String, BuiltinEntry>,
 ) -> ExeState<'a> {
     ExeState {
         program_stack: Vec::new(),
@@ -30,7 +30,7 @@ fn call_builtin(
     state: &mut ExeState,
     args: Vec<DataValue>,
 ) -> Vec<DataValue> {
-    (entry.exec)(state, args)
+    (entry.exec)(state, args).unwrap_or_else(|e| panic!("{}", e))
 }
 
 #[test]
@@ -157,7 +157,7 @@ fn interpret_cfuncsig_in_global_scope() {
     ];
     let builtins = get_interpret_builtins();
 
-    interpret(program, data_stack, global_state, &builtins);
+    interpret(program, data_stack, global_state, &builtins).unwrap_or_else(|e| panic!("{}", e));
 }
 
 #[test]
@@ -196,7 +196,8 @@ fn interpret_cfuncsig_label_arg_resolved() {
             &mut scope_stack,
             &mut global_state,
             &builtins,
-        );
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
     }
     match data_stack_out.as_slice() {
         [TemplateValue::Literal(ProgramValue::Data(DataValue::Float(v)))] => {
@@ -231,7 +232,7 @@ fn interpret_cfuncsig_undefined_label_arg_panics() {
         DataValue::Label("sqrt".to_owned()).into(),
     ];
     let builtins = get_interpret_builtins();
-    interpret(program, data_stack, global_state, &builtins);
+    interpret(program, data_stack, global_state, &builtins).unwrap_or_else(|e| panic!("{}", e));
 }
 
 #[test]
@@ -259,7 +260,7 @@ fn interpret_cfuncsig_variadic_stack_form() {
         DataValue::Label("printf".to_owned()).into(),
     ];
     let builtins = get_interpret_builtins();
-    interpret(program, data_stack, global_state, &builtins);
+    interpret(program, data_stack, global_state, &builtins).unwrap_or_else(|e| panic!("{}", e));
 }
 
 #[test]
@@ -300,7 +301,8 @@ fn interpret_cfuncsig_multi_param_stack_form() {
             &mut scope_stack,
             &mut global_state,
             &builtins,
-        );
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
     }
     match data_stack_out.as_slice() {
         [TemplateValue::Literal(ProgramValue::Data(DataValue::Float(v)))] => {
@@ -355,7 +357,8 @@ fn interpret_cfuncsig_multi_param_struct_form() {
             &mut scope_stack,
             &mut global_state,
             &builtins,
-        );
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
     }
     match data_stack_out.as_slice() {
         [TemplateValue::Literal(ProgramValue::Data(DataValue::Float(v)))] => {
@@ -607,13 +610,13 @@ fn c_load_header_available_at_comptime() {
         "debug_stack",
     ] {
         assert!(
-            builtins.contains_key(name),
+            builtins.contains_key(*name),
             "{name} must be a comptime builtin"
         );
     }
     for name in &["c_link_lib", "ptr_read_cstr"] {
         assert!(
-            !builtins.contains_key(name),
+            !builtins.contains_key(*name),
             "{name} must NOT be a comptime builtin"
         );
     }
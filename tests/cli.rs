@@ -0,0 +1,142 @@
+/// Tests for the `sid` binary's handling of its input.
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn sid_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sid"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start sid");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn dash_reads_program_from_stdin() {
+    let out = sid_with_stdin(&["-"], "1 2 add ! 3 eq ! assert !");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+#[test]
+fn stdin_errors_are_reported_against_stdin() {
+    let out = sid_with_stdin(&["-"], "1 true add !");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("--> <stdin>:1:8"), "{}", stderr);
+}
+
+#[test]
+fn shebang_line_is_skipped() {
+    let path = std::env::temp_dir().join(format!("sid_shebang_{}.sid", std::process::id()));
+    std::fs::write(&path, "#!/usr/bin/env sid\n1 2 add ! 3 eq ! assert !\n").unwrap();
+    let out = sid_with_stdin(&[path.to_str().unwrap()], "");
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+#[test]
+fn missing_file_is_an_error_not_a_panic() {
+    let out = sid_with_stdin(&["/nonexistent/program.sid"], "");
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.starts_with("error: failed to read"), "{}", stderr);
+}
+
+#[test]
+fn sandbox_flag_denies_filesystem_access() {
+    let out = sid_with_stdin(&["--sandbox", "-"], r#""Cargo.toml" read_file !"#);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("read_file: filesystem access is not allowed"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn allow_flag_reenables_a_capability() {
+    let out = sid_with_stdin(
+        &["--sandbox", "--allow-fs", "-"],
+        r#""Cargo.toml" exists ! assert !"#,
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+#[test]
+fn fuel_flag_stops_a_runaway_loop() {
+    let out = sid_with_stdin(&["--fuel", "10000", "-"], "(true) () while_do !");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("out of fuel"), "{}", stderr);
+}
+
+#[test]
+fn timeout_flag_stops_a_runaway_loop() {
+    let out = sid_with_stdin(&["--timeout", "0.2", "-"], "(true) () while_do !");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("deadline passed"), "{}", stderr);
+}
+
+#[test]
+fn runtime_errors_print_a_backtrace() {
+    let out = sid_with_stdin(&["-"], "f (1 0 div!) local!\nf!");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("backtrace (most recent call first):\n  0: f, called at 2:1"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn every_parse_error_is_reported_with_its_location() {
+    let out = sid_with_stdin(&["-"], "1 )\n2 ]\n(3");
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(stderr.matches("error: ").count(), 3, "{}", stderr);
+    assert!(stderr.contains("--> <stdin>:1:3"), "{}", stderr);
+    assert!(stderr.contains("--> <stdin>:2:3"), "{}", stderr);
+    assert!(stderr.contains("--> <stdin>:3:1"), "{}", stderr);
+}
+
+#[test]
+fn debug_subcommand_steps_and_stops_at_breakpoints() {
+    let path = std::env::temp_dir().join(format!("sid_debug_{}.sid", std::process::id()));
+    std::fs::write(&path, "f (1 2 add!) local!\nf!\n3 mul!\n").unwrap();
+    let out = sid_with_stdin(
+        &["debug", path.to_str().unwrap()],
+        "break builtin add\ncontinue\nbt\nwatch 2 3 add!\nout\ncontinue\n",
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("breakpoint 0: builtin add\n"), "{}", stdout);
+    assert!(stdout.contains("1 | f (1 2 add!) local!\n"), "{}", stdout);
+    assert!(stdout.contains("  0: f, called at 2:1\n"), "{}", stdout);
+    assert!(stdout.contains("3 | 3 mul!\n"), "{}", stdout);
+    assert!(stdout.contains("0: 2 3 add! = 5\n"), "{}", stdout);
+    assert!(stdout.ends_with("program finished\n"), "{}", stdout);
+}
@@ -0,0 +1,107 @@
+use sid::*;
+/// Tests for the ordering built-ins `lt`, `le`, `gt`, `ge`.
+///
+/// Int and Float compare numerically (mixed operands are promoted), Char, Str
+/// and Label compare bytewise, and any other pairing is an error.
+use std::collections::HashMap;
+
+fn run_snippet(source: &str) -> Vec<DataValue> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered: DataValue = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
+    let builtins = get_interpret_builtins();
+    let mut global_scope_for_run = global_scope;
+    let global_state = GlobalState::new(&mut global_scope_for_run);
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: instructions,
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn bools(values: &[bool]) -> Vec<DataValue> {
+    values.iter().map(|b| DataValue::Bool(*b)).collect()
+}
+
+#[test]
+fn int_ordering() {
+    assert_eq!(
+        run_snippet("1 2 lt! 2 2 lt! 2 2 le! 3 2 gt! 2 2 gt! 2 2 ge!"),
+        bools(&[true, false, true, true, false, true])
+    );
+}
+
+#[test]
+fn float_and_mixed_ordering() {
+    assert_eq!(
+        run_snippet("0.5 1.5 lt! 1 0.5 gt! 2 2.0 le! 2.0 2 ge!"),
+        bools(&[true, true, true, true])
+    );
+}
+
+#[test]
+fn str_char_label_ordering_is_bytewise() {
+    assert_eq!(
+        run_snippet(r#""abc" "abd" lt! "b" "abc" gt! 'a' 'b' lt! "Z" "a" lt!"#),
+        bools(&[true, true, true, true])
+    );
+    // Undefined labels stay labels and compare by name.
+    assert_eq!(run_snippet("apple banana lt!"), bools(&[true]));
+}
+
+/// Ordering built-ins are available at comptime.
+#[test]
+fn comptime_ordering() {
+    assert_eq!(run_snippet("1 2 lt @!"), bools(&[true]));
+}
+
+#[test]
+#[should_panic(expected = "lt: cannot compare")]
+fn cross_kind_comparison_errors() {
+    run_snippet(r#"1 "one" lt!"#);
+}
+
+#[test]
+#[should_panic(expected = "ge: cannot compare")]
+fn bool_comparison_errors() {
+    run_snippet("true false ge!");
+}
@@ -2,23 +2,36 @@ use std::collections::HashMap;
 
 use sid::*;
 
-fn mock_double(_state: &mut sid::ExeState, args: Vec<DataValue>) -> Vec<DataValue> {
+fn mock_double(
+    _state: &mut sid::ExeState,
+    args: Vec<DataValue>,
+) -> Result<Vec<DataValue>, SidError> {
     match args.into_iter().next() {
-        Some(DataValue::Int(n)) => vec![DataValue::Int(n * 2)],
-        Some(other) => panic!("MockDouble: expected Int, got {:?}", other),
-        None => panic!("MockDouble: expected an argument"),
+        Some(DataValue::Int(n)) => Ok(vec![DataValue::Int(n * 2)]),
+        Some(other) => Err(SidError::TypeMismatch(format!(
+            "MockDouble: expected Int, got {:?}",
+            other
+        ))),
+        None => Err(SidError::StackUnderflow(
+            "MockDouble: expected an argument".to_owned(),
+        )),
     }
 }
 
-fn mock_drop(_state: &mut sid::ExeState, args: Vec<DataValue>) -> Vec<DataValue> {
+fn mock_drop(_state: &mut sid::ExeState, args: Vec<DataValue>) -> Result<Vec<DataValue>, SidError> {
     if args.is_empty() {
-        panic!("MockDrop: expected an argument");
+        return Err(SidError::StackUnderflow(
+            "MockDrop: expected an argument".to_owned(),
+        ));
     }
-    vec![]
+    Ok(vec![])
 }
 
-fn mock_const(_state: &mut sid::ExeState, _args: Vec<DataValue>) -> Vec<DataValue> {
-    vec![DataValue::Int(42)]
+fn mock_const(
+    _state: &mut sid::ExeState,
+    _args: Vec<DataValue>,
+) -> Result<Vec<DataValue>, SidError> {
+    Ok(vec![DataValue::Int(42)])
 }
 
 pub struct ComptimePassFixture {
@@ -26,7 +39,7 @@ pub struct ComptimePassFixture {
     pub expected_output: Vec<TemplateValue>,
 }
 impl ComptimePassFixture {
-    pub fn test(&self, builtins: &HashMap<&'static str, sid::BuiltinEntry>) {
+    pub fn test(&self, builtins: &HashMap<String, sid::BuiltinEntry>) {
         let result = comptime_pass(self.input.clone(), builtins, &mut HashMap::new())
             .expect("comptime_pass failed unexpectedly");
         assert_eq!(
@@ -40,7 +53,7 @@ pub struct ComptimeErrorFixture {
     pub input: Vec<TemplateValue>,
 }
 impl ComptimeErrorFixture {
-    pub fn test(&self, builtins: &HashMap<&'static str, sid::BuiltinEntry>) {
+    pub fn test(&self, builtins: &HashMap<String, sid::BuiltinEntry>) {
         assert!(
             comptime_pass(self.input.clone(), builtins, &mut HashMap::new()).is_err(),
             "expected comptime_pass to return Err but it succeeded"
@@ -48,7 +61,7 @@ impl ComptimeErrorFixture {
     }
 }
 
-fn no_builtins() -> HashMap<&'static str, sid::BuiltinEntry> {
+fn no_builtins() -> HashMap<String, sid::BuiltinEntry> {
     HashMap::new()
 }
 
@@ -109,14 +122,14 @@ fn passthrough_parent_refs() {
 
 #[test]
 fn comptime_invoke_one_arg_one_return() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "double",
+        "double".to_owned(),
         sid::BuiltinEntry {
-            name: "double",
+            name: "double".to_owned(),
             args: vec![SidType::Int],
             ret: vec![SidType::Int],
-            exec: mock_double,
+            exec: Box::new(mock_double),
         },
     );
 
@@ -129,14 +142,14 @@ fn comptime_invoke_one_arg_one_return() {
 
 #[test]
 fn comptime_invoke_one_arg_zero_return() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "drop",
+        "drop".to_owned(),
         sid::BuiltinEntry {
-            name: "drop",
+            name: "drop".to_owned(),
             args: vec![SidType::Any],
             ret: vec![],
-            exec: mock_drop,
+            exec: Box::new(mock_drop),
         },
     );
 
@@ -149,14 +162,14 @@ fn comptime_invoke_one_arg_zero_return() {
 
 #[test]
 fn comptime_invoke_zero_arg_one_return() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "const",
+        "const".to_owned(),
         sid::BuiltinEntry {
-            name: "const",
+            name: "const".to_owned(),
             args: vec![],
             ret: vec![SidType::Int],
-            exec: mock_const,
+            exec: Box::new(mock_const),
         },
     );
 
@@ -169,14 +182,14 @@ fn comptime_invoke_zero_arg_one_return() {
 
 #[test]
 fn comptime_invoke_leaves_surrounding_stack_intact() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "double",
+        "double".to_owned(),
         sid::BuiltinEntry {
-            name: "double",
+            name: "double".to_owned(),
             args: vec![SidType::Int],
             ret: vec![SidType::Int],
-            exec: mock_double,
+            exec: Box::new(mock_double),
         },
     );
 
@@ -199,14 +212,14 @@ fn comptime_invoke_leaves_surrounding_stack_intact() {
 
 #[test]
 fn recurses_into_substack_body() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "double",
+        "double".to_owned(),
         sid::BuiltinEntry {
-            name: "double",
+            name: "double".to_owned(),
             args: vec![SidType::Int],
             ret: vec![SidType::Int],
-            exec: mock_double,
+            exec: Box::new(mock_double),
         },
     );
 
@@ -226,14 +239,14 @@ fn recurses_into_substack_body() {
 
 #[test]
 fn recurses_into_list_body() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "double",
+        "double".to_owned(),
         sid::BuiltinEntry {
-            name: "double",
+            name: "double".to_owned(),
             args: vec![SidType::Int],
             ret: vec![SidType::Int],
-            exec: mock_double,
+            exec: Box::new(mock_double),
         },
     );
 
@@ -253,14 +266,14 @@ fn recurses_into_list_body() {
 
 #[test]
 fn recurses_into_script_body() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "double",
+        "double".to_owned(),
         sid::BuiltinEntry {
-            name: "double",
+            name: "double".to_owned(),
             args: vec![SidType::Int],
             ret: vec![SidType::Int],
-            exec: mock_double,
+            exec: Box::new(mock_double),
         },
     );
 
@@ -292,14 +305,14 @@ fn error_comptime_invoke_unknown_function() {
 
 #[test]
 fn error_comptime_invoke_unrendered_template_as_arg() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "double",
+        "double".to_owned(),
         sid::BuiltinEntry {
-            name: "double",
+            name: "double".to_owned(),
             args: vec![SidType::Int],
             ret: vec![SidType::Int],
-            exec: mock_double,
+            exec: Box::new(mock_double),
         },
     );
 
@@ -317,14 +330,14 @@ fn error_comptime_invoke_unrendered_template_as_arg() {
 
 #[test]
 fn error_comptime_invoke_parent_ref_as_arg() {
-    let mut builtins: HashMap<&'static str, sid::BuiltinEntry> = HashMap::new();
+    let mut builtins: HashMap<String, sid::BuiltinEntry> = HashMap::new();
     builtins.insert(
-        "double",
+        "double".to_owned(),
         sid::BuiltinEntry {
-            name: "double",
+            name: "double".to_owned(),
             args: vec![SidType::Int],
             ret: vec![SidType::Int],
-            exec: mock_double,
+            exec: Box::new(mock_double),
         },
     );
 
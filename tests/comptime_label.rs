@@ -35,6 +35,7 @@ fn comptime_label_inside_nested_substack() {
     let input = vec![TemplateValue::Literal(ProgramValue::Template(Template {
         data: TemplateData::Substack(vec![comptime_label("x")]),
         consumes_stack_entries: 0,
+        spans: SpanTable::default(),
     }))];
     let result = comptime_pass(input, &HashMap::new(), &mut scope).expect("comptime_pass failed");
 
@@ -43,6 +44,7 @@ fn comptime_label_inside_nested_substack() {
         vec![TemplateValue::Literal(ProgramValue::Template(Template {
             data: TemplateData::Substack(vec![DataValue::Int(7).into()]),
             consumes_stack_entries: 0,
+            spans: SpanTable::default(),
         }))]
     );
 }
@@ -55,6 +57,7 @@ fn comptime_label_inside_nested_list() {
     let input = vec![TemplateValue::Literal(ProgramValue::Template(Template {
         data: TemplateData::List(vec![comptime_label("v")]),
         consumes_stack_entries: 0,
+        spans: SpanTable::default(),
     }))];
     let result = comptime_pass(input, &HashMap::new(), &mut scope).expect("comptime_pass failed");
 
@@ -63,6 +66,7 @@ fn comptime_label_inside_nested_list() {
         vec![TemplateValue::Literal(ProgramValue::Template(Template {
             data: TemplateData::List(vec![DataValue::Int(42).into()]),
             consumes_stack_entries: 0,
+            spans: SpanTable::default(),
         }))]
     );
 }
@@ -78,6 +82,7 @@ fn comptime_label_inside_nested_map() {
             vec![comptime_label("t")],
         )]),
         consumes_stack_entries: 0,
+        spans: SpanTable::default(),
     }))];
     let result = comptime_pass(input, &HashMap::new(), &mut scope).expect("comptime_pass failed");
 
@@ -89,6 +94,7 @@ fn comptime_label_inside_nested_map() {
                 vec![DataValue::Type(SidType::Int).into()],
             )]),
             consumes_stack_entries: 0,
+            spans: SpanTable::default(),
         }))]
     );
 }
@@ -114,6 +120,7 @@ fn comptime_label_parsed_without_at_sign() {
                 "types.int".to_owned()
             )]),
             consumes_stack_entries: 0,
+            spans: SpanTable::default(),
         }))]
     );
 }
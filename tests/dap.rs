@@ -0,0 +1,181 @@
+use serde_json::{json, Value};
+/// Tests for `sid dap`, driven by a scripted client: every request is written
+/// up front and the responses and events are checked afterwards.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+
+const PROGRAM: &str = "double (\n  2 mul!\n) local!\n\"start\\n\" print!\n3 double!\n";
+
+/// Launch `source` from a temporary file, send `requests` (numbered from 1)
+/// and return every message the adapter sent back.
+fn session(name: &str, source: &str, requests: &[Value]) -> Vec<Value> {
+    let path = std::env::temp_dir().join(format!("sid_dap_{}_{}.sid", name, std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    let mut input = Vec::new();
+    for (i, request) in requests.iter().enumerate() {
+        let mut request = request.clone();
+        request["seq"] = json!(i + 1);
+        request["type"] = json!("request");
+        if request["command"] == "launch" {
+            request["arguments"]["program"] = json!(path.to_str().unwrap());
+        }
+        let body = request.to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sid"))
+        .arg("dap")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut messages = Vec::new();
+    loop {
+        let mut header = String::new();
+        if stdout.read_line(&mut header).unwrap() == 0 {
+            break;
+        }
+        let length: usize = header
+            .trim()
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        stdout.read_line(&mut String::new()).unwrap();
+        let mut body = vec![0; length];
+        stdout.read_exact(&mut body).unwrap();
+        messages.push(serde_json::from_slice(&body).unwrap());
+    }
+    assert!(child.wait().unwrap().success());
+    std::fs::remove_file(&path).unwrap();
+    messages
+}
+
+fn request(command: &str, arguments: Value) -> Value {
+    json!({ "command": command, "arguments": arguments })
+}
+
+fn response(messages: &[Value], request_seq: i64) -> &Value {
+    messages
+        .iter()
+        .find(|m| m["type"] == "response" && m["request_seq"] == request_seq)
+        .unwrap_or_else(|| panic!("no response to request {}", request_seq))
+}
+
+fn events<'a>(messages: &'a [Value], event: &str) -> Vec<&'a Value> {
+    messages
+        .iter()
+        .filter(|m| m["type"] == "event" && m["event"] == event)
+        .collect()
+}
+
+#[test]
+fn breakpoint_stack_trace_and_variables() {
+    let messages = session(
+        "breakpoint",
+        PROGRAM,
+        &[
+            request("initialize", json!({ "adapterID": "sid" })),
+            request("launch", json!({})),
+            request(
+                "setBreakpoints",
+                json!({ "source": {}, "breakpoints": [{ "line": 2 }] }),
+            ),
+            request("configurationDone", json!({})),
+            request("stackTrace", json!({ "threadId": 1 })),
+            request("variables", json!({ "variablesReference": 1 })),
+            request("variables", json!({ "variablesReference": 4 })),
+            request(
+                "evaluate",
+                json!({ "expression": "double", "frameId": 1, "context": "hover" }),
+            ),
+            request("continue", json!({ "threadId": 1 })),
+            request("disconnect", json!({})),
+        ],
+    );
+    assert_eq!(response(&messages, 1)["success"], true);
+    assert_eq!(events(&messages, "initialized").len(), 1);
+    assert_eq!(response(&messages, 2)["success"], true);
+    assert_eq!(
+        response(&messages, 3)["body"]["breakpoints"][0]["verified"],
+        true
+    );
+
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped[0]["body"]["reason"], "breakpoint");
+    let output = events(&messages, "output");
+    assert_eq!(output[0]["body"]["output"], "start\n");
+
+    let frames = &response(&messages, 5)["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "double");
+    assert_eq!(frames[0]["line"], 2);
+    assert_eq!(frames[1]["name"], "<main>");
+    assert_eq!(frames[1]["line"], 5);
+    assert_eq!(frames[1]["column"], 3);
+
+    let stack = &response(&messages, 6)["body"]["variables"];
+    assert_eq!(stack[0]["value"], "3");
+    let caller_locals = &response(&messages, 7)["body"]["variables"];
+    assert_eq!(caller_locals[0]["name"], "double");
+    assert_eq!(caller_locals[0]["value"], "( 2 mul ! )");
+
+    assert_eq!(response(&messages, 8)["body"]["result"], "( 2 mul ! )");
+    assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 0);
+    assert_eq!(events(&messages, "terminated").len(), 1);
+}
+
+#[test]
+fn stepping_and_stop_on_entry() {
+    let messages = session(
+        "step",
+        "1\n2 add!\n",
+        &[
+            request("initialize", json!({})),
+            request("launch", json!({ "stopOnEntry": true })),
+            request("configurationDone", json!({})),
+            request("next", json!({ "threadId": 1 })),
+            request("stackTrace", json!({ "threadId": 1 })),
+            request("disconnect", json!({})),
+        ],
+    );
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped[0]["body"]["reason"], "entry");
+    assert_eq!(stopped[1]["body"]["reason"], "step");
+    assert_eq!(response(&messages, 5)["body"]["stackFrames"][0]["line"], 2);
+}
+
+#[test]
+fn runtime_errors_stop_with_an_exception() {
+    let messages = session(
+        "error",
+        "1 0 div!\n",
+        &[
+            request("initialize", json!({})),
+            request("launch", json!({})),
+            request("configurationDone", json!({})),
+            request("continue", json!({ "threadId": 1 })),
+            request("disconnect", json!({})),
+        ],
+    );
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped[0]["body"]["reason"], "exception");
+    assert_eq!(stopped[0]["body"]["text"], "div: division by zero");
+    assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 1);
+}
+
+#[test]
+fn launch_reports_parse_errors() {
+    let messages = session(
+        "parse_error",
+        "(1 2",
+        &[
+            request("initialize", json!({})),
+            request("launch", json!({})),
+            request("disconnect", json!({})),
+        ],
+    );
+    let launch = response(&messages, 2);
+    assert_eq!(launch["success"], false);
+    assert!(!launch["message"].as_str().unwrap().is_empty(), "{}", launch);
+}
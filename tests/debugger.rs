@@ -0,0 +1,160 @@
+use sid::*;
+/// Tests for `Debugger`: stepping by call depth, the three kinds of
+/// breakpoint, watch expressions and stopping on errors.
+///
+/// `PROGRAM` defines `double` on line 1 and calls it on line 2; line 3 runs
+/// after the call returns.
+const PROGRAM: &str = "double (2 mul!) local!\n3 double!\n1 add!";
+
+fn loaded(source: &str) -> (Debugger, MockSideEffects) {
+    let side_effects = MockSideEffects::new();
+    let mut debugger = Debugger::with_side_effects(Box::new(side_effects.clone()));
+    debugger.load(source).unwrap();
+    (debugger, side_effects)
+}
+
+fn line(debugger: &Debugger) -> usize {
+    debugger.span().expect("no span").line
+}
+
+#[test]
+fn load_pauses_before_the_first_instruction() {
+    let (debugger, _) = loaded(PROGRAM);
+    assert_eq!(debugger.span(), Some(Span::new(1, 1, 6)));
+    assert!(debugger.data_stack().is_empty());
+    assert!(!debugger.is_finished());
+}
+
+#[test]
+fn step_into_enters_calls_and_step_out_leaves_them() {
+    let (mut debugger, _) = loaded(PROGRAM);
+    debugger
+        .breakpoints
+        .push(Breakpoint::Label("double".to_owned()));
+    assert_eq!(
+        debugger.resume(Resume::Continue).unwrap(),
+        Stop::Breakpoint(0)
+    );
+    assert_eq!(debugger.depth(), 0);
+
+    assert_eq!(debugger.resume(Resume::StepInto).unwrap(), Stop::Paused);
+    assert_eq!(debugger.depth(), 1);
+    assert_eq!(line(&debugger), 1);
+    assert_eq!(
+        debugger.backtrace().calls[0].callee.as_deref(),
+        Some("double")
+    );
+
+    assert_eq!(debugger.resume(Resume::StepOut).unwrap(), Stop::Paused);
+    assert_eq!(debugger.depth(), 0);
+    assert_eq!(line(&debugger), 3);
+    assert_eq!(
+        debugger.data_stack().last(),
+        Some(&TemplateValue::from(DataValue::Int(6)))
+    );
+}
+
+#[test]
+fn step_over_runs_whole_calls() {
+    let (mut debugger, _) = loaded(PROGRAM);
+    let mut lines = vec![line(&debugger)];
+    while debugger.resume(Resume::StepOver).unwrap() == Stop::Paused {
+        assert_eq!(debugger.depth(), 0);
+        lines.push(line(&debugger));
+    }
+    assert!(debugger.is_finished());
+    assert_eq!(lines, vec![1, 1, 1, 1, 2, 2, 2, 3, 3, 3]);
+}
+
+#[test]
+fn line_breakpoints_hit_once_per_visit() {
+    let (mut debugger, _) = loaded(PROGRAM);
+    debugger.breakpoints.push("1".parse().unwrap());
+    // Line 1 is where the program starts, so the first hit is on the way
+    // back in, inside `double`.
+    assert_eq!(
+        debugger.resume(Resume::Continue).unwrap(),
+        Stop::Breakpoint(0)
+    );
+    assert_eq!(debugger.depth(), 1);
+    assert_eq!(debugger.resume(Resume::Continue).unwrap(), Stop::Finished);
+}
+
+#[test]
+fn column_and_builtin_breakpoints() {
+    let (mut debugger, _) = loaded(PROGRAM);
+    debugger.breakpoints.push("3:1".parse().unwrap());
+    debugger.breakpoints.push("builtin mul".parse().unwrap());
+    assert_eq!(
+        debugger.resume(Resume::Continue).unwrap(),
+        Stop::Breakpoint(1)
+    );
+    assert_eq!(debugger.span(), Some(Span::new(1, 11, 4)));
+    assert_eq!(
+        debugger.resume(Resume::Continue).unwrap(),
+        Stop::Breakpoint(0)
+    );
+    assert_eq!(debugger.span(), Some(Span::new(3, 1, 1)));
+}
+
+#[test]
+fn breakpoint_syntax_round_trips() {
+    for text in ["12", "12:5", "double", "builtin print"] {
+        let breakpoint: Breakpoint = text.parse().unwrap();
+        assert_eq!(breakpoint.to_string(), text);
+    }
+    assert!("not a label".parse::<Breakpoint>().is_err());
+    assert!("3:x".parse::<Breakpoint>().is_err());
+}
+
+#[test]
+fn watches_see_the_paused_scope_without_changing_it() {
+    let (mut debugger, side_effects) = loaded("x 5 local!\nx 1 add!");
+    debugger.watches.push("x 2 mul!".to_owned());
+    debugger.watches.push("\"hi\" print!".to_owned());
+    debugger.watches.push("y".to_owned());
+    while line(&debugger) == 1 {
+        debugger.resume(Resume::StepOver).unwrap();
+    }
+    let watches = debugger.watch_values();
+    assert_eq!(watches[0].1.as_ref().unwrap(), &vec![DataValue::Int(10)]);
+    assert!(watches[1].1.as_ref().unwrap().is_empty());
+    assert_eq!(
+        watches[2].1.as_ref().unwrap(),
+        &vec![DataValue::Label("y".to_owned())]
+    );
+    assert!(debugger.data_stack().is_empty());
+    assert_eq!(side_effects.stdout_string(), "");
+}
+
+#[test]
+fn errors_stop_the_program_where_it_failed() {
+    let (mut debugger, _) = loaded("f (1 0 div!) local!\nf!");
+    let err = debugger.resume(Resume::Continue).unwrap_err();
+    assert!(matches!(err.unlocated(), SidError::Arithmetic(_)));
+    assert_eq!(debugger.error(), Some(&err));
+    assert!(debugger.is_finished());
+    assert_eq!(debugger.depth(), 1);
+    assert_eq!(debugger.resume(Resume::StepInto), Err(err));
+}
+
+#[test]
+fn advance_respects_its_step_budget() {
+    let (mut debugger, _) = loaded("(true) () while_do!");
+    debugger.start(Resume::Continue);
+    assert_eq!(debugger.advance(1000).unwrap(), Stop::Running);
+    assert!(debugger.is_running());
+    debugger.pause();
+    assert!(!debugger.is_running());
+    assert_eq!(debugger.advance(1000).unwrap(), Stop::Paused);
+}
+
+#[test]
+fn evaluate_in_reaches_caller_scopes() {
+    let (mut debugger, _) = loaded(PROGRAM);
+    debugger.breakpoints.push("builtin mul".parse().unwrap());
+    debugger.resume(Resume::Continue).unwrap();
+    assert!(debugger.evaluate_in("3 double!", 1).is_ok());
+    assert!(debugger.evaluate("3 double!").is_err());
+    assert!(debugger.evaluate_in("1", 2).is_err());
+}
@@ -0,0 +1,211 @@
+use sid::*;
+/// Tests for the `Engine` embedding API and the conversions between
+/// `DataValue` and Rust types.
+use std::ffi::CString;
+
+#[test]
+fn eval_leaves_values_to_pop() {
+    let mut engine = Engine::new();
+    engine.eval_str("1 2 add!").unwrap();
+    assert_eq!(engine.stack_len(), 1);
+    let sum: i64 = engine.pop().unwrap().unwrap().try_into().unwrap();
+    assert_eq!(sum, 3);
+    assert_eq!(engine.pop().unwrap(), None);
+}
+
+#[test]
+fn stack_and_locals_persist_between_evals() {
+    let mut engine = Engine::new();
+    engine.eval_str("x 10 local!").unwrap();
+    engine.push(5i64);
+    engine.eval_str("x add!").unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(15)));
+}
+
+#[test]
+fn call_runs_a_named_function() {
+    let mut engine = Engine::new();
+    engine
+        .eval_str("sub_then_double (sub! 2 mul!) local!")
+        .unwrap();
+    engine.push(100i64);
+    let out = engine
+        .call("sub_then_double", vec![10i64.into(), 4i64.into()])
+        .unwrap();
+    assert_eq!(out, vec![DataValue::Int(12)]);
+    // Values below the arguments are left alone.
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(100)));
+}
+
+#[test]
+fn call_reaches_builtins() {
+    let mut engine = Engine::new();
+    let out = engine.call("mul", vec![6i64.into(), 7i64.into()]).unwrap();
+    assert_eq!(out, vec![DataValue::Int(42)]);
+}
+
+#[test]
+fn call_that_consumes_earlier_values_fails() {
+    let mut engine = Engine::new();
+    engine.push(1i64);
+    let err = engine.call("drop", vec![]).unwrap_err();
+    assert!(matches!(err, SidError::StackUnderflow(_)), "{}", err);
+}
+
+#[test]
+fn globals_are_visible_to_code() {
+    let mut engine = Engine::new();
+    engine.set_global("answer", 42i64);
+    engine.eval_str("answer 1 add!").unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(43)));
+    assert_eq!(engine.get_global("answer"), Some(&DataValue::Int(42)));
+    assert!(engine.get_global("missing").is_none());
+}
+
+#[test]
+fn failed_eval_leaves_state_unchanged() {
+    let mut engine = Engine::new();
+    engine.push(1i64);
+    engine.eval_str("y 2 local!").unwrap();
+    let err = engine
+        .eval_str("z 3 local! 10 undefined_label!")
+        .unwrap_err();
+    assert!(err.downcast_ref::<SidError>().is_some(), "{}", err);
+    assert_eq!(engine.stack_len(), 1);
+    assert!(engine.call("z", vec![]).is_err());
+    engine.eval_str("y get!").unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(2)));
+}
+
+#[test]
+fn eval_file_reads_through_side_effects() {
+    let mock = MockSideEffects::new();
+    mock.set_file("/prog.sid", r#""hello" println!"#);
+    let mut engine = Engine::with_side_effects(Box::new(mock.clone()));
+    engine.eval_file("/prog.sid").unwrap();
+    assert_eq!(mock.stdout_string(), "hello\n");
+    assert!(engine.eval_file("/missing.sid").is_err());
+}
+
+#[test]
+fn sandbox_and_limits_apply() {
+    let mock = MockSideEffects::new();
+    let mut engine = Engine::with_side_effects(Box::new(mock));
+    engine.sandbox = SandboxPolicy::deny_all();
+    let err = engine.eval_str(r#""/f" exists!"#).unwrap_err();
+    assert!(
+        matches!(
+            err.downcast_ref::<SidError>().map(SidError::unlocated),
+            Some(SidError::PermissionDenied(_))
+        ),
+        "{}",
+        err
+    );
+
+    engine.limits.fuel = Some(500);
+    let err = engine.eval_str("(true) () while_do!").unwrap_err();
+    assert_eq!(err.to_string(), "out of fuel");
+}
+
+#[test]
+fn rust_values_convert_to_data_values() {
+    assert_eq!(DataValue::from(true), DataValue::Bool(true));
+    assert_eq!(DataValue::from(1.5), DataValue::Float(1.5));
+    assert_eq!(DataValue::from('x'), DataValue::Char("x".to_owned()));
+    assert_eq!(
+        DataValue::from(vec![1i64, 2]),
+        DataValue::List(vec![DataValue::Int(1), DataValue::Int(2)])
+    );
+    assert_eq!(
+        DataValue::try_from("hi").unwrap(),
+        DataValue::Str(CString::new("hi").unwrap())
+    );
+    assert!(matches!(
+        DataValue::try_from("a\0b"),
+        Err(SidError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn data_values_convert_to_rust_values() {
+    let list = DataValue::List(vec![DataValue::Int(1), DataValue::Int(2)]);
+    assert_eq!(Vec::<i64>::try_from(list).unwrap(), vec![1, 2]);
+    assert_eq!(f64::try_from(DataValue::Int(2)).unwrap(), 2.0);
+    let s = DataValue::try_from("hi").unwrap();
+    assert_eq!(String::try_from(s).unwrap(), "hi");
+    assert!(matches!(
+        i64::try_from(DataValue::Bool(true)),
+        Err(SidError::TypeMismatch(_))
+    ));
+    let mixed = DataValue::List(vec![DataValue::Int(1), DataValue::Bool(false)]);
+    assert!(Vec::<i64>::try_from(mixed).is_err());
+}
+
+#[test]
+fn host_closures_capture_state() {
+    let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let mut engine = Engine::new();
+    let sink = log.clone();
+    engine.register_builtin(
+        "record",
+        vec![SidType::Int],
+        vec![SidType::Int],
+        move |_state, args| {
+            let n = i64::try_from(args[0].clone())?;
+            sink.borrow_mut().push(n);
+            Ok(vec![DataValue::Int(sink.borrow().len() as i64)])
+        },
+    );
+    engine.eval_str("5 record! 7 record!").unwrap();
+    assert_eq!(*log.borrow(), vec![5, 7]);
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(2)));
+    let out = engine.call("record", vec![9i64.into()]).unwrap();
+    assert_eq!(out, vec![DataValue::Int(3)]);
+}
+
+#[test]
+fn host_closures_take_several_args() {
+    let mut engine = Engine::new();
+    let offset = 100;
+    engine.register_builtin(
+        "offset_sub",
+        vec![SidType::Int, SidType::Int],
+        vec![SidType::Int],
+        move |_state, args| {
+            // Arguments arrive in stack order, the top last.
+            let below = i64::try_from(args[0].clone())?;
+            let top = i64::try_from(args[1].clone())?;
+            Ok(vec![DataValue::Int(below - top + offset)])
+        },
+    );
+    engine.eval_str("10 3 offset_sub!").unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(107)));
+}
+
+#[test]
+fn host_closure_args_are_type_checked() {
+    let mut engine = Engine::new();
+    engine.register_builtin("needs_int", vec![SidType::Int], vec![], |_state, _args| {
+        panic!("must not run with a bad argument")
+    });
+    let err = engine.eval_str("true needs_int!").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "builtin 'needs_int': arg 0 expected Int, got Bool(true)"
+    );
+}
+
+#[test]
+fn host_closure_returns_are_type_checked() {
+    let mut engine = Engine::new();
+    engine.register_builtin("liar", vec![], vec![SidType::Int], |_state, _args| {
+        Ok(vec![DataValue::Bool(false)])
+    });
+    let err = engine.call("liar", vec![]).unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::TypeMismatch(_)),
+        "{}",
+        err
+    );
+    assert_eq!(engine.stack_len(), 0);
+}
@@ -0,0 +1,126 @@
+use sid::*;
+
+use std::collections::HashMap;
+
+/// Run `source` to completion and return the first error, if any.
+///
+/// Unlike the `run_snippet` helpers elsewhere this never unwraps, so each test
+/// checks that the failure surfaces as a `SidError` rather than a panic.
+fn run_snippet(source: &str) -> Result<(), SidError> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )?
+    };
+    let builtins = get_interpret_builtins();
+    interpret(
+        vec![ProgramValue::Invoke],
+        vec![TemplateValue::from(rendered)],
+        GlobalState::new(&mut global_scope),
+        &builtins,
+    )
+}
+
+#[test]
+fn builtin_type_mismatch() {
+    let err = run_snippet("true 1 add !").unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::TypeMismatch(_)),
+        "{:?}",
+        err
+    );
+    assert!(err.message().contains("builtin 'add'"), "{}", err);
+}
+
+#[test]
+fn builtin_stack_underflow() {
+    let err = run_snippet("1 add !").unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::StackUnderflow(_)),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn invoke_on_empty_stack_underflows() {
+    let err = run_snippet("!").unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::StackUnderflow(_)),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn undefined_label() {
+    let err = run_snippet("no_such_label !").unwrap_err();
+    assert_eq!(
+        err,
+        SidError::UndefinedLabel("undefined label 'no_such_label'".to_owned())
+    );
+}
+
+#[test]
+fn assertion_failed() {
+    let err = run_snippet("1 2 eq ! assert !").unwrap_err();
+    assert_eq!(
+        err,
+        SidError::AssertionFailed("assertion failed".to_owned())
+    );
+    assert_eq!(err.to_string(), "assertion failed");
+}
+
+#[test]
+fn arithmetic_error() {
+    let err = run_snippet("1 0 div !").unwrap_err();
+    assert_eq!(
+        err,
+        SidError::Arithmetic("div: division by zero".to_owned())
+    );
+}
+
+#[test]
+fn loop_invariant() {
+    let err = run_snippet("0 (true) (1) while_do !").unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::LoopInvariant(_)),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn match_without_case_is_invalid_argument() {
+    let err = run_snippet("3 {1: (42)} match !").unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::InvalidArgument(_)),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn ffi_error() {
+    let err = run_snippet(r#""/nonexistent/header.h" c_load_header !"#).unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::FfiError(_)),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn successful_program_returns_ok() {
+    assert_eq!(run_snippet("1 2 add ! 3 eq ! assert !"), Ok(()));
+}
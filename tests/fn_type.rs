@@ -19,6 +19,7 @@ fn run_snippet(source: &str) -> Vec<DataValue> {
             &mut gs,
             &comptime_builtins,
         )
+        .unwrap_or_else(|e| panic!("{}", e))
     };
     let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
     let builtins = get_interpret_builtins();
@@ -40,7 +41,8 @@ fn run_snippet(source: &str) -> Vec<DataValue> {
             &mut exe_state.scope_stack,
             &mut exe_state.global_state,
             &builtins,
-        );
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
     }
     exe_state
         .data_stack
@@ -81,6 +83,7 @@ fn typed_args_sets_args_on_substack() {
             body: vec![ProgramValue::Data(DataValue::Int(42))],
             args: Some(vec![("n".to_owned(), SidType::Int)]),
             ret: None,
+            spans: SpanTable::default(),
         }]
     );
 }
@@ -116,6 +119,7 @@ fn typed_rets_sets_ret_on_substack() {
             body: vec![ProgramValue::Data(DataValue::Int(42))],
             args: None,
             ret: Some(vec![SidType::Int]),
+            spans: SpanTable::default(),
         }]
     );
 }
@@ -143,6 +147,7 @@ fn typed_args_and_rets_chained_on_substack() {
             body: vec![ProgramValue::Data(DataValue::Int(42))],
             args: Some(vec![("n".to_owned(), SidType::Int)]),
             ret: Some(vec![SidType::Bool]),
+            spans: SpanTable::default(),
         }]
     );
 }
@@ -170,6 +175,7 @@ fn untyped_args_clears_args_on_substack() {
             body: vec![ProgramValue::Data(DataValue::Int(42))],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         }]
     );
 }
@@ -183,6 +189,7 @@ fn untyped_rets_clears_ret_on_substack() {
             body: vec![ProgramValue::Data(DataValue::Int(42))],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         }]
     );
 }
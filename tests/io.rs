@@ -0,0 +1,154 @@
+use sid::*;
+/// Tests for the console and file built-ins.
+///
+/// These run against `MockSideEffects` so nothing touches the real terminal
+/// or filesystem, except for one round trip through `RealSideEffects` in a
+/// temporary directory.
+use std::collections::HashMap;
+
+fn run_with(source: &str, effects: Box<dyn SideEffects>) -> Result<Vec<DataValue>, SidError> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let mut global_state = GlobalState::with_side_effects(&mut global_scope, effects);
+    let rendered = render_template(
+        Template::substack((after_comptime, 0)),
+        &mut vec![],
+        &HashMap::new(),
+        &mut global_state,
+        &comptime_builtins,
+    )?;
+    let builtins = get_interpret_builtins();
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: vec![TemplateValue::from(rendered)],
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )?;
+    }
+    Ok(exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+fn run_mocked(source: &str, mock: &MockSideEffects) -> Vec<DataValue> {
+    run_with(source, Box::new(mock.clone())).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn s(v: &str) -> DataValue {
+    DataValue::Str(std::ffi::CString::new(v).unwrap())
+}
+
+// ── Console ───────────────────────────────────────────────────────────────────
+
+#[test]
+fn print_println_and_eprint() {
+    let mock = MockSideEffects::new();
+    run_mocked(
+        r#""hello" print! ' ' print! "world" println! 42 println! "oops" eprint!"#,
+        &mock,
+    );
+    assert_eq!(mock.stdout_string(), "hello world\n42\n");
+    assert_eq!(mock.stderr_string(), "oops");
+}
+
+#[test]
+fn read_line_keeps_newline_and_is_empty_at_eof() {
+    let mock = MockSideEffects::new();
+    mock.set_stdin("one\ntwo");
+    assert_eq!(
+        run_mocked("read_line! read_line! read_line!", &mock),
+        vec![s("one\n"), s("two"), s("")]
+    );
+}
+
+#[test]
+fn read_all_stdin_reads_everything() {
+    let mock = MockSideEffects::new();
+    mock.set_stdin("a\nb\n");
+    assert_eq!(run_mocked("read_all_stdin!", &mock), vec![s("a\nb\n")]);
+}
+
+// ── Files ─────────────────────────────────────────────────────────────────────
+
+#[test]
+fn write_append_and_read_file() {
+    let mock = MockSideEffects::new();
+    assert_eq!(
+        run_mocked(
+            r#""/d/f.txt" "ab" write_file!
+            "/d/f.txt" "cd" append_file!
+            "/d/f.txt" read_file!
+            "/d/f.txt" exists! "/d/nope" exists!
+            "/d" list_dir!"#,
+            &mock
+        ),
+        vec![
+            s("abcd"),
+            DataValue::Bool(true),
+            DataValue::Bool(false),
+            DataValue::List(vec![s("f.txt")]),
+        ]
+    );
+    assert_eq!(mock.file("/d/f.txt"), Some(b"abcd".to_vec()));
+}
+
+#[test]
+fn mock_directories_are_implied_by_their_files() {
+    let mock = MockSideEffects::new();
+    mock.set_file("/d/b.txt", "");
+    mock.set_file("/d/a/inner.txt", "");
+    assert_eq!(
+        run_mocked(r#""/d" list_dir! "/d/a" exists!"#, &mock),
+        vec![
+            DataValue::List(vec![s("a"), s("b.txt")]),
+            DataValue::Bool(true)
+        ]
+    );
+}
+
+#[test]
+fn missing_file_is_an_io_error() {
+    let err = run_with(r#""/nope" read_file!"#, Box::new(MockSideEffects::new())).unwrap_err();
+    assert!(matches!(err.unlocated(), SidError::Io(_)), "{:?}", err);
+    assert!(err.message().starts_with("read_file: /nope"), "{}", err);
+}
+
+#[test]
+fn real_files_round_trip() {
+    let dir = std::env::temp_dir().join(format!("sid-io-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("out.txt");
+    let source = format!(
+        r#""{f}" "x" write_file! "{f}" "y" append_file! "{f}" read_file! "{d}" list_dir!"#,
+        f = file.display(),
+        d = dir.display()
+    );
+    let result = run_with(&source, Box::new(RealSideEffects));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        result.unwrap(),
+        vec![s("xy"), DataValue::List(vec![s("out.txt")])]
+    );
+}
@@ -0,0 +1,165 @@
+use sid::*;
+/// Tests for `Limits`: fuel, stack depth and deadline bounds stop a program
+/// with `SidError::LimitExceeded`, and raising the limit lets it resume.
+use std::collections::HashMap;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Compile `source` and return an interpreter state ready to run it, with
+/// `limits` installed and `effects` as its side effects.
+fn start<'a>(
+    source: &str,
+    global_scope: &'a mut HashMap<String, DataValue>,
+    builtins: &'a HashMap<String, BuiltinEntry>,
+    effects: &MockSideEffects,
+    limits: Limits,
+) -> ExeState<'a> {
+    let (parsed, _, spans) = parse_str_spanned(source).expect("parse error");
+    let comptime_builtins = get_comptime_builtins();
+    let (after_comptime, spans) = comptime_pass_spanned(
+        parsed,
+        spans,
+        &comptime_builtins,
+        global_scope,
+        SandboxPolicy::default(),
+    )
+    .expect("comptime error");
+    let mut global_state = GlobalState::with_side_effects(global_scope, Box::new(effects.clone()));
+    let rendered = render_template(
+        Template::substack((after_comptime, 0)).with_spans(spans),
+        &mut vec![],
+        &HashMap::new(),
+        &mut global_state,
+        &comptime_builtins,
+    )
+    .expect("render error");
+    global_state.limits = limits;
+    ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: vec![TemplateValue::from(rendered)],
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins,
+    }
+}
+
+fn ints(exe_state: &ExeState) -> Vec<i64> {
+    exe_state
+        .data_stack
+        .iter()
+        .map(|tv| match tv {
+            TemplateValue::Literal(ProgramValue::Data(DataValue::Int(i))) => *i,
+            other => panic!("expected an Int, got {:?}", other),
+        })
+        .collect()
+}
+
+fn limit_message(result: Result<(), SidError>) -> String {
+    let err = result.expect_err("expected a limit to be hit");
+    assert!(
+        matches!(err.unlocated(), SidError::LimitExceeded(_)),
+        "{:?}",
+        err
+    );
+    err.message().to_owned()
+}
+
+const COUNT_TO_50: &str = "0 (clone! 50 eq! not!) (1 add!) while_do!";
+
+#[test]
+fn fuel_stops_a_runaway_loop() {
+    let mut scope = default_scope();
+    let builtins = get_interpret_builtins();
+    let limits = Limits {
+        fuel: Some(1000),
+        ..Limits::default()
+    };
+    let mock = MockSideEffects::new();
+    let mut state = start("(true) () while_do!", &mut scope, &builtins, &mock, limits);
+    assert_eq!(limit_message(state.run()), "out of fuel");
+    assert_eq!(state.global_state.limits.fuel, Some(0));
+}
+
+#[test]
+fn refuelling_resumes_where_the_program_stopped() {
+    let mut scope = default_scope();
+    let builtins = get_interpret_builtins();
+    let limits = Limits {
+        fuel: Some(50),
+        ..Limits::default()
+    };
+    let mock = MockSideEffects::new();
+    let mut state = start(COUNT_TO_50, &mut scope, &builtins, &mock, limits);
+    let mut refuels = 0;
+    while state.run().is_err() {
+        state.global_state.limits.fuel = Some(50);
+        refuels += 1;
+    }
+    assert!(refuels > 0);
+    assert_eq!(ints(&state), vec![50]);
+}
+
+#[test]
+fn unlimited_by_default() {
+    let mut scope = default_scope();
+    let builtins = get_interpret_builtins();
+    let mock = MockSideEffects::new();
+    let mut state = start(COUNT_TO_50, &mut scope, &builtins, &mock, Limits::default());
+    state.run().unwrap();
+    assert_eq!(ints(&state), vec![50]);
+}
+
+#[test]
+fn data_stack_depth_is_bounded() {
+    let mut scope = default_scope();
+    let builtins = get_interpret_builtins();
+    let limits = Limits {
+        max_data_stack: Some(3),
+        ..Limits::default()
+    };
+    let mock = MockSideEffects::new();
+    let mut state = start("1 2 3 4 5", &mut scope, &builtins, &mock, limits);
+    assert_eq!(
+        limit_message(state.run()),
+        "data stack holds 4 values, more than the limit of 3"
+    );
+    assert_eq!(ints(&state), vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn program_stack_depth_is_bounded() {
+    let mut scope = default_scope();
+    let builtins = get_interpret_builtins();
+    let limits = Limits {
+        max_program_stack: Some(10),
+        ..Limits::default()
+    };
+    let mock = MockSideEffects::new();
+    let source = "(1 drop! 1 drop! 1 drop! 1 drop! 1 drop! 1 drop!)!";
+    let mut state = start(source, &mut scope, &builtins, &mock, limits);
+    let message = limit_message(state.run());
+    assert!(message.starts_with("program stack holds"), "{}", message);
+    state.global_state.limits.max_program_stack = None;
+    state.run().unwrap();
+    assert_eq!(ints(&state), vec![]);
+}
+
+#[test]
+fn deadline_uses_the_side_effects_clock() {
+    let mut scope = default_scope();
+    let builtins = get_interpret_builtins();
+    let mock = MockSideEffects::new();
+    mock.set_now(UNIX_EPOCH + Duration::from_secs(100));
+    let limits = Limits {
+        fuel: Some(100),
+        ..Limits::default()
+    };
+    let mut state = start("(true) () while_do!", &mut scope, &builtins, &mock, limits);
+    state.global_state.set_timeout(Duration::from_secs(5));
+    // The mock clock stands still, so only the fuel runs out.
+    assert_eq!(limit_message(state.run()), "out of fuel");
+
+    mock.set_now(UNIX_EPOCH + Duration::from_secs(105));
+    state.global_state.limits.fuel = None;
+    assert_eq!(limit_message(state.run()), "deadline passed");
+}
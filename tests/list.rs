@@ -0,0 +1,332 @@
+use sid::*;
+/// Tests for the list built-ins.
+///
+/// The higher-order ones (`map`, `filter`, `fold`, `each`, `any`, `all`,
+/// `sort_by`) schedule their function on the program stack, so these also
+/// check that the data stack below the list is left alone.
+use std::collections::HashMap;
+
+fn run_snippet(source: &str) -> Vec<DataValue> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered: DataValue = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
+    let builtins = get_interpret_builtins();
+    let mut global_scope_for_run = global_scope;
+    let global_state = GlobalState::new(&mut global_scope_for_run);
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: instructions,
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn ints(v: &[i64]) -> DataValue {
+    DataValue::List(v.iter().copied().map(DataValue::Int).collect())
+}
+
+fn s(v: &str) -> DataValue {
+    DataValue::Str(std::ffi::CString::new(v).unwrap())
+}
+
+// ── Basic operations ──────────────────────────────────────────────────────────
+
+#[test]
+fn len_concat_and_slice_work_on_lists() {
+    assert_eq!(run_snippet("[1 2 3] len!"), vec![DataValue::Int(3)]);
+    assert_eq!(run_snippet("[] len!"), vec![DataValue::Int(0)]);
+    assert_eq!(run_snippet("[1 2] [3] concat!"), vec![ints(&[1, 2, 3])]);
+    assert_eq!(run_snippet("[1 2 3 4] 1 3 slice!"), vec![ints(&[2, 3])]);
+    assert_eq!(run_snippet("[1 2 3] 3 3 slice!"), vec![ints(&[])]);
+}
+
+#[test]
+fn len_concat_and_slice_still_work_on_strings() {
+    assert_eq!(run_snippet(r#""abc" len!"#), vec![DataValue::Int(3)]);
+    assert_eq!(run_snippet(r#""ab" "c" concat!"#), vec![s("abc")]);
+    assert_eq!(run_snippet(r#""abc" 1 2 slice!"#), vec![s("b")]);
+}
+
+#[test]
+fn get_and_set_by_index() {
+    assert_eq!(
+        run_snippet("[10 20 30] 1 list_get!"),
+        vec![DataValue::Int(20)]
+    );
+    assert_eq!(
+        run_snippet("[10 20 30] 2 99 list_set!"),
+        vec![ints(&[10, 20, 99])]
+    );
+}
+
+#[test]
+fn push_and_pop() {
+    assert_eq!(run_snippet("[1] 2 push!"), vec![ints(&[1, 2])]);
+    assert_eq!(
+        run_snippet("[1 2 3] pop!"),
+        vec![ints(&[1, 2]), DataValue::Int(3)]
+    );
+}
+
+#[test]
+fn reverse_and_sort() {
+    assert_eq!(run_snippet("[1 2 3] reverse!"), vec![ints(&[3, 2, 1])]);
+    assert_eq!(run_snippet("[3 1 2 1] sort!"), vec![ints(&[1, 1, 2, 3])]);
+    assert_eq!(
+        run_snippet(r#"["b" "c" "a"] sort!"#),
+        vec![DataValue::List(vec![s("a"), s("b"), s("c")])]
+    );
+    assert_eq!(
+        run_snippet("[2 0.5 1] sort!"),
+        vec![DataValue::List(vec![
+            DataValue::Float(0.5),
+            DataValue::Int(1),
+            DataValue::Int(2)
+        ])]
+    );
+}
+
+#[test]
+fn range_is_half_open() {
+    assert_eq!(run_snippet("0 4 range!"), vec![ints(&[0, 1, 2, 3])]);
+    assert_eq!(run_snippet("-1 1 range!"), vec![ints(&[-1, 0])]);
+    assert_eq!(run_snippet("3 3 range!"), vec![ints(&[])]);
+    assert_eq!(run_snippet("3 0 range!"), vec![ints(&[])]);
+}
+
+/// Everything but the higher-order built-ins works at comptime.
+#[test]
+fn comptime_list_ops() {
+    assert_eq!(
+        run_snippet("0 3 range @! reverse @!"),
+        vec![ints(&[2, 1, 0])]
+    );
+}
+
+// ── Higher-order ──────────────────────────────────────────────────────────────
+
+#[test]
+fn map_applies_function_to_each_element() {
+    assert_eq!(run_snippet("[1 2 3] (2 mul!) map!"), vec![ints(&[2, 4, 6])]);
+    assert_eq!(run_snippet("[] (2 mul!) map!"), vec![ints(&[])]);
+}
+
+#[test]
+fn map_accepts_a_builtin_by_name() {
+    assert_eq!(run_snippet("[1 -2] neg map!"), vec![ints(&[-1, 2])]);
+}
+
+#[test]
+fn filter_keeps_matching_elements() {
+    assert_eq!(
+        run_snippet("0 10 range! (2 mod! 0 eq!) filter!"),
+        vec![ints(&[0, 2, 4, 6, 8])]
+    );
+}
+
+#[test]
+fn fold_threads_accumulator() {
+    assert_eq!(
+        run_snippet("[1 2 3 4] 0 (add!) fold!"),
+        vec![DataValue::Int(10)]
+    );
+    assert_eq!(
+        run_snippet("[1 2 3] [] (push!) fold!"),
+        vec![ints(&[1, 2, 3])]
+    );
+    assert_eq!(run_snippet("[] 7 (add!) fold!"), vec![DataValue::Int(7)]);
+}
+
+#[test]
+fn each_runs_function_and_keeps_nothing() {
+    assert_eq!(
+        run_snippet(r#""keep" [1 2 3] (drop!) each!"#),
+        vec![s("keep")]
+    );
+    assert_eq!(run_snippet("[true true] (assert!) each!"), vec![]);
+}
+
+#[test]
+#[should_panic(expected = "assert")]
+fn each_visits_every_element() {
+    run_snippet("[true false] (assert!) each!");
+}
+
+#[test]
+fn any_and_all_short_circuit() {
+    assert_eq!(
+        run_snippet("[1 2 3] (2 eq!) any! [1 2 3] (5 eq!) any! [] (drop! false) any!"),
+        vec![
+            DataValue::Bool(true),
+            DataValue::Bool(false),
+            DataValue::Bool(false)
+        ]
+    );
+    assert_eq!(
+        run_snippet("[1 2 3] (0 gt!) all! [1 2 3] (2 lt!) all! [] (drop! false) all!"),
+        vec![
+            DataValue::Bool(true),
+            DataValue::Bool(false),
+            DataValue::Bool(true)
+        ]
+    );
+    // The element after the deciding one is never visited: `assert` would fail.
+    assert_eq!(
+        run_snippet("[true false] (clone! assert!) any!"),
+        vec![DataValue::Bool(true)]
+    );
+}
+
+#[test]
+fn sort_by_uses_comparator() {
+    assert_eq!(
+        run_snippet("[3 1 4 1 5 9 2 6] (gt!) sort_by!"),
+        vec![ints(&[9, 6, 5, 4, 3, 2, 1, 1])]
+    );
+    assert_eq!(run_snippet("[] (lt!) sort_by!"), vec![ints(&[])]);
+}
+
+/// Elements the comparator considers equal keep their order.
+#[test]
+fn sort_by_is_stable() {
+    assert_eq!(
+        run_snippet(
+            r#"["bb" "a" "cc" "b"]
+            {a: $types.str, b: $types.str} (a len! b len! lt!) typed_args!
+            sort_by!"#
+        ),
+        vec![DataValue::List(vec![s("a"), s("b"), s("bb"), s("cc")])]
+    );
+}
+
+#[test]
+fn higher_order_leaves_stack_below_untouched() {
+    assert_eq!(
+        run_snippet(r#""below" [1 2] (1 add!) map!"#),
+        vec![s("below"), ints(&[2, 3])]
+    );
+}
+
+#[test]
+fn higher_order_calls_nest() {
+    assert_eq!(
+        run_snippet("[[1 2] [3]] ((10 mul!) map!) map!"),
+        vec![DataValue::List(vec![ints(&[10, 20]), ints(&[30])])]
+    );
+}
+
+// ── Error cases ───────────────────────────────────────────────────────────────
+
+#[test]
+#[should_panic(expected = "list_get: index 3 is out of bounds")]
+fn get_out_of_bounds_errors() {
+    run_snippet("[1 2 3] 3 list_get!");
+}
+
+#[test]
+#[should_panic(expected = "list_set: index -1 is out of bounds")]
+fn set_negative_index_errors() {
+    run_snippet("[1 2 3] -1 0 list_set!");
+}
+
+#[test]
+#[should_panic(expected = "pop: list is empty")]
+fn pop_empty_errors() {
+    run_snippet("[] pop!");
+}
+
+#[test]
+#[should_panic(expected = "slice: start 2 is after end 1")]
+fn slice_reversed_range_errors() {
+    run_snippet("[1 2 3] 2 1 slice!");
+}
+
+#[test]
+#[should_panic(expected = "range: 0..9223372036854775807 has more than")]
+fn huge_range_errors() {
+    run_snippet("0 9223372036854775807 range!");
+}
+
+#[test]
+#[should_panic(expected = "range: -9223372036854775807..9223372036854775807 has more than")]
+fn range_whose_length_overflows_errors() {
+    run_snippet("-9223372036854775807 9223372036854775807 range!");
+}
+
+#[test]
+#[should_panic(expected = "concat: expected two Strs or two Lists")]
+fn concat_mixed_errors() {
+    run_snippet(r#"[1] "a" concat!"#);
+}
+
+#[test]
+#[should_panic(expected = "sort: cannot compare")]
+fn sort_mixed_kinds_errors() {
+    run_snippet(r#"[1 "a"] sort!"#);
+}
+
+#[test]
+#[should_panic(expected = "map: function must leave exactly 1 value(s)")]
+fn map_function_leaving_two_values_errors() {
+    run_snippet("[1 2] (clone!) map!");
+}
+
+#[test]
+#[should_panic(expected = "each: function must leave exactly 0 value(s)")]
+fn each_function_leaving_a_value_errors() {
+    run_snippet("[1 2] () each!");
+}
+
+#[test]
+#[should_panic(expected = "filter: function must return a Bool")]
+fn filter_non_bool_errors() {
+    run_snippet("[1 2] () filter!");
+}
+
+/// Calls are scheduled rather than nested in Rust, so long lists don't grow
+/// the native stack.
+#[test]
+fn higher_order_handles_long_lists() {
+    assert_eq!(
+        run_snippet("0 3000 range! (1 add!) map! 0 (add!) fold!"),
+        vec![DataValue::Int(3000 * 3001 / 2)]
+    );
+}
@@ -0,0 +1,121 @@
+use sid::*;
+/// Tests for the boolean logic built-ins `and`, `or`, `xor` and the
+/// short-circuiting `and_then` / `or_else`.
+use std::collections::HashMap;
+
+fn run_snippet(source: &str) -> Vec<DataValue> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered: DataValue = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
+    let builtins = get_interpret_builtins();
+    let mut global_scope_for_run = global_scope;
+    let global_state = GlobalState::new(&mut global_scope_for_run);
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: instructions,
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn bools(values: &[bool]) -> Vec<DataValue> {
+    values.iter().map(|b| DataValue::Bool(*b)).collect()
+}
+
+#[test]
+fn strict_operators() {
+    assert_eq!(
+        run_snippet("true false and! true true and! false true or! false false or!"),
+        bools(&[false, true, true, false])
+    );
+    assert_eq!(
+        run_snippet("true false xor! true true xor!"),
+        bools(&[true, false])
+    );
+}
+
+#[test]
+fn and_then_runs_rhs_when_true() {
+    assert_eq!(run_snippet("true (1 2 lt!) and_then!"), bools(&[true]));
+    assert_eq!(run_snippet("true (false) and_then!"), bools(&[false]));
+}
+
+/// The right-hand side is never run when `and_then` short-circuits, so an
+/// erroring substack is harmless.
+#[test]
+fn and_then_short_circuits_on_false() {
+    assert_eq!(run_snippet("false (1 0 div!) and_then!"), bools(&[false]));
+}
+
+#[test]
+fn or_else_runs_rhs_when_false() {
+    assert_eq!(run_snippet("false (true) or_else!"), bools(&[true]));
+    assert_eq!(run_snippet("false (false) or_else!"), bools(&[false]));
+}
+
+#[test]
+fn or_else_short_circuits_on_true() {
+    assert_eq!(run_snippet("true (1 0 div!) or_else!"), bools(&[true]));
+}
+
+/// Combining conditions for a loop: count while below 5 and not equal to 3.
+#[test]
+fn combined_loop_condition() {
+    let stack = run_snippet(
+        "0 \
+         (clone! 5 lt! ($1 $2 $1)! 3 eq! not! and!) \
+         (1 add!) \
+         while_do !",
+    );
+    assert_eq!(stack, vec![DataValue::Int(3)]);
+}
+
+#[test]
+#[should_panic(expected = "and_then right-hand side ret check failed")]
+fn and_then_rhs_must_leave_bool() {
+    run_snippet("true (42) and_then!");
+}
+
+#[test]
+#[should_panic(expected = "or_else: right-hand side must be a Substack")]
+fn or_else_rhs_must_be_substack() {
+    run_snippet("false true or_else!");
+}
@@ -0,0 +1,237 @@
+use serde_json::{json, Value};
+/// Tests for `sid lsp`, driven by a scripted client: the document is opened,
+/// every request is written up front and the responses and notifications are
+/// checked afterwards.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+
+const URI: &str = "file:///test.sid";
+
+const PROGRAM: &str = "\
+double (
+  2 mul!
+) local!
+{scale: 10, offset: 1} load_scope!
+3 double! types.int print!
+";
+
+/// Open `source`, send `requests` (with ids from 1), shut down and return
+/// every message the server sent back.
+fn session(source: &str, requests: &[Value]) -> Vec<Value> {
+    let mut messages = vec![
+        json!({ "id": 0, "method": "initialize", "params": { "capabilities": {} } }),
+        json!({ "method": "initialized", "params": {} }),
+        json!({
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": { "uri": URI, "languageId": "sid", "version": 1, "text": source },
+            },
+        }),
+    ];
+    for (i, request) in requests.iter().enumerate() {
+        let mut request = request.clone();
+        request["id"] = json!(i + 1);
+        messages.push(request);
+    }
+    messages.push(json!({ "id": requests.len() + 1, "method": "shutdown" }));
+    messages.push(json!({ "method": "exit" }));
+
+    let mut input = Vec::new();
+    for mut message in messages {
+        message["jsonrpc"] = json!("2.0");
+        let body = message.to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sid"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut received = Vec::new();
+    loop {
+        let mut header = String::new();
+        if stdout.read_line(&mut header).unwrap() == 0 {
+            break;
+        }
+        let length: usize = header
+            .trim()
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        stdout.read_line(&mut String::new()).unwrap();
+        let mut body = vec![0; length];
+        stdout.read_exact(&mut body).unwrap();
+        received.push(serde_json::from_slice(&body).unwrap());
+    }
+    assert!(child.wait().unwrap().success());
+    received
+}
+
+/// A request about the position `line`:`character` of the test document.
+fn at(method: &str, line: usize, character: usize) -> Value {
+    json!({
+        "method": method,
+        "params": {
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        },
+    })
+}
+
+fn result(messages: &[Value], id: i64) -> &Value {
+    &messages
+        .iter()
+        .find(|m| m["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {}", id))["result"]
+}
+
+fn diagnostics(messages: &[Value]) -> &Vec<Value> {
+    messages
+        .iter()
+        .find(|m| m["method"] == "textDocument/publishDiagnostics")
+        .expect("no diagnostics published")["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+}
+
+#[test]
+fn initialize_advertises_the_supported_features() {
+    let messages = session(PROGRAM, &[]);
+    let capabilities = &result(&messages, 0)["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(capabilities["documentSymbolProvider"], true);
+    assert!(capabilities["completionProvider"].is_object());
+    assert!(diagnostics(&messages).is_empty());
+}
+
+#[test]
+fn errors_are_published_as_diagnostics() {
+    let messages = session("(1 2", &[]);
+    let published = diagnostics(&messages);
+    assert_eq!(published.len(), 1);
+    let message = published[0]["message"].as_str().unwrap();
+    assert!(message.contains("unexpected end of input"), "{}", message);
+
+    // Comptime errors are located at the `name @!` that raised them.
+    let messages = session("1\n1 \"a\" add @!", &[]);
+    let published = diagnostics(&messages);
+    assert_eq!(published.len(), 1);
+    assert_eq!(
+        published[0]["range"]["start"],
+        json!({ "line": 1, "character": 6 })
+    );
+}
+
+#[test]
+fn hover_shows_bindings_scope_values_and_builtin_signatures() {
+    let messages = session(
+        PROGRAM,
+        &[
+            at("textDocument/hover", 4, 3),
+            at("textDocument/hover", 4, 21),
+            at("textDocument/hover", 4, 12),
+            at("textDocument/hover", 4, 0),
+        ],
+    );
+    let text = |id| result(&messages, id)["contents"]["value"].as_str().unwrap();
+    assert_eq!(text(1), "`double`, bound at 1:1");
+    assert_eq!(
+        result(&messages, 1)["range"],
+        json!({ "start": { "line": 4, "character": 2 }, "end": { "line": 4, "character": 8 } })
+    );
+    assert!(text(2).contains("print ("), "{}", text(2));
+    assert!(text(2).contains("built-in"), "{}", text(2));
+    assert!(text(3).contains("types.int = "), "{}", text(3));
+    // `3` is not a label.
+    assert_eq!(result(&messages, 4), &Value::Null);
+}
+
+#[test]
+fn definition_finds_local_and_load_scope_bindings() {
+    let source = format!("{}scale offset add!\n", PROGRAM);
+    let messages = session(
+        &source,
+        &[
+            at("textDocument/definition", 4, 4),
+            at("textDocument/definition", 5, 1),
+            at("textDocument/definition", 4, 23),
+        ],
+    );
+    assert_eq!(
+        result(&messages, 1)["range"],
+        json!({ "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 6 } })
+    );
+    assert_eq!(result(&messages, 1)["uri"], URI);
+    assert_eq!(
+        result(&messages, 2)["range"]["start"],
+        json!({ "line": 3, "character": 0 })
+    );
+    // Built-ins are not defined in the document.
+    assert_eq!(result(&messages, 3), &Value::Null);
+}
+
+#[test]
+fn completion_offers_builtins_scope_and_bindings() {
+    let messages = session(PROGRAM, &[at("textDocument/completion", 4, 0)]);
+    let items = result(&messages, 1).as_array().unwrap();
+    let item = |label: &str| {
+        items
+            .iter()
+            .find(|i| i["label"] == label)
+            .unwrap_or_else(|| panic!("no completion '{}'", label))
+    };
+    assert!(item("print")["detail"]
+        .as_str()
+        .unwrap()
+        .starts_with("print ("));
+    assert_eq!(item("print")["kind"], 3);
+    assert_eq!(item("types")["kind"], 6);
+    assert_eq!(item("double")["kind"], 3);
+    assert_eq!(item("scale")["kind"], 6);
+}
+
+#[test]
+fn document_symbols_nest_bindings_made_inside_functions() {
+    let source = "outer (\n  x 1 local!\n) local!\n{scale: 10} load_scope!\n";
+    let messages = session(
+        source,
+        &[json!({
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": URI } },
+        })],
+    );
+    let symbols = result(&messages, 1).as_array().unwrap();
+    let names: Vec<_> = symbols.iter().map(|s| &s["name"]).collect();
+    assert_eq!(names, ["outer", "scale"]);
+    assert_eq!(symbols[0]["kind"], 12);
+    assert_eq!(symbols[1]["kind"], 13);
+    let children = symbols[0]["children"].as_array().unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0]["name"], "x");
+    assert_eq!(
+        children[0]["selectionRange"]["start"],
+        json!({ "line": 1, "character": 2 })
+    );
+}
+
+#[test]
+fn documents_with_parse_errors_are_still_analysed() {
+    let source = "double (2 mul!) local!\n) 3 double! ]\n";
+    let messages = session(source, &[at("textDocument/definition", 1, 4)]);
+    let published = diagnostics(&messages);
+    assert_eq!(published.len(), 2);
+    assert_eq!(
+        published[1]["range"]["start"],
+        json!({ "line": 1, "character": 12 })
+    );
+    assert_eq!(
+        result(&messages, 1)["range"]["start"],
+        json!({ "line": 0, "character": 0 })
+    );
+}
@@ -0,0 +1,195 @@
+use sid::*;
+/// Tests for the map and struct built-ins.
+///
+/// Struct types match fields in order, so these check that every operation
+/// keeps the entry order of its input.
+use std::collections::HashMap;
+
+fn run_snippet(source: &str) -> Vec<DataValue> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered: DataValue = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
+    let builtins = get_interpret_builtins();
+    let mut global_scope_for_run = global_scope;
+    let global_state = GlobalState::new(&mut global_scope_for_run);
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: instructions,
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn ints(v: &[i64]) -> DataValue {
+    DataValue::List(v.iter().copied().map(DataValue::Int).collect())
+}
+
+fn label(name: &str) -> DataValue {
+    DataValue::Label(name.to_owned())
+}
+
+fn entries(pairs: &[(&str, i64)]) -> DataValue {
+    DataValue::Map(
+        pairs
+            .iter()
+            .map(|(k, v)| (label(k), DataValue::Int(*v)))
+            .collect(),
+    )
+}
+
+#[test]
+fn get_and_has() {
+    assert_eq!(
+        run_snippet("{x: 1, y: 2} y map_get!"),
+        vec![DataValue::Int(2)]
+    );
+    assert_eq!(
+        run_snippet("{x: 1, y: 2} x map_has! {x: 1} z map_has!"),
+        vec![DataValue::Bool(true), DataValue::Bool(false)]
+    );
+    assert_eq!(
+        run_snippet(r#"{:} 1 "one" map_insert! 1 map_get!"#),
+        vec![DataValue::Str(std::ffi::CString::new("one").unwrap())]
+    );
+}
+
+#[test]
+fn label_keys_are_not_resolved_through_scope() {
+    assert_eq!(
+        run_snippet("x 5 local! {x: 1, y: 2} x map_get!"),
+        vec![DataValue::Int(1)]
+    );
+    assert_eq!(
+        run_snippet("x 5 local! {x: 1} x map_has!"),
+        vec![DataValue::Bool(true)]
+    );
+    assert_eq!(
+        run_snippet("x 5 local! {x: 1} x 10 map_insert! x map_remove!"),
+        vec![entries(&[])]
+    );
+}
+
+#[test]
+#[should_panic(expected = "map_get: key")]
+fn get_missing_key_fails() {
+    run_snippet("{x: 1} y map_get!");
+}
+
+#[test]
+fn insert_replaces_in_place_and_appends_new_keys() {
+    assert_eq!(
+        run_snippet("{x: 1, y: 2, z: 3} x 10 map_insert!"),
+        vec![entries(&[("x", 10), ("y", 2), ("z", 3)])]
+    );
+    assert_eq!(
+        run_snippet("{x: 1} w 4 map_insert!"),
+        vec![entries(&[("x", 1), ("w", 4)])]
+    );
+}
+
+#[test]
+fn remove_keeps_remaining_order() {
+    assert_eq!(
+        run_snippet("{x: 1, y: 2, z: 3} y map_remove!"),
+        vec![entries(&[("x", 1), ("z", 3)])]
+    );
+    assert_eq!(
+        run_snippet("{x: 1} y map_remove!"),
+        vec![entries(&[("x", 1)])]
+    );
+}
+
+#[test]
+fn keys_values_and_entries_are_in_order() {
+    assert_eq!(
+        run_snippet("{b: 1, a: 2} keys!"),
+        vec![DataValue::List(vec![label("b"), label("a")])]
+    );
+    assert_eq!(run_snippet("{b: 1, a: 2} values!"), vec![ints(&[1, 2])]);
+    assert_eq!(
+        run_snippet("{b: 1, a: 2} entries!"),
+        vec![DataValue::List(vec![
+            DataValue::List(vec![label("b"), DataValue::Int(1)]),
+            DataValue::List(vec![label("a"), DataValue::Int(2)]),
+        ])]
+    );
+    assert_eq!(run_snippet("{:} keys!"), vec![ints(&[])]);
+}
+
+#[test]
+fn merge_right_wins_and_keeps_left_order() {
+    assert_eq!(
+        run_snippet("{x: 1, y: 2} {z: 3, x: 4} merge!"),
+        vec![entries(&[("x", 4), ("y", 2), ("z", 3)])]
+    );
+}
+
+#[test]
+fn with_field_updates_a_struct() {
+    assert_eq!(
+        run_snippet("{x: 1, y: 2} x 5 with_field!"),
+        vec![entries(&[("x", 5), ("y", 2)])]
+    );
+}
+
+#[test]
+fn updated_struct_still_matches_its_type() {
+    assert_eq!(
+        run_snippet(
+            "{x: 1, y: 2} y 5 with_field!
+            {p: $types.any {x: $types.int, y: $types.int} require!}
+            (p y map_get!) typed_args! !"
+        ),
+        vec![DataValue::Int(5)]
+    );
+}
+
+#[test]
+#[should_panic(expected = "with_field: struct has no field")]
+fn with_field_cannot_add_fields() {
+    run_snippet("{x: 1, y: 2} z 5 with_field!");
+}
+
+#[test]
+#[should_panic(expected = "expected Map")]
+fn non_map_is_a_type_mismatch() {
+    run_snippet("[1 2] keys!");
+}
@@ -0,0 +1,100 @@
+use sid::*;
+/// Tests for `DataValue::Opaque`: host objects pass through sid code, match
+/// their registered type, and downcast safely in host built-ins.
+use std::sync::Mutex;
+
+struct Counter(Mutex<i64>);
+
+/// An engine with a `Counter` type, a `new_counter` built-in that makes one,
+/// and a `bump` built-in that increments one and returns its new value.
+fn counter_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.register_opaque_type("Counter");
+    engine.register_builtin(
+        "new_counter",
+        vec![],
+        vec![SidType::Opaque("Counter".to_owned())],
+        |_state, _args| Ok(vec![Opaque::new("Counter", Counter(Mutex::new(0))).into()]),
+    );
+    engine.register_builtin(
+        "bump",
+        vec![SidType::Opaque("Counter".to_owned())],
+        vec![SidType::Int],
+        |_state, args| {
+            let opaque = Opaque::try_from(args[0].clone())?;
+            let counter = opaque
+                .downcast_ref::<Counter>()
+                .ok_or_else(|| SidError::TypeMismatch("bump: not a Rust Counter".to_owned()))?;
+            let mut n = counter.0.lock().unwrap();
+            *n += 1;
+            Ok(vec![DataValue::Int(*n)])
+        },
+    );
+    engine
+}
+
+#[test]
+fn host_objects_round_trip_through_sid_code() {
+    let mut engine = counter_engine();
+    engine
+        .eval_str("c new_counter! local! c bump! drop! c bump!")
+        .unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(2)));
+}
+
+#[test]
+fn opaque_type_checks_args() {
+    let mut engine = counter_engine();
+    let err = engine.eval_str("5 bump!").unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("builtin 'bump': arg 0 expected Opaque(\"Counter\")"),
+        "{}",
+        err
+    );
+
+    // A host object of another type doesn't pass for a Counter.
+    engine.set_global("other", Opaque::new("Other", 1u8));
+    assert!(engine.eval_str("other bump!").is_err());
+}
+
+#[test]
+fn opaque_type_works_in_typed_args_and_match() {
+    let mut engine = counter_engine();
+    engine
+        .eval_str("new_counter! {c: $types.Counter} (c bump!) typed_args! !")
+        .unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(1)));
+
+    engine
+        .eval_str("(new_counter! {$types.int: (1), $types.Counter: (2)} match!)!")
+        .unwrap();
+    assert_eq!(engine.pop().unwrap(), Some(DataValue::Int(2)));
+}
+
+#[test]
+fn opaque_syntax_names_the_type() {
+    let value = DataValue::Opaque(Opaque::new("Socket", ()));
+    assert_eq!(value.to_syntax(), "<Opaque Socket>");
+    assert_eq!(
+        SidType::Opaque("Socket".to_owned()).to_syntax(),
+        "types.Socket"
+    );
+}
+
+#[test]
+fn downcast_checks_the_rust_type() {
+    let opaque = Opaque::new("Number", 7i32);
+    assert_eq!(opaque.downcast_ref::<i32>(), Some(&7));
+    assert_eq!(opaque.downcast_ref::<i64>(), None);
+    assert_eq!(opaque.downcast::<i32>().as_deref(), Some(&7));
+    assert!(opaque.downcast::<String>().is_none());
+}
+
+#[test]
+fn opaque_equality_is_identity() {
+    let a = Opaque::new("Number", 7i32);
+    let b = Opaque::new("Number", 7i32);
+    assert_eq!(a, a.clone());
+    assert_ne!(a, b);
+}
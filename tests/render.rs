@@ -20,6 +20,7 @@ pub fn render_test_fixture(
             &mut gs,
             &builtins,
         )
+        .unwrap_or_else(|e| panic!("{}", e))
     };
     // Verify remaining parent stack
     assert_eq!(
@@ -50,6 +51,7 @@ fn render_empty_substack() {
             body: vec![],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         },
     )
 }
@@ -82,10 +84,11 @@ fn render_substack() {
                 DataValue::Int(2).into(),
                 DataValue::Int(1).into(),
                 DataValue::Label("add".to_string()).into(),
-                ProgramValue::Invoke.into(),
+                ProgramValue::Invoke,
             ],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         },
     )
 }
@@ -173,6 +176,7 @@ fn render_script() {
             body: vec![ProgramValue::Data(DataValue::Int(42))],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         },
     )
 }
@@ -203,6 +207,7 @@ fn render_substack_dollar_one_twice() {
             body: vec![DataValue::Int(7).into(), DataValue::Int(7).into()],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         },
     )
 }
@@ -228,6 +233,7 @@ fn render_substack_dollar_two_drops_first() {
             body: vec![DataValue::Int(2).into()],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         },
     )
 }
@@ -249,6 +255,7 @@ fn render_substack_dollar_three_drops_first_two() {
             body: vec![DataValue::Int(3).into()],
             args: None,
             ret: None,
+            spans: SpanTable::default(),
         },
     )
 }
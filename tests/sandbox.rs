@@ -0,0 +1,167 @@
+use sid::*;
+/// Tests for `SandboxPolicy`: denied capabilities fail with
+/// `SidError::PermissionDenied` at comptime and at runtime, and everything
+/// else keeps working.
+use std::collections::HashMap;
+
+fn run_sandboxed(
+    source: &str,
+    sandbox: SandboxPolicy,
+    mut global_scope: HashMap<String, DataValue>,
+    effects: &MockSideEffects,
+) -> Result<Vec<DataValue>, SidError> {
+    let (parsed, _, spans) = parse_str_spanned(source).expect("parse error");
+    let comptime_builtins = get_comptime_builtins();
+    let (after_comptime, spans) = comptime_pass_spanned(
+        parsed,
+        spans,
+        &comptime_builtins,
+        &mut global_scope,
+        sandbox,
+    )
+    .map_err(|e| match e.downcast::<SidError>() {
+        Ok(e) => e,
+        Err(e) => panic!("comptime error: {}", e),
+    })?;
+    let mut global_state =
+        GlobalState::with_side_effects(&mut global_scope, Box::new(effects.clone()));
+    global_state.sandbox = sandbox;
+    let rendered = render_template(
+        Template::substack((after_comptime, 0)).with_spans(spans),
+        &mut vec![],
+        &HashMap::new(),
+        &mut global_state,
+        &comptime_builtins,
+    )?;
+    let builtins = get_interpret_builtins();
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: vec![TemplateValue::from(rendered)],
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )?;
+    }
+    Ok(exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+fn denied(source: &str, sandbox: SandboxPolicy) -> SidError {
+    let mock = MockSideEffects::new();
+    mock.set_file("/f.txt", "data");
+    mock.set_env("HOME", "/home/sid");
+    let err = run_sandboxed(source, sandbox, default_scope(), &mock).unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::PermissionDenied(_)),
+        "{:?}",
+        err
+    );
+    err
+}
+
+#[test]
+fn filesystem_is_denied() {
+    let err = denied(r#""/f.txt" read_file!"#, SandboxPolicy::deny_all());
+    assert_eq!(
+        err.message(),
+        "read_file: filesystem access is not allowed by the sandbox policy"
+    );
+    denied(r#""/f.txt" "x" write_file!"#, SandboxPolicy::deny_all());
+    denied(r#""/f.txt" "x" append_file!"#, SandboxPolicy::deny_all());
+    denied(r#""/f.txt" exists!"#, SandboxPolicy::deny_all());
+    denied(r#""/" list_dir!"#, SandboxPolicy::deny_all());
+}
+
+#[test]
+fn denied_write_has_no_effect() {
+    let mock = MockSideEffects::new();
+    let result = run_sandboxed(
+        r#""/f.txt" "x" write_file!"#,
+        SandboxPolicy::deny_all(),
+        default_scope(),
+        &mock,
+    );
+    assert!(result.is_err());
+    assert_eq!(mock.file("/f.txt"), None);
+}
+
+#[test]
+fn env_and_pointers_are_denied() {
+    denied(r#""HOME" env_var!"#, SandboxPolicy::deny_all());
+    denied("types.null types.int ptr_cast!", SandboxPolicy::deny_all());
+    denied("types.null ptr_read_cstr!", SandboxPolicy::deny_all());
+}
+
+#[test]
+fn ffi_is_denied_at_comptime_and_runtime() {
+    let err = denied(
+        r#""tests/fixtures/test.h" c_load_header @!"#,
+        SandboxPolicy::deny_all(),
+    );
+    assert!(err.message().starts_with("c_load_header: FFI"), "{}", err);
+    denied(r#""libm.so.6" c_link_lib!"#, SandboxPolicy::deny_all());
+}
+
+#[test]
+fn calling_a_c_function_is_denied() {
+    let header = format!("{}/tests/fixtures/test.h", env!("CARGO_MANIFEST_DIR"));
+    let sqrt = parse_c_header(&header, "libm.so.6")
+        .unwrap()
+        .into_iter()
+        .find(|s| s.name == "sqrt")
+        .unwrap();
+    let mut scope = default_scope();
+    scope.insert("sqrt".to_owned(), DataValue::CFuncSig(sqrt));
+    let err = run_sandboxed(
+        "4.0 sqrt!",
+        SandboxPolicy::deny_all(),
+        scope,
+        &MockSideEffects::new(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.message(),
+        "CFuncSig 'sqrt': FFI is not allowed by the sandbox policy"
+    );
+}
+
+#[test]
+fn allowed_capabilities_and_console_still_work() {
+    let mock = MockSideEffects::new();
+    mock.set_file("/f.txt", "data");
+    let sandbox = SandboxPolicy {
+        filesystem: true,
+        ..SandboxPolicy::deny_all()
+    };
+    let result = run_sandboxed(
+        r#""/f.txt" read_file! clone! println!"#,
+        sandbox,
+        default_scope(),
+        &mock,
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        vec![DataValue::Str(std::ffi::CString::new("data").unwrap())]
+    );
+    assert_eq!(mock.stdout_string(), "data\n");
+}
@@ -3,7 +3,7 @@ use sid::*;
 ///
 /// - `get`        : local → global priority, available at comptime and runtime.
 /// - `get_local`  : local scope only; errors if not found.  At comptime the
-///                  local scope is empty so it always errors.
+///   local scope is empty so it always errors.
 /// - `get_global` : global scope only, bypassing any local shadow.
 use std::collections::HashMap;
 
@@ -29,6 +29,7 @@ fn run_snippet_with_scope(source: &str, extra_scope: HashMap<String, DataValue>)
             &mut gs,
             &comptime_builtins,
         )
+        .unwrap_or_else(|e| panic!("{}", e))
     };
     let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
     let builtins = get_interpret_builtins();
@@ -50,7 +51,8 @@ fn run_snippet_with_scope(source: &str, extra_scope: HashMap<String, DataValue>)
             &mut exe_state.scope_stack,
             &mut exe_state.global_state,
             &builtins,
-        );
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
     }
     exe_state
         .data_stack
@@ -0,0 +1,180 @@
+use sid::*;
+/// Tests for the set built-ins and the `set`/`map` type constructors.
+///
+/// Sets keep the order in which items were first added and never hold two
+/// equal items.
+use std::collections::HashMap;
+
+fn run_snippet(source: &str) -> Vec<DataValue> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered: DataValue = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
+    let builtins = get_interpret_builtins();
+    let mut global_scope_for_run = global_scope;
+    let global_state = GlobalState::new(&mut global_scope_for_run);
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: instructions,
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn ints(v: &[i64]) -> DataValue {
+    DataValue::List(v.iter().copied().map(DataValue::Int).collect())
+}
+
+fn set(v: &[i64]) -> DataValue {
+    DataValue::Set(v.iter().copied().map(DataValue::Int).collect())
+}
+
+#[test]
+fn set_literals_deduplicate() {
+    assert_eq!(run_snippet("{1, 2, 1, 3, 2}"), vec![set(&[1, 2, 3])]);
+}
+
+#[test]
+fn union_intersection_and_difference() {
+    assert_eq!(
+        run_snippet("{1, 2, 3} {3, 4, 1} union!"),
+        vec![set(&[1, 2, 3, 4])]
+    );
+    assert_eq!(
+        run_snippet("{1, 2, 3} {3, 4, 1} intersection!"),
+        vec![set(&[1, 3])]
+    );
+    assert_eq!(
+        run_snippet("{1, 2, 3} {3, 4, 1} difference!"),
+        vec![set(&[2])]
+    );
+}
+
+#[test]
+fn contains_insert_and_remove() {
+    assert_eq!(
+        run_snippet("{1, 2} 2 contains! {1, 2} 3 contains!"),
+        vec![DataValue::Bool(true), DataValue::Bool(false)]
+    );
+    assert_eq!(run_snippet("{1, 2} 3 insert!"), vec![set(&[1, 2, 3])]);
+    assert_eq!(run_snippet("{1, 2} 1 insert!"), vec![set(&[1, 2])]);
+    assert_eq!(run_snippet("{1, 2, 3} 2 remove!"), vec![set(&[1, 3])]);
+    assert_eq!(run_snippet("{1, 2} 5 remove!"), vec![set(&[1, 2])]);
+}
+
+#[test]
+fn label_items_are_not_resolved_through_scope() {
+    let labels = |names: &[&str]| {
+        DataValue::Set(
+            names
+                .iter()
+                .map(|n| DataValue::Label((*n).to_owned()))
+                .collect(),
+        )
+    };
+    assert_eq!(
+        run_snippet("x 5 local! {x, y} x contains!"),
+        vec![DataValue::Bool(true)]
+    );
+    assert_eq!(
+        run_snippet("x 5 local! {y} x insert!"),
+        vec![labels(&["y", "x"])]
+    );
+    assert_eq!(
+        run_snippet("x 5 local! {x, y} x remove!"),
+        vec![labels(&["y"])]
+    );
+}
+
+#[test]
+fn structurally_equal_items_are_one_item() {
+    assert_eq!(
+        run_snippet("{[1 2]} [1 2] insert!"),
+        vec![DataValue::Set(vec![ints(&[1, 2])])]
+    );
+}
+
+#[test]
+#[should_panic(expected = "expected Set(Any), got List")]
+fn non_set_is_a_type_mismatch() {
+    run_snippet("{1} [2] union!");
+}
+
+// ── Type constructors ─────────────────────────────────────────────────────────
+
+#[test]
+fn set_type_constructor() {
+    assert_eq!(
+        run_snippet("@types.int set @!"),
+        vec![DataValue::Type(SidType::Set(Box::new(SidType::Int)))]
+    );
+    assert_eq!(
+        run_snippet("{2, 3} {s: $types.int set!} (s 3 contains!) typed_args! !"),
+        vec![DataValue::Bool(true)]
+    );
+}
+
+#[test]
+#[should_panic(expected = "substack args check failed")]
+fn set_type_rejects_wrong_elements() {
+    run_snippet(r#"{"a"} {s: $types.int set!} (s) typed_args! !"#);
+}
+
+#[test]
+fn map_type_constructor() {
+    assert_eq!(
+        run_snippet("@types.str @types.int map @!"),
+        vec![DataValue::Type(SidType::Map {
+            key: Box::new(SidType::Str),
+            value: Box::new(SidType::Int),
+        })]
+    );
+}
+
+#[test]
+#[should_panic(expected = "expected List")]
+fn map_type_constructor_is_comptime_only() {
+    run_snippet("types.str types.int map!");
+}
+
+#[test]
+fn runtime_map_still_maps_over_lists() {
+    assert_eq!(run_snippet("[1 2] (1 add!) map!"), vec![ints(&[2, 3])]);
+}
@@ -0,0 +1,115 @@
+use sid::*;
+/// Tests for the clock, randomness, environment and library loading side
+/// effects, all run against `MockSideEffects`.
+use std::collections::HashMap;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn run_with(source: &str, effects: Box<dyn SideEffects>) -> Result<Vec<DataValue>, SidError> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let mut global_state = GlobalState::with_side_effects(&mut global_scope, effects);
+    let rendered = render_template(
+        Template::substack((after_comptime, 0)),
+        &mut vec![],
+        &HashMap::new(),
+        &mut global_state,
+        &comptime_builtins,
+    )?;
+    let builtins = get_interpret_builtins();
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: vec![TemplateValue::from(rendered)],
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )?;
+    }
+    Ok(exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+fn run_mocked(source: &str, mock: &MockSideEffects) -> Vec<DataValue> {
+    run_with(source, Box::new(mock.clone())).unwrap_or_else(|e| panic!("{}", e))
+}
+
+#[test]
+fn now_reads_the_clock() {
+    let mock = MockSideEffects::new();
+    mock.set_now(UNIX_EPOCH + Duration::from_millis(1_700_000_000_123));
+    assert_eq!(
+        run_mocked("now!", &mock),
+        vec![DataValue::Int(1_700_000_000_123)]
+    );
+}
+
+#[test]
+fn random_is_in_unit_range_and_seeded() {
+    let mock = MockSideEffects::new();
+    mock.set_seed(42);
+    let first = run_mocked("random! random!", &mock);
+    mock.set_seed(42);
+    assert_eq!(run_mocked("random! random!", &mock), first);
+    assert_ne!(first[0], first[1]);
+    for v in first {
+        match v {
+            DataValue::Float(f) => assert!((0.0..1.0).contains(&f), "{}", f),
+            other => panic!("expected Float, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn env_var_reads_the_environment() {
+    let mock = MockSideEffects::new();
+    mock.set_env("GREETING", "hi");
+    assert_eq!(
+        run_mocked(r#""GREETING" env_var!"#, &mock),
+        vec![DataValue::Str(std::ffi::CString::new("hi").unwrap())]
+    );
+    let err = run_with(r#""MISSING" env_var!"#, Box::new(mock)).unwrap_err();
+    assert_eq!(err.message(), "env_var: MISSING is not set");
+}
+
+#[test]
+fn c_link_lib_goes_through_side_effects() {
+    let err = run_with(
+        r#""libm.so.6" c_link_lib!"#,
+        Box::new(MockSideEffects::new()),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err.unlocated(), SidError::FfiError(_)),
+        "{:?}",
+        err
+    );
+    assert!(err.message().contains("can't load libraries"), "{}", err);
+}
+
+#[test]
+fn debug_stack_writes_to_mocked_stderr() {
+    let mock = MockSideEffects::new();
+    run_mocked("0 debug_stack!", &mock);
+    assert_eq!(mock.stderr_string(), "=== debug_stack (top 0) ===\n");
+}
@@ -0,0 +1,96 @@
+use sid::*;
+
+use std::collections::HashMap;
+
+/// Run `source` through the spanned pipeline the `sid` binary uses and return
+/// the first error, if any.
+fn run_spanned(source: &str) -> Result<(), SidError> {
+    let (parsed, _, spans) = parse_str_spanned(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let (after_comptime, spans) = comptime_pass_spanned(
+        parsed,
+        spans,
+        &comptime_builtins,
+        &mut global_scope,
+        SandboxPolicy::default(),
+    )
+    .expect("comptime error");
+    let rendered = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)).with_spans(spans),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )?
+    };
+    let builtins = get_interpret_builtins();
+    interpret(
+        vec![ProgramValue::Invoke],
+        vec![TemplateValue::from(rendered)],
+        GlobalState::new(&mut global_scope),
+        &builtins,
+    )
+}
+
+#[test]
+fn builtin_error_is_located_at_invocation() {
+    let err = run_spanned("1 2 add !\n1 true add !").unwrap_err();
+    assert_eq!(err.span(), Some(Span::new(2, 8, 5)));
+    assert!(matches!(err.unlocated(), SidError::TypeMismatch(_)));
+}
+
+#[test]
+fn error_inside_substack_is_located_at_inner_instruction() {
+    let err = run_spanned("(\n  1 0 div !\n) !").unwrap_err();
+    assert_eq!(err.span(), Some(Span::new(2, 7, 5)));
+    assert_eq!(
+        err.unlocated(),
+        &SidError::Arithmetic("div: division by zero".to_owned())
+    );
+}
+
+#[test]
+fn ret_contract_failure_is_located_at_call_site() {
+    let err = run_spanned("[$types.int] (\"s\") typed_rets !\n!").unwrap_err();
+    assert_eq!(err.span(), Some(Span::new(2, 1, 1)));
+    assert!(matches!(err.unlocated(), SidError::TypeMismatch(_)));
+}
+
+#[test]
+fn comptime_results_take_the_span_of_their_invocation() {
+    let (parsed, _, spans) = parse_str_spanned("1 2 add @! 3").expect("parse error");
+    let (out, spans) = comptime_pass_spanned(
+        parsed,
+        spans,
+        &get_comptime_builtins(),
+        &mut default_scope(),
+        SandboxPolicy::default(),
+    )
+    .expect("comptime error");
+    assert_eq!(
+        out,
+        vec![DataValue::Int(3).into(), DataValue::Int(3).into()]
+    );
+    assert_eq!(
+        spans.0,
+        vec![Some(Span::new(1, 5, 6)), Some(Span::new(1, 12, 1))]
+    );
+}
+
+#[test]
+fn spans_do_not_affect_equality() {
+    // Same body, parsed at different locations.
+    assert_eq!(run_spanned("(1 2) (1\n2) eq ! assert !"), Ok(()));
+}
+
+#[test]
+fn snippet_underlines_span() {
+    let source = "1 2 add !\n1 true add !\n";
+    assert_eq!(
+        render_snippet("example.sid", source, Span::new(2, 8, 5)),
+        "  --> example.sid:2:8\n  |\n2 | 1 true add !\n  |        ^^^^^\n"
+    );
+}
@@ -0,0 +1,221 @@
+use sid::*;
+/// Tests for the string built-ins: `concat`, `len`, `grapheme_len`, `slice`,
+/// `split`, `join`, `find`, `replace`, `trim`, `upper`, `lower`,
+/// `starts_with`, `ends_with` and `chars`.
+///
+/// Offsets taken and returned by `slice` and `find` are byte offsets.
+use std::collections::HashMap;
+
+fn run_snippet(source: &str) -> Vec<DataValue> {
+    let parsed = parse_str(source).expect("parse error");
+    let mut global_scope = default_scope();
+    let comptime_builtins = get_comptime_builtins();
+    let after_comptime =
+        comptime_pass(parsed.0, &comptime_builtins, &mut global_scope).expect("comptime error");
+    let rendered: DataValue = {
+        let mut gs = GlobalState::new(&mut global_scope);
+        render_template(
+            Template::substack((after_comptime, 0)),
+            &mut vec![],
+            &HashMap::new(),
+            &mut gs,
+            &comptime_builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let instructions: Vec<TemplateValue> = vec![TemplateValue::from(rendered)];
+    let builtins = get_interpret_builtins();
+    let mut global_scope_for_run = global_scope;
+    let global_state = GlobalState::new(&mut global_scope_for_run);
+    let mut exe_state = ExeState {
+        program_stack: vec![ProgramValue::Invoke],
+        data_stack: instructions,
+        local_scope: HashMap::new(),
+        scope_stack: Vec::new(),
+        global_state,
+        builtins: &builtins,
+    };
+    while !exe_state.program_stack.is_empty() {
+        interpret_one(
+            &mut exe_state.data_stack,
+            &mut exe_state.program_stack,
+            &mut exe_state.local_scope,
+            &mut exe_state.scope_stack,
+            &mut exe_state.global_state,
+            &builtins,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    exe_state
+        .data_stack
+        .into_iter()
+        .filter_map(|tv| {
+            if let TemplateValue::Literal(ProgramValue::Data(v)) = tv {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn s(v: &str) -> DataValue {
+    DataValue::Str(std::ffi::CString::new(v).unwrap())
+}
+
+fn c(v: &str) -> DataValue {
+    DataValue::Char(v.to_owned())
+}
+
+// ── Building and measuring ────────────────────────────────────────────────────
+
+#[test]
+fn concat_joins_two_strings() {
+    assert_eq!(run_snippet(r#""foo" "bar" concat!"#), vec![s("foobar")]);
+    assert_eq!(run_snippet(r#""" "" concat!"#), vec![s("")]);
+}
+
+/// `len` counts bytes, `grapheme_len` user-perceived characters.
+#[test]
+fn len_counts_bytes_and_graphemes() {
+    assert_eq!(run_snippet(r#""héllo" len!"#), vec![DataValue::Int(6)]);
+    assert_eq!(
+        run_snippet(r#""héllo" grapheme_len!"#),
+        vec![DataValue::Int(5)]
+    );
+    assert_eq!(
+        run_snippet(r#""👮‍♀️" grapheme_len!"#),
+        vec![DataValue::Int(1)]
+    );
+}
+
+#[test]
+fn slice_takes_byte_range() {
+    assert_eq!(run_snippet(r#""hello" 1 4 slice!"#), vec![s("ell")]);
+    assert_eq!(run_snippet(r#""hello" 0 5 slice!"#), vec![s("hello")]);
+    assert_eq!(run_snippet(r#""hello" 2 2 slice!"#), vec![s("")]);
+}
+
+// ── Splitting and joining ─────────────────────────────────────────────────────
+
+#[test]
+fn split_on_separator() {
+    assert_eq!(
+        run_snippet(r#""a,b,,c" "," split!"#),
+        vec![DataValue::List(vec![s("a"), s("b"), s(""), s("c")])]
+    );
+}
+
+#[test]
+fn join_with_separator() {
+    assert_eq!(
+        run_snippet(r#"["a" "b" "c"] ", " join!"#),
+        vec![s("a, b, c")]
+    );
+    assert_eq!(run_snippet(r#"[] "-" join!"#), vec![s("")]);
+}
+
+#[test]
+fn split_then_join_round_trips() {
+    assert_eq!(
+        run_snippet(r#""x y z" " " split! " " join!"#),
+        vec![s("x y z")]
+    );
+}
+
+#[test]
+fn chars_splits_into_graphemes() {
+    assert_eq!(
+        run_snippet(r#""aé👮‍♀️" chars!"#),
+        vec![DataValue::List(vec![c("a"), c("é"), c("👮‍♀️")])]
+    );
+}
+
+// ── Searching and rewriting ───────────────────────────────────────────────────
+
+#[test]
+fn find_returns_byte_offset_or_minus_one() {
+    assert_eq!(run_snippet(r#""héllo" "l" find!"#), vec![DataValue::Int(3)]);
+    assert_eq!(
+        run_snippet(r#""hello" "z" find!"#),
+        vec![DataValue::Int(-1)]
+    );
+}
+
+#[test]
+fn find_result_can_be_used_to_slice() {
+    assert_eq!(
+        run_snippet(r#"s "key=value" local! s s "=" find! s len! slice!"#),
+        vec![s("=value")]
+    );
+}
+
+#[test]
+fn replace_all_occurrences() {
+    assert_eq!(run_snippet(r#""a-b-c" "-" "+" replace!"#), vec![s("a+b+c")]);
+}
+
+#[test]
+fn trim_and_case() {
+    assert_eq!(run_snippet("\" \\t hi \\n\" trim!"), vec![s("hi")]);
+    assert_eq!(run_snippet(r#""Straße" upper!"#), vec![s("STRASSE")]);
+    assert_eq!(run_snippet(r#""ÀB" lower!"#), vec![s("àb")]);
+}
+
+#[test]
+fn prefix_and_suffix_tests() {
+    assert_eq!(
+        run_snippet(r#""main.sid" "main" starts_with! "main.sid" ".rs" ends_with!"#),
+        vec![DataValue::Bool(true), DataValue::Bool(false)]
+    );
+}
+
+/// String operations are available at comptime.
+#[test]
+fn comptime_string_ops() {
+    assert_eq!(run_snippet(r#""ab" "cd" concat @!"#), vec![s("abcd")]);
+}
+
+// ── Error cases ───────────────────────────────────────────────────────────────
+
+#[test]
+#[should_panic(expected = "slice: 1 is not a character boundary")]
+fn slice_inside_a_character_errors() {
+    run_snippet(r#""é" 0 1 slice!"#);
+}
+
+#[test]
+#[should_panic(expected = "slice: 9 is not a character boundary")]
+fn slice_out_of_range_errors() {
+    run_snippet(r#""hello" 0 9 slice!"#);
+}
+
+#[test]
+#[should_panic(expected = "slice: start 3 is after end 1")]
+fn slice_reversed_range_errors() {
+    run_snippet(r#""hello" 3 1 slice!"#);
+}
+
+#[test]
+#[should_panic(expected = "split: separator is empty")]
+fn split_on_empty_separator_errors() {
+    run_snippet(r#""abc" "" split!"#);
+}
+
+#[test]
+#[should_panic(expected = "replace: pattern is empty")]
+fn replace_empty_pattern_errors() {
+    run_snippet(r#""abc" "" "x" replace!"#);
+}
+
+#[test]
+#[should_panic(expected = "expected")]
+fn join_non_string_element_errors() {
+    run_snippet(r#"["a" 1] "," join!"#);
+}
+
+#[test]
+#[should_panic(expected = "expected")]
+fn non_string_operand_errors() {
+    run_snippet("1 len!");
+}
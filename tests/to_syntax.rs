@@ -0,0 +1,60 @@
+/// `ToSyntax` output must parse back to the value it was produced from.
+use sid::*;
+
+fn round_trip(value: DataValue) {
+    let syntax = value.to_syntax();
+    let (parsed, _) = parse_str(&syntax).unwrap_or_else(|e| panic!("{}: {}", syntax, e));
+    assert_eq!(parsed, vec![TemplateValue::from(value)], "{}", syntax);
+}
+
+fn str_value(s: &str) -> DataValue {
+    DataValue::Str(std::ffi::CString::new(s).unwrap())
+}
+
+#[test]
+fn strings_are_escaped() {
+    assert_eq!(
+        str_value("say \"hi\"\n\\").to_syntax(),
+        r#""say \"hi\"\n\\""#
+    );
+    for s in ["", "plain", "tab\tcr\r", "quote ' \"", "bell \u{7}", "👮‍♀️ é"] {
+        round_trip(str_value(s));
+    }
+}
+
+#[test]
+fn chars_are_escaped() {
+    assert_eq!(DataValue::Char("'".to_owned()).to_syntax(), r"'\''");
+    for c in ["a", "\"", "'", "\\", "\n", "\u{1b}", "👮‍♀️"] {
+        round_trip(DataValue::Char(c.to_owned()));
+    }
+}
+
+#[test]
+fn floats_round_trip_losslessly() {
+    for f in [
+        0.0,
+        -0.0,
+        1.0,
+        -2.5,
+        0.1,
+        1.0 / 3.0,
+        6.02e23,
+        1e-300,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    ] {
+        let syntax = DataValue::Float(f).to_syntax();
+        let (parsed, _) = parse_str(&syntax).unwrap_or_else(|e| panic!("{}: {}", syntax, e));
+        match parsed[..] {
+            [TemplateValue::Literal(ProgramValue::Data(DataValue::Float(back)))] => {
+                assert_eq!(back.to_bits(), f.to_bits(), "{}", syntax)
+            }
+            _ => panic!("{} parsed to {:?}", syntax, parsed),
+        }
+    }
+    assert_eq!(DataValue::Float(f64::NAN).to_syntax(), "nan");
+    assert_eq!(DataValue::Float(1.0).to_syntax(), "1.0");
+}